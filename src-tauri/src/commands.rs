@@ -3,8 +3,40 @@
 
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
-use tauri::State;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use crate::error::{AppError, AppResult};
+use crate::file_history::{HistoryDiff, HistoryEntry};
+use crate::file_ops::{self, FileVersion, WriteExpectation};
+use crate::file_reader::{self, FileReadResult, ReadRange};
+use crate::file_tree::{self, DirectoryListing};
+use crate::file_watcher::{self, ChangeSink, FileChangeBatch, FileChangeEvent, FileChangeKind};
+use crate::git::blame::{BlameRequest, BlameResult};
+use crate::git::branch::{BranchInfo, HeadState};
+use crate::git::changelog::{Changelog, ChangelogRequest};
+use crate::git::checkpoint::{Checkpoint, RestoreResult};
+use crate::git::commit::CommitOptions;
+use crate::git::diff::{DiffRequest, DiffResult, DiffTarget};
+use crate::git::hunks::LineSelection;
+use crate::git::init::InitResult;
+use crate::git::log::{LogPage, LogQuery};
+use crate::git::merge::{ConflictFile, MergeOptions, MergeOutcome};
+use crate::git::remote::{
+    CloneOptions, CloneResult, FetchResult, PullMode, PullResult, PushRequest, PushResult,
+    RemoteAuth,
+};
+use crate::git::stash::{StashApplyResult, StashEntry, StashOptions};
+use crate::git::status::GitStatus;
+use crate::git::tag::TagInfo;
+use crate::git::worktree::WorktreeInfo;
+use crate::git::{self, SharedRepo};
+use crate::ollama_manager::OllamaStatus;
+use crate::patch::{self, PatchResult, SearchReplaceEdit};
+use crate::project_search::{self, SearchMatchBatch, SearchQuery, SearchSummary};
+use crate::trash::{self, TrashEntry};
+use crate::workspace::{self, WorkspaceError};
 use crate::AppState;
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -20,161 +52,1248 @@ pub struct GitCommitRequest {
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-pub struct GitStatus {
-    pub modified: Vec<String>,
-    pub staged: Vec<String>,
-    pub untracked: Vec<String>,
-    pub branch: String,
+pub struct NotificationRequest {
+    pub title: String,
+    pub body: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct OllamaStatus {
-    pub running: bool,
-    pub port: u16,
-    pub version: Option<String>,
-    pub models: Vec<String>,
+/// Resolve a command path against the registered workspace root, or against
+/// the named agent worktree's checkout
+async fn resolve_path(
+    state: &State<'_, AppState>,
+    worktree: Option<&str>,
+    path: &str,
+) -> AppResult<PathBuf> {
+    let workspace = state.workspace.lock().await;
+    Ok(workspace.resolve_in(worktree, path)?)
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct NotificationRequest {
-    pub title: String,
-    pub body: String,
+/// Resolve a command path, also returning the root and the root-relative path.
+/// Paths inside a worktree stay relative to the workspace root, so history and
+/// trash entries for them live with the rest of the project's.
+async fn resolve_workspace_path(
+    state: &State<'_, AppState>,
+    worktree: Option<&str>,
+    path: &str,
+) -> AppResult<(PathBuf, PathBuf, String)> {
+    let workspace = state.workspace.lock().await;
+    let resolved = workspace.resolve_in(worktree, path)?;
+    let rel = workspace.relative_path(&resolved)?;
+    let root = workspace.root()?.to_path_buf();
+    Ok((root, resolved, rel))
+}
+
+/// Register the project root that file commands are sandboxed to
+#[tauri::command]
+pub async fn set_workspace_root(
+    state: State<'_, AppState>,
+    path: String,
+) -> AppResult<String> {
+    let mut workspace = state.workspace.lock().await;
+    let root = workspace.set_root(&path)?;
+    Ok(root.to_string_lossy().into_owned())
+}
+
+/// Get the registered project root
+#[tauri::command]
+pub async fn get_workspace_root(state: State<'_, AppState>) -> AppResult<Option<String>> {
+    let workspace = state.workspace.lock().await;
+    Ok(workspace.root().ok().map(|r| r.to_string_lossy().into_owned()))
 }
 
 /// Read a file from the project
 #[tauri::command]
-pub async fn read_project_file(path: String) -> Result<String, String> {
-    fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))
+pub async fn read_project_file(
+    state: State<'_, AppState>,
+    path: String,
+    worktree: Option<String>,
+) -> AppResult<String> {
+    let resolved = resolve_path(&state, worktree.as_deref(), &path).await?;
+    fs::read_to_string(&resolved)
+        .map_err(|e| AppError::io("Failed to read file", e).with_path(&path))
+}
+
+/// Read a file with encoding and binary detection, optionally limited to a byte or line range
+#[tauri::command]
+pub async fn read_file(
+    state: State<'_, AppState>,
+    path: String,
+    range: Option<ReadRange>,
+    worktree: Option<String>,
+) -> AppResult<FileReadResult> {
+    let resolved = resolve_path(&state, worktree.as_deref(), &path).await?;
+    let range = range.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || file_reader::read_file(&resolved, &range))
+        .await
+        .map_err(|e| AppError::internal(format!("File read task failed: {}", e)))?
 }
 
-/// Write content to a file
+/// Write content to a file atomically, optionally guarding against concurrent edits
 #[tauri::command]
-pub async fn write_file(path: String, content: String) -> Result<(), String> {
-    let parent = Path::new(&path).parent();
-    if let Some(p) = parent {
-        fs::create_dir_all(p).map_err(|e| format!("Failed to create directory: {}", e))?;
+pub async fn write_file(
+    state: State<'_, AppState>,
+    path: String,
+    content: String,
+    expected_hash: Option<String>,
+    expected_mtime: Option<i64>,
+    worktree: Option<String>,
+) -> AppResult<FileVersion> {
+    let (root, resolved, rel) = resolve_workspace_path(&state, worktree.as_deref(), &path).await?;
+    let expected = WriteExpectation {
+        expected_hash,
+        expected_mtime,
+    };
+
+    if let Ok(previous) = fs::read(&resolved) {
+        if previous != content.as_bytes() {
+            // A write that will be refused must not leave a version behind
+            file_ops::check_expectation(&resolved, &expected)?;
+            // History is a safety net; failing to record it must not block the save
+            let history = state.file_history.lock().await;
+            let _ = history.snapshot(&root, &rel, &previous);
+        }
     }
-    fs::write(&path, content).map_err(|e| format!("Failed to write file: {}", e))
+
+    file_ops::atomic_write(&resolved, content.as_bytes(), &expected)
 }
 
-/// Commit changes to git
+/// Get the hash and mtime of a file for later conflict-checked writes
 #[tauri::command]
-pub async fn git_commit(message: String, files: Vec<String>) -> Result<String, String> {
-    // Use git2 for git operations
-    let repo = git2::Repository::discover(".")
-        .map_err(|e| format!("Failed to discover repo: {}", e))?;
+pub async fn file_version(
+    state: State<'_, AppState>,
+    path: String,
+    worktree: Option<String>,
+) -> AppResult<Option<FileVersion>> {
+    let resolved = resolve_path(&state, worktree.as_deref(), &path).await?;
+    file_ops::file_version(&resolved)
+}
 
-    // Stage files
-    let mut index = repo.index().map_err(|e| format!("Failed to get index: {}", e))?;
-    
-    for file in &files {
-        index.add_path(Path::new(file))
-            .map_err(|e| format!("Failed to stage file: {}", e))?;
+/// List the recorded versions of a file, newest first
+#[tauri::command]
+pub async fn file_history_list(
+    state: State<'_, AppState>,
+    path: String,
+    worktree: Option<String>,
+) -> AppResult<Vec<HistoryEntry>> {
+    let (root, _, rel) = resolve_workspace_path(&state, worktree.as_deref(), &path).await?;
+    let history = state.file_history.lock().await;
+    history.list(&root, &rel)
+}
+
+/// Diff two recorded versions of a file, or one version against the file on disk
+#[tauri::command]
+pub async fn file_history_diff(
+    state: State<'_, AppState>,
+    path: String,
+    from_version: String,
+    to_version: Option<String>,
+    worktree: Option<String>,
+) -> AppResult<HistoryDiff> {
+    let (root, _, rel) = resolve_workspace_path(&state, worktree.as_deref(), &path).await?;
+    let history = state.file_history.lock().await;
+    history.diff(&root, &rel, &from_version, to_version.as_deref())
+}
+
+/// Restore a recorded version of a file
+#[tauri::command]
+pub async fn file_history_restore(
+    state: State<'_, AppState>,
+    path: String,
+    version_id: String,
+    worktree: Option<String>,
+) -> AppResult<FileVersion> {
+    let (root, _, rel) = resolve_workspace_path(&state, worktree.as_deref(), &path).await?;
+    let history = state.file_history.lock().await;
+    history.restore(&root, &rel, &version_id)
+}
+
+/// Rename or move a file or directory, trashing an existing destination if `overwrite` is set
+#[tauri::command]
+pub async fn rename_path(
+    state: State<'_, AppState>,
+    from: String,
+    to: String,
+    overwrite: Option<bool>,
+    worktree: Option<String>,
+) -> AppResult<()> {
+    let (root, source, destination) =
+        resolve_transfer(&state, worktree.as_deref(), &from, &to).await?;
+    if overwrite.unwrap_or(false) {
+        trash_existing(&state, &root, &destination).await?;
     }
-    
-    index.write().map_err(|e| format!("Failed to write index: {}", e))?;
+    file_ops::move_path(&source, &destination)?;
 
-    // Create commit
-    let signature = repo.signature()
-        .map_err(|e| format!("Failed to get signature: {}", e))?;
-    
-    let tree_id = index.write_tree()
-        .map_err(|e| format!("Failed to write tree: {}", e))?;
-    
-    let tree = repo.find_tree(tree_id)
-        .map_err(|e| format!("Failed to find tree: {}", e))?;
-
-    let parent_commit = repo.head()
-        .and_then(|h| h.peel_to_commit())
-        .map_err(|e| format!("Failed to get parent: {}", e))?;
-
-    let commit_id = repo.commit(
-        Some("HEAD"),
-        &signature,
-        &signature,
-        &message,
-        &tree,
-        &[&parent_commit],
-    ).map_err(|e| format!("Failed to create commit: {}", e))?;
+    notify_watchers(
+        &state,
+        vec![FileChangeEvent {
+            kind: FileChangeKind::Rename,
+            path: destination.to_string_lossy().into_owned(),
+            old_path: Some(source.to_string_lossy().into_owned()),
+        }],
+    )
+    .await;
+    Ok(())
+}
+
+/// Copy a file or directory, trashing an existing destination if `overwrite` is set
+#[tauri::command]
+pub async fn copy_path(
+    state: State<'_, AppState>,
+    from: String,
+    to: String,
+    overwrite: Option<bool>,
+    worktree: Option<String>,
+) -> AppResult<()> {
+    let (root, source, destination) =
+        resolve_transfer(&state, worktree.as_deref(), &from, &to).await?;
+    if overwrite.unwrap_or(false) {
+        trash_existing(&state, &root, &destination).await?;
+    }
+    file_ops::copy_path(&source, &destination)?;
 
+    notify_watchers(
+        &state,
+        vec![FileChangeEvent {
+            kind: FileChangeKind::Create,
+            path: destination.to_string_lossy().into_owned(),
+            old_path: None,
+        }],
+    )
+    .await;
+    Ok(())
+}
+
+/// Delete a file or directory by moving it to the app trash
+#[tauri::command]
+pub async fn delete_path(
+    state: State<'_, AppState>,
+    path: String,
+    worktree: Option<String>,
+) -> AppResult<TrashEntry> {
+    let (root, target, rel) = {
+        let workspace = state.workspace.lock().await;
+        let target = workspace.resolve_entry_in(worktree.as_deref(), &path)?;
+        let rel = workspace.relative_path(&target)?;
+        let root = workspace.root()?.to_path_buf();
+        (root, target, rel)
+    };
+    let entry = trash::move_to_trash(&root, &target, &rel)?;
+
+    notify_watchers(
+        &state,
+        vec![FileChangeEvent {
+            kind: FileChangeKind::Delete,
+            path: target.to_string_lossy().into_owned(),
+            old_path: None,
+        }],
+    )
+    .await;
+    Ok(entry)
+}
+
+/// List items in the app trash, most recently deleted first
+#[tauri::command]
+pub async fn list_trash(state: State<'_, AppState>) -> AppResult<Vec<TrashEntry>> {
+    let workspace = state.workspace.lock().await;
+    trash::list(workspace.root()?)
+}
+
+/// Restore an item from the app trash to its original path, or to `path` if given
+#[tauri::command]
+pub async fn restore_from_trash(
+    state: State<'_, AppState>,
+    id: String,
+    path: Option<String>,
+    worktree: Option<String>,
+) -> AppResult<TrashEntry> {
+    let (root, target) = {
+        let workspace = state.workspace.lock().await;
+        let root = workspace.root()?.to_path_buf();
+        let target = match path {
+            Some(p) => Some(workspace.resolve_entry_in(worktree.as_deref(), &p)?),
+            None => None,
+        };
+        (root, target)
+    };
+    let (entry, restored) = trash::restore(&root, &id, target.as_deref())?;
+
+    notify_watchers(
+        &state,
+        vec![FileChangeEvent {
+            kind: FileChangeKind::Create,
+            path: restored.to_string_lossy().into_owned(),
+            old_path: None,
+        }],
+    )
+    .await;
+    Ok(entry)
+}
+
+/// Permanently delete everything in the app trash
+#[tauri::command]
+pub async fn empty_trash(state: State<'_, AppState>) -> AppResult<usize> {
+    let workspace = state.workspace.lock().await;
+    trash::empty(workspace.root()?)
+}
+
+/// Resolve both ends of a move or copy inside the workspace
+async fn resolve_transfer(
+    state: &State<'_, AppState>,
+    worktree: Option<&str>,
+    from: &str,
+    to: &str,
+) -> AppResult<(PathBuf, PathBuf, PathBuf)> {
+    let workspace = state.workspace.lock().await;
+    let source = workspace.resolve_entry_in(worktree, from)?;
+    let destination = workspace.resolve_entry_in(worktree, to)?;
+    let root = workspace.root()?.to_path_buf();
+    Ok((root, source, destination))
+}
+
+/// Move whatever currently sits at `path` into the trash, if anything
+async fn trash_existing(state: &State<'_, AppState>, root: &Path, path: &Path) -> AppResult<()> {
+    if fs::symlink_metadata(path).is_ok() {
+        // Recorded relative to the workspace root, like `delete_path`, so a
+        // path inside a worktree is restored there
+        let rel = state.workspace.lock().await.relative_path(path)?;
+        trash::move_to_trash(root, path, &rel)?;
+        notify_watchers(
+            state,
+            vec![FileChangeEvent {
+                kind: FileChangeKind::Delete,
+                path: path.to_string_lossy().into_owned(),
+                old_path: None,
+            }],
+        )
+        .await;
+    }
+    Ok(())
+}
+
+async fn notify_watchers(state: &State<'_, AppState>, events: Vec<FileChangeEvent>) {
+    let watcher = state.file_watcher.lock().await;
+    watcher.notify_changes(&events);
+}
+
+/// Apply a multi-file unified diff or search/replace edits, all-or-nothing
+#[tauri::command]
+pub async fn apply_patch(
+    state: State<'_, AppState>,
+    diff: Option<String>,
+    edits: Option<Vec<SearchReplaceEdit>>,
+    dry_run: Option<bool>,
+    worktree: Option<String>,
+) -> AppResult<PatchResult> {
+    let root = {
+        let workspace = state.workspace.lock().await;
+        workspace.scope_root(worktree.as_deref())?
+    };
+    let dry_run = dry_run.unwrap_or(false);
+
+    tauri::async_runtime::spawn_blocking(move || match (diff, edits) {
+        (Some(diff), None) => patch::apply_unified_diff(&root, &diff, dry_run),
+        (None, Some(edits)) => patch::apply_search_replace(&root, &edits, dry_run),
+        _ => Err(AppError::invalid_input(
+            "Provide either a unified diff or a list of edits",
+        )),
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Patch task failed: {}", e)))?
+}
+
+/// List one level of a project directory, paginated and gitignore-aware
+#[tauri::command]
+pub async fn list_directory(
+    state: State<'_, AppState>,
+    path: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    worktree: Option<String>,
+) -> AppResult<DirectoryListing> {
+    let (root, dir) = {
+        let workspace = state.workspace.lock().await;
+        let root = workspace.scope_root(worktree.as_deref())?;
+        let dir = workspace.resolve_in(worktree.as_deref(), path.as_deref().unwrap_or("."))?;
+        (root, dir)
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        file_tree::list_directory(
+            &root,
+            &dir,
+            offset.unwrap_or(0),
+            limit.unwrap_or(file_tree::DEFAULT_PAGE_SIZE),
+        )
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Directory listing task failed: {}", e)))?
+}
+
+/// Search the project, streaming `search-matches` events and a final `search-complete`
+#[tauri::command]
+pub async fn search_project(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    query: SearchQuery,
+    worktree: Option<String>,
+) -> AppResult<String> {
+    let root = {
+        let workspace = state.workspace.lock().await;
+        workspace.scope_root(worktree.as_deref())?
+    };
+    let (search_id, cancel) = state.search_manager.lock().await.start();
+    let manager = state.search_manager.clone();
+    let id = search_id.clone();
+
+    tauri::async_runtime::spawn(async move {
+        let batch_app = app.clone();
+        let batch_id = id.clone();
+        let result = tauri::async_runtime::spawn_blocking(move || {
+            project_search::search(&root, &query, &cancel, |matches| {
+                let _ = batch_app.emit_all(
+                    "search-matches",
+                    SearchMatchBatch {
+                        search_id: batch_id.clone(),
+                        matches,
+                    },
+                );
+            })
+        })
+        .await;
+
+        manager.lock().await.finish(&id);
+
+        let summary = match result {
+            Ok(Ok(summary)) => SearchSummary {
+                search_id: id,
+                ..summary
+            },
+            Ok(Err(e)) => SearchSummary {
+                search_id: id,
+                error: Some(e),
+                ..Default::default()
+            },
+            Err(e) => SearchSummary {
+                search_id: id,
+                error: Some(AppError::internal(format!("Search task failed: {}", e))),
+                ..Default::default()
+            },
+        };
+        let _ = app.emit_all("search-complete", summary);
+    });
+
+    Ok(search_id)
+}
+
+/// Cancel a running project search
+#[tauri::command]
+pub async fn cancel_search(state: State<'_, AppState>, search_id: String) -> AppResult<()> {
+    let mut manager = state.search_manager.lock().await;
+    manager.cancel(&search_id)
+}
+
+/// Open (or reuse) the repository at `repo_path`, defaulting to the workspace root
+async fn workspace_repo(
+    state: &State<'_, AppState>,
+    repo_path: Option<&str>,
+) -> AppResult<SharedRepo> {
+    let (root, path) = {
+        let workspace = state.workspace.lock().await;
+        let root = workspace.root()?.to_path_buf();
+        let path = workspace.resolve(repo_path.unwrap_or("."))?;
+        (root, path)
+    };
+    let mut repos = state.repos.lock().await;
+    repos.open(&path, Some(&root))
+}
+
+/// Run a git operation against a shared repository on the blocking pool
+async fn run_git<T, F>(repo: SharedRepo, op: F) -> AppResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&git2::Repository) -> AppResult<T> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(move || {
+        let repo = repo
+            .lock()
+            .map_err(|_| AppError::internal("Repository handle is poisoned"))?;
+        op(&repo)
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Git task failed: {}", e)))?
+}
+
+/// Like `run_git`, for the operations git2 only exposes on a mutable repository
+async fn run_git_mut<T, F>(repo: SharedRepo, op: F) -> AppResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut git2::Repository) -> AppResult<T> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut repo = repo
+            .lock()
+            .map_err(|_| AppError::internal("Repository handle is poisoned"))?;
+        op(&mut repo)
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Git task failed: {}", e)))?
+}
+
+/// Like `run_git`, but on a fresh handle to the same repository, so a long
+/// network transfer does not hold up every other git command on it
+async fn run_git_detached<T, F>(repo: SharedRepo, op: F) -> AppResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&git2::Repository) -> AppResult<T> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(move || {
+        let git_dir = repo
+            .lock()
+            .map_err(|_| AppError::internal("Repository handle is poisoned"))?
+            .path()
+            .to_path_buf();
+        let repo = git2::Repository::open(&git_dir)
+            .map_err(|e| AppError::git("Failed to open repository", e).with_path(&git_dir))?;
+        op(&repo)
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Git task failed: {}", e)))?
+}
+
+/// Workspace-relative path, as file history records it, of a file named
+/// relative to `repo`'s working tree
+fn history_path(repo: &git2::Repository, root: &Path, path: &str) -> AppResult<String> {
+    let full = repo
+        .workdir()
+        .ok_or_else(|| AppError::invalid_input("Bare repositories have no working tree"))?
+        .join(path);
+    let rel = full
+        .strip_prefix(root)
+        .map_err(|_| WorkspaceError::OutsideRoot(full.display().to_string()))?;
+    Ok(workspace::to_slash_path(rel))
+}
+
+/// Commit changes to git, staging the listed files (including deletions) first
+#[tauri::command]
+pub async fn git_commit(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    message: String,
+    files: Vec<String>,
+    options: Option<CommitOptions>,
+) -> AppResult<String> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let options = options.unwrap_or_default();
+    let commit_id = run_git(repo, move |repo| {
+        git::commit::commit(repo, &message, &files, &options)
+    })
+    .await?;
     Ok(commit_id.to_string())
 }
 
-/// Get git status
+/// Get git status: per-file index and worktree states, conflicts, upstream and submodules
 #[tauri::command]
-pub async fn git_status() -> Result<GitStatus, String> {
-    let repo = git2::Repository::discover(".")
-        .map_err(|e| format!("Failed to discover repo: {}", e))?;
+pub async fn git_status(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+) -> AppResult<GitStatus> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, git::status::status).await
+}
 
-    let statuses = repo.statuses(None)
-        .map_err(|e| format!("Failed to get statuses: {}", e))?;
+/// Diff the working tree, the index or two revisions as structured files, hunks and lines
+#[tauri::command]
+pub async fn git_diff(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    target: DiffTarget,
+    request: Option<DiffRequest>,
+) -> AppResult<DiffResult> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let request = request.unwrap_or_default();
+    run_git(repo, move |repo| git::diff::diff(repo, &target, &request)).await
+}
 
-    let mut modified = Vec::new();
-    let mut staged = Vec::new();
-    let mut untracked = Vec::new();
+/// Stage selected hunks or lines of a file's working tree changes
+#[tauri::command]
+pub async fn git_stage_lines(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    path: String,
+    selection: LineSelection,
+) -> AppResult<()> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, move |repo| {
+        let path = git::workdir_path(repo, &path)?;
+        git::hunks::stage(repo, &path, &selection)
+    })
+    .await
+}
 
-    for status in statuses.iter() {
-        let path = status.path().unwrap_or("").to_string();
-        let status_bits = status.status();
+/// Unstage selected hunks or lines of a file's staged changes
+#[tauri::command]
+pub async fn git_unstage_lines(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    path: String,
+    selection: LineSelection,
+) -> AppResult<()> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, move |repo| {
+        let path = git::workdir_path(repo, &path)?;
+        git::hunks::unstage(repo, &path, &selection)
+    })
+    .await
+}
 
-        if status_bits.is_index_new() || status_bits.is_index_modified() {
-            staged.push(path.clone());
-        }
-        if status_bits.is_wt_modified() {
-            modified.push(path.clone());
-        }
-        if status_bits.is_wt_new() {
-            untracked.push(path);
-        }
+/// Discard selected hunks or lines from the working tree, keeping the old content in file history
+#[tauri::command]
+pub async fn git_discard_lines(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    path: String,
+    selection: LineSelection,
+) -> AppResult<()> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let root = state.workspace.lock().await.root()?.to_path_buf();
+    let history = state.file_history.clone();
+    run_git(repo, move |repo| {
+        let path = git::workdir_path(repo, &path)?;
+        let rel = history_path(repo, &root, &path)?;
+        // The old content goes into file history before the file is touched
+        git::hunks::discard(repo, &path, &selection, |previous| {
+            history
+                .blocking_lock()
+                .snapshot(&root, &rel, previous)
+                .map(|_| ())
+        })
+    })
+    .await
+}
+
+/// One page of history, newest first, optionally filtered by path, author and date
+#[tauri::command]
+pub async fn git_log(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    query: Option<LogQuery>,
+) -> AppResult<LogPage> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let query = query.unwrap_or_default();
+    run_git(repo, move |repo| git::log::log(repo, &query)).await
+}
+
+/// Attribute each line of a file (relative to the repository root) to the commit that last changed it
+#[tauri::command]
+pub async fn git_blame(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    path: String,
+    request: Option<BlameRequest>,
+) -> AppResult<BlameResult> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let request = request.unwrap_or_default();
+    run_git(repo, move |repo| {
+        let path = git::workdir_path(repo, &path)?;
+        git::blame::blame(repo, &path, &request)
+    })
+    .await
+}
+
+/// Stash local changes, optionally including untracked files
+#[tauri::command]
+pub async fn git_stash_save(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    options: Option<StashOptions>,
+) -> AppResult<StashEntry> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let options = options.unwrap_or_default();
+    run_git_mut(repo, move |repo| git::stash::save(repo, &options)).await
+}
+
+/// List stashes, most recent first
+#[tauri::command]
+pub async fn git_stash_list(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+) -> AppResult<Vec<StashEntry>> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git_mut(repo, git::stash::list).await
+}
+
+/// Diff the changes held in a stash
+#[tauri::command]
+pub async fn git_stash_show(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    index: usize,
+    request: Option<DiffRequest>,
+) -> AppResult<DiffResult> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let request = request.unwrap_or_default();
+    run_git_mut(repo, move |repo| git::stash::show(repo, index, &request)).await
+}
+
+/// Apply a stash, keeping it in the list
+#[tauri::command]
+pub async fn git_stash_apply(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    index: usize,
+    reinstate_index: Option<bool>,
+) -> AppResult<StashApplyResult> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let reinstate_index = reinstate_index.unwrap_or(false);
+    run_git_mut(repo, move |repo| git::stash::apply(repo, index, reinstate_index)).await
+}
+
+/// Apply a stash and drop it if it applied without conflicts
+#[tauri::command]
+pub async fn git_stash_pop(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    index: usize,
+    reinstate_index: Option<bool>,
+) -> AppResult<StashApplyResult> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let reinstate_index = reinstate_index.unwrap_or(false);
+    run_git_mut(repo, move |repo| git::stash::pop(repo, index, reinstate_index)).await
+}
+
+/// Remove a stash without applying it
+#[tauri::command]
+pub async fn git_stash_drop(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    index: usize,
+) -> AppResult<()> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git_mut(repo, move |repo| git::stash::drop(repo, index)).await
+}
+
+/// Merge a branch into the current one, fast-forwarding when possible
+#[tauri::command]
+pub async fn git_merge(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    branch: String,
+    options: Option<MergeOptions>,
+) -> AppResult<MergeOutcome> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let options = options.unwrap_or_default();
+    run_git(repo, move |repo| git::merge::merge(repo, &branch, &options)).await
+}
+
+/// Ours/theirs/base content and conflict regions for each file in the merge in progress
+#[tauri::command]
+pub async fn git_merge_conflicts(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+) -> AppResult<Vec<ConflictFile>> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, git::merge::conflicts).await
+}
+
+/// Mark a conflicted file resolved, optionally writing its final content; returns paths still in conflict
+#[tauri::command]
+pub async fn git_merge_resolve(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    path: String,
+    content: Option<String>,
+) -> AppResult<Vec<String>> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let root = state.workspace.lock().await.root()?.to_path_buf();
+    let history = state.file_history.clone();
+    run_git(repo, move |repo| {
+        let rel = history_path(repo, &root, &git::workdir_path(repo, &path)?)?;
+        // The conflicted version goes into file history before it is replaced
+        git::merge::resolve(repo, &path, content.as_deref(), |previous| {
+            history
+                .blocking_lock()
+                .snapshot(&root, &rel, previous)
+                .map(|_| ())
+        })
+    })
+    .await
+}
+
+/// Commit the merge in progress once all conflicts are resolved
+#[tauri::command]
+pub async fn git_merge_conclude(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    message: Option<String>,
+) -> AppResult<String> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let commit_id = run_git(repo, move |repo| {
+        git::merge::conclude(repo, message.as_deref())
+    })
+    .await?;
+    Ok(commit_id.to_string())
+}
+
+/// Abandon the merge in progress and restore HEAD
+#[tauri::command]
+pub async fn git_merge_abort(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+) -> AppResult<()> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, git::merge::abort).await
+}
+
+/// Create an isolated worktree and branch for an agent run under `.nova/worktrees/<name>`
+#[tauri::command]
+pub async fn git_worktree_create(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    name: String,
+    branch: Option<String>,
+    start_point: Option<String>,
+) -> AppResult<WorktreeInfo> {
+    let root = {
+        let workspace = state.workspace.lock().await;
+        workspace.root()?.to_path_buf()
+    };
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, move |repo| {
+        git::worktree::create(repo, &root, &name, branch.as_deref(), start_point.as_deref())
+    })
+    .await
+}
+
+/// List the repository's worktrees
+#[tauri::command]
+pub async fn git_worktree_list(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+) -> AppResult<Vec<WorktreeInfo>> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, git::worktree::list).await
+}
+
+/// Delete a worktree's checkout, keeping its branch
+#[tauri::command]
+pub async fn git_worktree_remove(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    name: String,
+    force: Option<bool>,
+) -> AppResult<()> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let force = force.unwrap_or(false);
+    run_git(repo, move |repo| git::worktree::remove(repo, &name, force)).await
+}
+
+/// Clean up worktrees whose directories were deleted; returns their names
+#[tauri::command]
+pub async fn git_worktree_prune(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+) -> AppResult<Vec<String>> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, git::worktree::prune).await
+}
+
+/// Merge a finished worktree's branch into the main checkout
+#[tauri::command]
+pub async fn git_worktree_merge(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    name: String,
+    into: Option<String>,
+    options: Option<MergeOptions>,
+) -> AppResult<MergeOutcome> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let options = options.unwrap_or_default();
+    run_git(repo, move |repo| {
+        git::worktree::merge_back(repo, &name, into.as_deref(), &options)
+    })
+    .await
+}
+
+/// Snapshot the whole working tree, untracked files included, under
+/// `refs/nova/checkpoints/<run_id>` without touching HEAD, the index or the stash
+#[tauri::command]
+pub async fn git_checkpoint_create(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    run_id: String,
+    message: Option<String>,
+) -> AppResult<Checkpoint> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, move |repo| {
+        git::checkpoint::create(repo, &run_id, message.as_deref())
+    })
+    .await
+}
+
+/// List checkpoints, newest first
+#[tauri::command]
+pub async fn git_checkpoint_list(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+) -> AppResult<Vec<Checkpoint>> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, git::checkpoint::list).await
+}
+
+/// Diff a checkpoint against the current working tree
+#[tauri::command]
+pub async fn git_checkpoint_diff(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    run_id: String,
+    request: Option<DiffRequest>,
+) -> AppResult<DiffResult> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let request = request.unwrap_or_default();
+    run_git(repo, move |repo| {
+        git::checkpoint::diff_to_workdir(repo, &run_id, &request)
+    })
+    .await
+}
+
+/// Restore files (all of them when `paths` is empty) from a checkpoint, first
+/// checkpointing the working tree so the restore can be undone
+#[tauri::command]
+pub async fn git_checkpoint_restore(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    run_id: String,
+    paths: Option<Vec<String>>,
+) -> AppResult<RestoreResult> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let paths = paths.unwrap_or_default();
+    run_git(repo, move |repo| {
+        git::checkpoint::restore(repo, &run_id, &paths)
+    })
+    .await
+}
+
+/// Delete a checkpoint ref
+#[tauri::command]
+pub async fn git_checkpoint_delete(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    run_id: String,
+) -> AppResult<()> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, move |repo| git::checkpoint::delete(repo, &run_id)).await
+}
+
+/// Initialise a repository at the workspace root (or `path` within it) on
+/// `initial_branch`, with a `.gitignore` for the detected project type
+#[tauri::command]
+pub async fn git_init(
+    state: State<'_, AppState>,
+    path: Option<String>,
+    initial_branch: Option<String>,
+    gitignore: Option<bool>,
+) -> AppResult<InitResult> {
+    let dir = resolve_path(&state, None, path.as_deref().unwrap_or(".")).await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        git::init::init(&dir, initial_branch.as_deref(), gitignore.unwrap_or(true))
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Git task failed: {}", e)))?
+}
+
+/// Clone `url` into the absolute path `destination` for a new project,
+/// emitting `git-remote-progress` events while transferring and checking out
+#[tauri::command]
+pub async fn git_clone(
+    app: AppHandle,
+    url: String,
+    destination: String,
+    options: Option<CloneOptions>,
+    auth: Option<RemoteAuth>,
+) -> AppResult<CloneResult> {
+    let destination = PathBuf::from(destination);
+    if !destination.is_absolute() {
+        return Err(AppError::invalid_input(
+            "Clone destination must be an absolute path",
+        ));
     }
+    let options = options.unwrap_or_default();
+    let auth = auth.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        git::remote::clone(&url, &destination, &options, &auth, &mut |progress| {
+            let _ = app.emit_all("git-remote-progress", progress);
+        })
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Git task failed: {}", e)))?
+}
+
+/// Fetch from a remote, emitting `git-remote-progress` events while transferring
+#[tauri::command]
+pub async fn git_fetch(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    remote: Option<String>,
+    prune: Option<bool>,
+    auth: Option<RemoteAuth>,
+) -> AppResult<FetchResult> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let auth = auth.unwrap_or_default();
+    run_git_detached(repo, move |repo| {
+        git::remote::fetch(
+            repo,
+            remote.as_deref(),
+            prune.unwrap_or(false),
+            &auth,
+            &mut |progress| {
+                let _ = app.emit_all("git-remote-progress", progress);
+            },
+        )
+    })
+    .await
+}
+
+/// Fetch and fast-forward or rebase the current branch onto its upstream,
+/// emitting `git-remote-progress` events while transferring
+#[tauri::command]
+pub async fn git_pull(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    remote: Option<String>,
+    mode: Option<PullMode>,
+    auth: Option<RemoteAuth>,
+) -> AppResult<PullResult> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let auth = auth.unwrap_or_default();
+    let fetched = run_git_detached(repo.clone(), move |repo| {
+        git::remote::pull_fetch(repo, remote.as_deref(), &auth, &mut |progress| {
+            let _ = app.emit_all("git-remote-progress", progress);
+        })
+    })
+    .await?;
+    // Only the fast-forward or rebase needs the shared handle
+    run_git(repo, move |repo| {
+        git::remote::pull_integrate(repo, fetched, mode.unwrap_or_default())
+    })
+    .await
+}
+
+/// Push a branch (default: the current one), emitting `git-remote-progress` events
+#[tauri::command]
+pub async fn git_push(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    request: Option<PushRequest>,
+    auth: Option<RemoteAuth>,
+) -> AppResult<PushResult> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let request = request.unwrap_or_default();
+    let auth = auth.unwrap_or_default();
+    run_git_detached(repo, move |repo| {
+        git::remote::push(
+            repo,
+            &request,
+            &auth,
+            &mut |progress| {
+                let _ = app.emit_all("git-remote-progress", progress);
+            },
+        )
+    })
+    .await
+}
+
+/// List tags matching an optional glob, newest first
+#[tauri::command]
+pub async fn git_tags(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    pattern: Option<String>,
+) -> AppResult<Vec<TagInfo>> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, move |repo| git::tag::list(repo, pattern.as_deref())).await
+}
 
-    let branch = repo.head()
-        .and_then(|h| h.shorthand().map(|s| s.to_string()))
-        .unwrap_or_else(|_| "main".to_string());
+/// Tag `target` (default HEAD); a `message` makes the tag annotated
+#[tauri::command]
+pub async fn git_create_tag(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    name: String,
+    target: Option<String>,
+    message: Option<String>,
+    force: Option<bool>,
+) -> AppResult<TagInfo> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, move |repo| {
+        git::tag::create(
+            repo,
+            &name,
+            target.as_deref(),
+            message.as_deref(),
+            force.unwrap_or(false),
+        )
+    })
+    .await
+}
+
+/// Delete a tag
+#[tauri::command]
+pub async fn git_delete_tag(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    name: String,
+) -> AppResult<()> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, move |repo| git::tag::delete(repo, &name)).await
+}
+
+/// Release notes between two refs, grouped by conventional-commit type
+#[tauri::command]
+pub async fn git_changelog(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    request: Option<ChangelogRequest>,
+) -> AppResult<Changelog> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    let request = request.unwrap_or_default();
+    run_git(repo, move |repo| git::changelog::changelog(repo, &request)).await
+}
+
+/// List local and remote-tracking branches
+#[tauri::command]
+pub async fn git_branches(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+) -> AppResult<Vec<BranchInfo>> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, git::branch::list_branches).await
+}
 
-    Ok(GitStatus {
-        modified,
-        staged,
-        untracked,
-        branch,
+/// Create a branch at `start_point` (default HEAD), optionally checking it out
+#[tauri::command]
+pub async fn git_create_branch(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    name: String,
+    start_point: Option<String>,
+    checkout: Option<bool>,
+) -> AppResult<BranchInfo> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, move |repo| {
+        git::branch::create_branch(
+            repo,
+            &name,
+            start_point.as_deref(),
+            checkout.unwrap_or(false),
+        )
     })
+    .await
+}
+
+/// Rename a local branch
+#[tauri::command]
+pub async fn git_rename_branch(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    old_name: String,
+    new_name: String,
+    force: Option<bool>,
+) -> AppResult<BranchInfo> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, move |repo| {
+        git::branch::rename_branch(repo, &old_name, &new_name, force.unwrap_or(false))
+    })
+    .await
+}
+
+/// Delete a local branch; unmerged branches need `force`
+#[tauri::command]
+pub async fn git_delete_branch(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    name: String,
+    force: Option<bool>,
+) -> AppResult<()> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, move |repo| {
+        git::branch::delete_branch(repo, &name, force.unwrap_or(false))
+    })
+    .await
+}
+
+/// Check out a branch, refusing on uncommitted changes unless `force` is set
+#[tauri::command]
+pub async fn git_checkout_branch(
+    state: State<'_, AppState>,
+    repo_path: Option<String>,
+    name: String,
+    force: Option<bool>,
+) -> AppResult<HeadState> {
+    let repo = workspace_repo(&state, repo_path.as_deref()).await?;
+    run_git(repo, move |repo| {
+        git::branch::checkout_branch(repo, &name, force.unwrap_or(false))
+    })
+    .await
 }
 
 /// Start Ollama service
 #[tauri::command]
-pub async fn spawn_ollama(state: State<'_, AppState>) -> Result<(), String> {
+pub async fn spawn_ollama(state: State<'_, AppState>) -> AppResult<()> {
     let manager = state.ollama_manager.lock().await;
     manager.start().await
 }
 
 /// Stop Ollama service
 #[tauri::command]
-pub async fn stop_ollama(state: State<'_, AppState>) -> Result<(), String> {
+pub async fn stop_ollama(state: State<'_, AppState>) -> AppResult<()> {
     let manager = state.ollama_manager.lock().await;
     manager.stop().await
 }
 
 /// Get Ollama status
 #[tauri::command]
-pub async fn ollama_status(state: State<'_, AppState>) -> Result<OllamaStatus, String> {
+pub async fn ollama_status(state: State<'_, AppState>) -> AppResult<OllamaStatus> {
     let manager = state.ollama_manager.lock().await;
     manager.check_status().await
 }
 
-/// Watch project directory for changes
+/// Watch project directory for changes, emitting debounced `project-file-changed` events
 #[tauri::command]
-pub async fn watch_project(path: String) -> Result<(), String> {
-    // In real implementation, this would set up file watching
-    // For now, just validate the path exists
-    if !Path::new(&path).exists() {
-        return Err(format!("Path does not exist: {}", path));
-    }
-    Ok(())
+pub async fn watch_project(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    debounce_ms: Option<u64>,
+    worktree: Option<String>,
+) -> AppResult<String> {
+    let (project_root, resolved) = {
+        let workspace = state.workspace.lock().await;
+        let project_root = workspace.scope_root(worktree.as_deref())?;
+        let resolved = workspace.resolve_in(worktree.as_deref(), &path)?;
+        (project_root, resolved)
+    };
+    let debounce = Duration::from_millis(debounce_ms.unwrap_or(file_watcher::DEFAULT_DEBOUNCE_MS));
+    let sink: ChangeSink = Arc::new(move |batch: FileChangeBatch| {
+        let _ = app.emit_all("project-file-changed", batch);
+    });
+
+    let mut watcher = state.file_watcher.lock().await;
+    watcher.watch(&resolved, &project_root, debounce, sink)
+}
+
+/// Stop a project watch started with `watch_project`
+#[tauri::command]
+pub async fn unwatch_project(state: State<'_, AppState>, watch_id: String) -> AppResult<()> {
+    let mut watcher = state.file_watcher.lock().await;
+    watcher.unwatch(&watch_id)
 }
 
 /// Send system notification
 #[tauri::command]
-pub async fn send_notification(title: String, body: String) -> Result<(), String> {
+pub async fn send_notification(title: String, body: String) -> AppResult<()> {
     #[cfg(target_os = "macos")]
     {
         // Use macOS notification center
@@ -206,10 +1325,18 @@ mod tests {
             modified: vec!["file1.rs".to_string()],
             staged: vec!["file2.rs".to_string()],
             untracked: vec!["file3.rs".to_string()],
-            branch: "main".to_string(),
+            branch: Some("main".to_string()),
+            head: HeadState::Branch {
+                name: "main".to_string(),
+                commit: "abc123".to_string(),
+            },
+            upstream: None,
+            files: Vec::new(),
+            conflicted: Vec::new(),
+            submodules: Vec::new(),
         };
         
-        assert_eq!(status.branch, "main");
+        assert_eq!(status.branch.as_deref(), Some("main"));
         assert_eq!(status.modified.len(), 1);
     }
 
@@ -6,6 +6,8 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
+use crate::error::{AppError, AppResult};
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SyncQueueItem {
     pub id: String,
@@ -38,7 +40,7 @@ impl ElectricSync {
     }
 
     /// Add item to sync queue
-    pub fn enqueue(&mut self, item: SyncQueueItem) -> Result<(), String> {
+    pub fn enqueue(&mut self, item: SyncQueueItem) -> AppResult<()> {
         self.queue.push(item);
         self.persist_queue()
     }
@@ -49,19 +51,22 @@ impl ElectricSync {
     }
 
     /// Flush queue to remote
-    pub async fn flush(&mut self) -> Result<FlushResult, String> {
+    pub async fn flush(&mut self) -> AppResult<FlushResult> {
         let mut processed = 0;
         let mut failed = 0;
         let mut conflicts = Vec::new();
 
-        for item in self.queue.iter_mut().filter(|i| !i.synced) {
-            match self.sync_item(item).await {
+        for i in 0..self.queue.len() {
+            if self.queue[i].synced {
+                continue;
+            }
+            match self.sync_item(&self.queue[i]).await {
                 Ok(true) => {
-                    item.synced = true;
+                    self.queue[i].synced = true;
                     processed += 1;
                 }
                 Ok(false) => {
-                    conflicts.push(item.id.clone());
+                    conflicts.push(self.queue[i].id.clone());
                 }
                 Err(_) => {
                     failed += 1;
@@ -85,7 +90,7 @@ impl ElectricSync {
         &mut self,
         item_id: &str,
         resolution: ConflictResolution,
-    ) -> Result<(), String> {
+    ) -> AppResult<()> {
         if let Some(item) = self.queue.iter_mut().find(|i| i.id == item_id) {
             match resolution.strategy.as_str() {
                 "last-write-wins" => {
@@ -98,49 +103,52 @@ impl ElectricSync {
                     }
                 }
                 _ => {
-                    return Err("Unknown conflict strategy".to_string());
+                    return Err(AppError::invalid_input(format!(
+                        "Unknown conflict strategy: {}",
+                        resolution.strategy
+                    )));
                 }
             }
             self.persist_queue()
         } else {
-            Err("Item not found".to_string())
+            Err(AppError::not_found(format!("Sync item not found: {}", item_id)))
         }
     }
 
     /// Load queue from disk
-    pub fn load_queue(&mut self) -> Result<(), String> {
+    pub fn load_queue(&mut self) -> AppResult<()> {
         if !Path::new(&self.queue_path).exists() {
             self.queue = Vec::new();
             return Ok(());
         }
 
         let content = fs::read_to_string(&self.queue_path)
-            .map_err(|e| format!("Failed to read queue: {}", e))?;
+            .map_err(|e| AppError::io("Failed to read queue", e).with_path(&self.queue_path))?;
         
         self.queue = serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse queue: {}", e))?;
+            .map_err(|e| AppError::internal(format!("Failed to parse queue: {}", e)))?;
         
         Ok(())
     }
 
     /// Save queue to disk
-    fn persist_queue(&self) -> Result<(), String> {
+    fn persist_queue(&self) -> AppResult<()> {
         let parent = Path::new(&self.queue_path).parent();
         if let Some(p) = parent {
-            fs::create_dir_all(p).map_err(|e| format!("Failed to create dir: {}", e))?;
+            fs::create_dir_all(p).map_err(|e| AppError::io("Failed to create dir", e).with_path(p))?;
         }
 
         let content = serde_json::to_string_pretty(&self.queue)
-            .map_err(|e| format!("Failed to serialize queue: {}", e))?;
+            .map_err(|e| AppError::internal(format!("Failed to serialize queue: {}", e)))?;
         
         fs::write(&self.queue_path, content)
-            .map_err(|e| format!("Failed to write queue: {}", e))?;
+            .map_err(|e| AppError::io("Failed to write queue", e).with_path(&self.queue_path))?;
         
         Ok(())
     }
 
     /// Sync a single item (mock implementation)
-    async fn sync_item(&self, item: &SyncQueueItem) -> Result<bool, String> {
+    async fn sync_item(&self, _item: &SyncQueueItem) -> AppResult<bool> {
         // In real implementation, this would sync with remote server
         // For now, simulate success
         Ok(true)
@@ -236,4 +244,18 @@ mod tests {
         assert_eq!(stats.total, 1);
         assert_eq!(stats.pending, 1);
     }
+
+    #[test]
+    fn test_resolve_conflict_errors() {
+        let mut sync = ElectricSync::new();
+        let resolution = ConflictResolution {
+            strategy: "merge".to_string(),
+            local_version: "1".to_string(),
+            remote_version: "2".to_string(),
+            resolved_content: None,
+        };
+
+        let err = sync.resolve_conflict("missing", resolution).unwrap_err();
+        assert_eq!(err.code, crate::error::ErrorCode::NotFound);
+    }
 }
@@ -0,0 +1,231 @@
+// App Error — R20-02
+// Structured error returned by every command: stable code, message and optional details
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use crate::workspace::WorkspaceError;
+
+pub type AppResult<T> = Result<T, AppError>;
+
+/// Machine-readable error class; serialized as `snake_case` and stable across releases
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    NoWorkspace,
+    InvalidWorkspace,
+    OutsideWorkspace,
+    ProtectedPath,
+    NotFound,
+    PermissionDenied,
+    AlreadyExists,
+    InvalidInput,
+    Conflict,
+    Io,
+    RepoNotFound,
+    Git,
+    /// A remote rejected the credentials, or none were available
+    AuthFailed,
+    Http,
+    ServiceUnavailable,
+    Internal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ErrorDetails {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// libgit2 error class, e.g. `Index` or `Reference`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_class: Option<String>,
+    /// libgit2 error code, e.g. `NotFound` or `Conflict`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<ErrorDetails>,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn already_exists(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::AlreadyExists, message)
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Conflict, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+
+    /// Classify an I/O error by its kind; `context` prefixes the message
+    pub fn io(context: &str, err: io::Error) -> Self {
+        let code = match err.kind() {
+            io::ErrorKind::NotFound => ErrorCode::NotFound,
+            io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+            io::ErrorKind::AlreadyExists => ErrorCode::AlreadyExists,
+            io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData => ErrorCode::InvalidInput,
+            _ => ErrorCode::Io,
+        };
+        Self::new(code, format!("{}: {}", context, err))
+    }
+
+    /// Classify a libgit2 error, keeping its class and code in the details
+    pub fn git(context: &str, err: git2::Error) -> Self {
+        let code = match (err.class(), err.code()) {
+            (git2::ErrorClass::Repository, git2::ErrorCode::NotFound) => ErrorCode::RepoNotFound,
+            (_, git2::ErrorCode::NotFound) => ErrorCode::NotFound,
+            (_, git2::ErrorCode::Exists) => ErrorCode::AlreadyExists,
+            (_, git2::ErrorCode::InvalidSpec) | (_, git2::ErrorCode::Invalid) => {
+                ErrorCode::InvalidInput
+            }
+            (_, git2::ErrorCode::Auth) => ErrorCode::AuthFailed,
+            (
+                _,
+                git2::ErrorCode::Conflict
+                | git2::ErrorCode::MergeConflict
+                | git2::ErrorCode::NotFastForward
+                | git2::ErrorCode::Modified
+                | git2::ErrorCode::Uncommitted
+                | git2::ErrorCode::Locked,
+            ) => ErrorCode::Conflict,
+            _ => ErrorCode::Git,
+        };
+        let mut error = Self::new(code, format!("{}: {}", context, err.message()));
+        let details = error.details_mut();
+        details.git_class = Some(format!("{:?}", err.class()));
+        details.git_code = Some(format!("{:?}", err.code()));
+        error
+    }
+
+    /// Classify an HTTP client error, keeping the status code when there is one
+    pub fn http(context: &str, err: reqwest::Error) -> Self {
+        let status = err.status().map(|s| s.as_u16());
+        let code = if err.is_connect() || err.is_timeout() {
+            ErrorCode::ServiceUnavailable
+        } else {
+            ErrorCode::Http
+        };
+        let mut error = Self::new(code, format!("{}: {}", context, err));
+        error.details_mut().http_status = status;
+        error
+    }
+
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.details_mut().path = Some(path.as_ref().to_string_lossy().into_owned());
+        self
+    }
+
+    fn details_mut(&mut self) -> &mut ErrorDetails {
+        self.details.get_or_insert_with(ErrorDetails::default)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<WorkspaceError> for AppError {
+    fn from(err: WorkspaceError) -> Self {
+        let message = err.to_string();
+        match err {
+            WorkspaceError::NoWorkspace => Self::new(ErrorCode::NoWorkspace, message),
+            WorkspaceError::InvalidRoot(_) => Self::new(ErrorCode::InvalidWorkspace, message),
+            WorkspaceError::OutsideRoot(path) => {
+                Self::new(ErrorCode::OutsideWorkspace, message).with_path(path)
+            }
+            WorkspaceError::Protected(path) => {
+                Self::new(ErrorCode::ProtectedPath, message).with_path(path)
+            }
+            WorkspaceError::UnknownWorktree(_) => Self::new(ErrorCode::NotFound, message),
+            WorkspaceError::Io(_) => Self::new(ErrorCode::Io, message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_errors_are_classified() {
+        let err = AppError::io(
+            "Failed to read file",
+            io::Error::from(io::ErrorKind::NotFound),
+        )
+        .with_path("src/main.rs");
+        assert_eq!(err.code, ErrorCode::NotFound);
+        assert!(err.message.starts_with("Failed to read file: "));
+
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["code"], "not_found");
+        assert_eq!(json["details"]["path"], "src/main.rs");
+        assert!(json["details"].get("http_status").is_none());
+
+        let denied = AppError::io("x", io::Error::from(io::ErrorKind::PermissionDenied));
+        assert_eq!(denied.code, ErrorCode::PermissionDenied);
+    }
+
+    #[test]
+    fn test_git_errors_keep_class_and_code() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = git2::Repository::open(dir.path())
+            .map(|_| ())
+            .map_err(|e| AppError::git("Failed to open repository", e))
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::RepoNotFound);
+        let details = err.details.unwrap();
+        assert_eq!(details.git_class.as_deref(), Some("Repository"));
+        assert_eq!(details.git_code.as_deref(), Some("NotFound"));
+
+        let denied = git2::Error::new(
+            git2::ErrorCode::Auth,
+            git2::ErrorClass::Net,
+            "authentication required",
+        );
+        assert_eq!(
+            AppError::git("Failed to fetch", denied).code,
+            ErrorCode::AuthFailed
+        );
+    }
+
+    #[test]
+    fn test_workspace_errors_map_to_codes() {
+        let err: AppError = WorkspaceError::OutsideRoot("../etc".to_string()).into();
+        assert_eq!(err.code, ErrorCode::OutsideWorkspace);
+        assert_eq!(
+            AppError::from(WorkspaceError::NoWorkspace).code,
+            ErrorCode::NoWorkspace
+        );
+    }
+}
@@ -0,0 +1,358 @@
+// File History — R20-02
+// Content-addressed snapshots of overwritten files under .nova/history, with retention
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{AppError, AppResult};
+use crate::file_ops::{self, content_hash, WriteExpectation};
+use crate::git;
+
+const HISTORY_DIR: &str = ".nova/history";
+const INDEX_FILE: &str = "index.json";
+const OBJECTS_DIR: &str = "objects";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RetentionPolicy {
+    pub max_versions_per_file: usize,
+    pub max_age_days: i64,
+    pub max_total_bytes: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_versions_per_file: 50,
+            max_age_days: 30,
+            max_total_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub id: String,
+    pub hash: String,
+    pub size: u64,
+    pub timestamp: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryDiff {
+    pub from: String,
+    /// Version id, or `None` for the file as it is on disk now
+    pub to: Option<String>,
+    pub diff: String,
+}
+
+/// Versions per workspace-relative path, oldest first
+type HistoryIndex = HashMap<String, Vec<HistoryEntry>>;
+
+pub struct FileHistory {
+    policy: RetentionPolicy,
+}
+
+impl FileHistory {
+    pub fn new() -> Self {
+        Self {
+            policy: RetentionPolicy::default(),
+        }
+    }
+
+    pub fn with_policy(policy: RetentionPolicy) -> Self {
+        Self { policy }
+    }
+
+    /// Record `content` as the version of `rel_path` about to be overwritten.
+    /// Skipped when it is identical to the most recent snapshot, and dropped
+    /// again when it alone is over the size budget; both return `None`.
+    pub fn snapshot(
+        &self,
+        root: &Path,
+        rel_path: &str,
+        content: &[u8],
+    ) -> AppResult<Option<HistoryEntry>> {
+        let mut index = load_index(root)?;
+        let hash = content_hash(content);
+
+        let versions = index.entry(rel_path.to_string()).or_default();
+        if versions.last().map(|v| v.hash == hash).unwrap_or(false) {
+            return Ok(None);
+        }
+
+        if !history_dir(root).exists() {
+            git::exclude_nova_dir_at(root);
+        }
+
+        let object = object_path(root, &hash);
+        if !object.exists() {
+            file_ops::atomic_write(&object, content, &WriteExpectation::default())?;
+        }
+
+        let entry = HistoryEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            hash,
+            size: content.len() as u64,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        };
+        versions.push(entry.clone());
+
+        self.prune(root, &mut index)?;
+        save_index(root, &index)?;
+        let kept = index
+            .get(rel_path)
+            .map(|versions| versions.iter().any(|v| v.id == entry.id))
+            .unwrap_or(false);
+        Ok(kept.then_some(entry))
+    }
+
+    /// All recorded versions of a file, newest first
+    pub fn list(&self, root: &Path, rel_path: &str) -> AppResult<Vec<HistoryEntry>> {
+        let index = load_index(root)?;
+        let mut versions = index.get(rel_path).cloned().unwrap_or_default();
+        versions.reverse();
+        Ok(versions)
+    }
+
+    /// Content of one recorded version
+    pub fn read_version(&self, root: &Path, rel_path: &str, id: &str) -> AppResult<Vec<u8>> {
+        let entry = find_entry(&load_index(root)?, rel_path, id)?;
+        fs::read(object_path(root, &entry.hash))
+            .map_err(|e| AppError::io("Failed to read history object", e).with_path(rel_path))
+    }
+
+    /// Unified diff between two versions, or a version and the current file
+    pub fn diff(
+        &self,
+        root: &Path,
+        rel_path: &str,
+        from: &str,
+        to: Option<&str>,
+    ) -> AppResult<HistoryDiff> {
+        let old = self.read_version(root, rel_path, from)?;
+        let new = match to {
+            Some(id) => self.read_version(root, rel_path, id)?,
+            None => fs::read(root.join(rel_path)).unwrap_or_default(),
+        };
+
+        let path = Path::new(rel_path);
+        let mut patch = git2::Patch::from_buffers(&old, Some(path), &new, Some(path), None)
+            .map_err(|e| AppError::git("Failed to diff versions", e))?;
+        let buf = patch
+            .to_buf()
+            .map_err(|e| AppError::git("Failed to render diff", e))?;
+
+        Ok(HistoryDiff {
+            from: from.to_string(),
+            to: to.map(|s| s.to_string()),
+            diff: String::from_utf8_lossy(&buf).into_owned(),
+        })
+    }
+
+    /// Put a recorded version back on disk, snapshotting the current content first
+    pub fn restore(
+        &self,
+        root: &Path,
+        rel_path: &str,
+        id: &str,
+    ) -> AppResult<file_ops::FileVersion> {
+        let content = self.read_version(root, rel_path, id)?;
+        let target = root.join(rel_path);
+        if let Ok(current) = fs::read(&target) {
+            self.snapshot(root, rel_path, &current)?;
+        }
+        file_ops::atomic_write(&target, &content, &WriteExpectation::default())
+    }
+
+    /// Apply the retention policy and delete objects no version refers to
+    fn prune(&self, root: &Path, index: &mut HistoryIndex) -> AppResult<()> {
+        let cutoff = chrono::Utc::now().timestamp_millis() - self.policy.max_age_days * 86_400_000;
+
+        for versions in index.values_mut() {
+            versions.retain(|v| v.timestamp >= cutoff);
+            let excess = versions
+                .len()
+                .saturating_sub(self.policy.max_versions_per_file);
+            versions.drain(..excess);
+        }
+
+        // Over the size budget: drop the globally oldest versions first
+        let mut all: Vec<(i64, String, String)> = index
+            .iter()
+            .flat_map(|(path, versions)| {
+                versions
+                    .iter()
+                    .map(move |v| (v.timestamp, path.clone(), v.id.clone()))
+            })
+            .collect();
+        all.sort();
+        let mut total = referenced_bytes(index);
+        for (_, path, id) in all {
+            if total <= self.policy.max_total_bytes {
+                break;
+            }
+            if let Some(versions) = index.get_mut(&path) {
+                versions.retain(|v| v.id != id);
+            }
+            total = referenced_bytes(index);
+        }
+
+        index.retain(|_, versions| !versions.is_empty());
+
+        let live: HashSet<&str> = index.values().flatten().map(|v| v.hash.as_str()).collect();
+        let objects = history_dir(root).join(OBJECTS_DIR);
+        for shard in fs::read_dir(&objects).into_iter().flatten().flatten() {
+            for object in fs::read_dir(shard.path()).into_iter().flatten().flatten() {
+                let name = object.file_name().to_string_lossy().into_owned();
+                if !live.contains(name.as_str()) {
+                    let _ = fs::remove_file(object.path());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for FileHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn history_dir(root: &Path) -> PathBuf {
+    root.join(HISTORY_DIR)
+}
+
+fn object_path(root: &Path, hash: &str) -> PathBuf {
+    history_dir(root)
+        .join(OBJECTS_DIR)
+        .join(&hash[..2])
+        .join(hash)
+}
+
+/// Bytes of distinct objects still referenced
+fn referenced_bytes(index: &HistoryIndex) -> u64 {
+    let mut seen = HashSet::new();
+    index
+        .values()
+        .flatten()
+        .filter(|v| seen.insert(v.hash.as_str()))
+        .map(|v| v.size)
+        .sum()
+}
+
+fn find_entry(index: &HistoryIndex, rel_path: &str, id: &str) -> AppResult<HistoryEntry> {
+    index
+        .get(rel_path)
+        .and_then(|versions| versions.iter().find(|v| v.id == id))
+        .cloned()
+        .ok_or_else(|| {
+            AppError::not_found(format!("Unknown version {} for {}", id, rel_path))
+                .with_path(rel_path)
+        })
+}
+
+fn load_index(root: &Path) -> AppResult<HistoryIndex> {
+    let path = history_dir(root).join(INDEX_FILE);
+    if !path.exists() {
+        return Ok(HistoryIndex::new());
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|e| AppError::io("Failed to read history index", e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| AppError::internal(format!("Failed to parse history index: {}", e)))
+}
+
+fn save_index(root: &Path, index: &HistoryIndex) -> AppResult<()> {
+    let content = serde_json::to_string_pretty(index)
+        .map_err(|e| AppError::internal(format!("Failed to serialize history index: {}", e)))?;
+    file_ops::atomic_write(
+        &history_dir(root).join(INDEX_FILE),
+        content.as_bytes(),
+        &WriteExpectation::default(),
+    )
+    .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_list_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let history = FileHistory::new();
+        fs::write(root.join("a.txt"), "v2\n").unwrap();
+
+        let first = history.snapshot(root, "a.txt", b"v1\n").unwrap().unwrap();
+        // Identical consecutive content is not recorded twice
+        assert!(history.snapshot(root, "a.txt", b"v1\n").unwrap().is_none());
+        assert_eq!(history.list(root, "a.txt").unwrap().len(), 1);
+
+        let diff = history.diff(root, "a.txt", &first.id, None).unwrap();
+        assert!(diff.diff.contains("-v1"));
+        assert!(diff.diff.contains("+v2"));
+
+        history.restore(root, "a.txt", &first.id).unwrap();
+        assert_eq!(fs::read_to_string(root.join("a.txt")).unwrap(), "v1\n");
+        // The overwritten "v2" is itself kept
+        let versions = history.list(root, "a.txt").unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(
+            history
+                .read_version(root, "a.txt", &versions[0].id)
+                .unwrap(),
+            b"v2\n"
+        );
+    }
+
+    #[test]
+    fn test_first_snapshot_excludes_nova_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let repo = git2::Repository::init(root).unwrap();
+        FileHistory::new().snapshot(root, "a.txt", b"v1\n").unwrap();
+
+        let mut options = git2::StatusOptions::new();
+        options.include_untracked(true).recurse_untracked_dirs(true);
+        assert!(repo.statuses(Some(&mut options)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_retention_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let history = FileHistory::with_policy(RetentionPolicy {
+            max_versions_per_file: 2,
+            max_age_days: 30,
+            max_total_bytes: 1024,
+        });
+
+        for i in 0..4 {
+            history
+                .snapshot(root, "a.txt", format!("version {}", i).as_bytes())
+                .unwrap();
+        }
+        let versions = history.list(root, "a.txt").unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(
+            history
+                .read_version(root, "a.txt", &versions[1].id)
+                .unwrap(),
+            b"version 2"
+        );
+        let objects: usize = fs::read_dir(root.join(HISTORY_DIR).join(OBJECTS_DIR))
+            .unwrap()
+            .map(|shard| fs::read_dir(shard.unwrap().path()).unwrap().count())
+            .sum();
+        assert_eq!(objects, 2);
+
+        let big = history.snapshot(root, "big.bin", &vec![7u8; 2048]).unwrap();
+        assert!(big.is_none());
+        assert!(history.list(root, "big.bin").unwrap().is_empty());
+    }
+}
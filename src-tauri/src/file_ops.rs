@@ -0,0 +1,409 @@
+// File Operations — R20-02
+// Atomic temp-file writes, permission preservation, conflict detection, move and copy
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, PoisonError};
+use std::time::UNIX_EPOCH;
+
+use crate::error::{AppError, AppResult};
+
+/// Snapshot of a file on disk, used by callers to detect concurrent edits
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileVersion {
+    pub hash: String,
+    pub mtime: i64,
+    pub size: u64,
+}
+
+/// What the caller believes is on disk; any set field must still match
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WriteExpectation {
+    pub expected_hash: Option<String>,
+    pub expected_mtime: Option<i64>,
+}
+
+impl WriteExpectation {
+    fn is_empty(&self) -> bool {
+        self.expected_hash.is_none() && self.expected_mtime.is_none()
+    }
+}
+
+const TEMP_SUFFIX: &str = ".nova-tmp";
+
+/// Paths with a write between its expectation check and rename. A second
+/// in-process writer to the same path waits here instead of racing the first.
+static WRITES_IN_FLIGHT: Lazy<(Mutex<HashSet<PathBuf>>, Condvar)> =
+    Lazy::new(|| (Mutex::new(HashSet::new()), Condvar::new()));
+
+/// Held while a path is being checked and replaced; released on drop
+struct WriteLock {
+    path: PathBuf,
+}
+
+impl WriteLock {
+    fn acquire(path: &Path) -> Self {
+        let (held, released) = &*WRITES_IN_FLIGHT;
+        let mut held = held.lock().unwrap_or_else(PoisonError::into_inner);
+        while held.contains(path) {
+            held = released.wait(held).unwrap_or_else(PoisonError::into_inner);
+        }
+        held.insert(path.to_path_buf());
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl Drop for WriteLock {
+    fn drop(&mut self) {
+        let (held, released) = &*WRITES_IN_FLIGHT;
+        held.lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.path);
+        released.notify_all();
+    }
+}
+
+/// Hex-encoded SHA-256 of a byte slice
+pub fn content_hash(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+/// Current version of a file, or `None` if it does not exist
+pub fn file_version(path: &Path) -> AppResult<Option<FileVersion>> {
+    let metadata = match fs::metadata(path) {
+        Ok(m) => m,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(AppError::io("Failed to stat file", e).with_path(path)),
+    };
+    let content =
+        fs::read(path).map_err(|e| AppError::io("Failed to read file", e).with_path(path))?;
+
+    Ok(Some(FileVersion {
+        hash: content_hash(&content),
+        mtime: mtime_millis(&metadata),
+        size: metadata.len(),
+    }))
+}
+
+/// Write `content` to `path` via temp file + fsync + rename.
+///
+/// The original file's permissions are carried over, and if `expected` is set
+/// the write is refused when the file on disk no longer matches it.
+pub fn atomic_write(
+    path: &Path,
+    content: &[u8],
+    expected: &WriteExpectation,
+) -> AppResult<FileVersion> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| AppError::invalid_input(format!("Invalid file path: {}", path.display())))?;
+    fs::create_dir_all(parent)
+        .map_err(|e| AppError::io("Failed to create directory", e).with_path(parent))?;
+
+    let temp_path = temp_path_for(path);
+    let result = write_temp(&temp_path, path, content).and_then(|_| {
+        // Checked as late as possible, and under the path's write lock so two
+        // writers holding the same expectation cannot both pass
+        let _lock = WriteLock::acquire(path);
+        check_expectation(path, expected)?;
+        fs::rename(&temp_path, path)
+            .map_err(|e| AppError::io("Failed to replace file", e).with_path(path))
+    });
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    sync_dir(parent);
+
+    let metadata =
+        fs::metadata(path).map_err(|e| AppError::io("Failed to stat file", e).with_path(path))?;
+    Ok(FileVersion {
+        hash: content_hash(content),
+        mtime: mtime_millis(&metadata),
+        size: metadata.len(),
+    })
+}
+
+/// Move or rename a file or directory. Callers decide what to do with an
+/// existing destination before calling; this refuses to overwrite one.
+pub fn move_path(from: &Path, to: &Path) -> AppResult<()> {
+    check_transfer(from, to)?;
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create directory", e).with_path(parent))?;
+    }
+    fs::rename(from, to).map_err(|e| AppError::io("Failed to move", e).with_path(from))
+}
+
+/// Copy a file or directory tree, recreating symlinks rather than following them
+pub fn copy_path(from: &Path, to: &Path) -> AppResult<()> {
+    check_transfer(from, to)?;
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create directory", e).with_path(parent))?;
+    }
+    copy_recursive(from, to)
+}
+
+fn check_transfer(from: &Path, to: &Path) -> AppResult<()> {
+    if fs::symlink_metadata(from).is_err() {
+        return Err(
+            AppError::not_found(format!("Path does not exist: {}", from.display())).with_path(from),
+        );
+    }
+    if fs::symlink_metadata(to).is_ok() {
+        return Err(AppError::already_exists(format!(
+            "Destination already exists: {}",
+            to.display()
+        ))
+        .with_path(to));
+    }
+    if to.starts_with(from) {
+        return Err(AppError::invalid_input(format!(
+            "Cannot move or copy {} into itself",
+            from.display()
+        )));
+    }
+    Ok(())
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> AppResult<()> {
+    let metadata = fs::symlink_metadata(from)
+        .map_err(|e| AppError::io("Failed to stat", e).with_path(from))?;
+
+    if metadata.file_type().is_symlink() {
+        copy_symlink(from, to)
+    } else if metadata.is_dir() {
+        fs::create_dir(to)
+            .map_err(|e| AppError::io("Failed to create directory", e).with_path(to))?;
+        let entries = fs::read_dir(from)
+            .map_err(|e| AppError::io("Failed to read directory", e).with_path(from))?;
+        for entry in entries {
+            let entry =
+                entry.map_err(|e| AppError::io("Failed to read directory", e).with_path(from))?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to)
+            .map(|_| ())
+            .map_err(|e| AppError::io("Failed to copy", e).with_path(from))
+    }
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> AppResult<()> {
+    let target =
+        fs::read_link(from).map_err(|e| AppError::io("Failed to read link", e).with_path(from))?;
+    std::os::unix::fs::symlink(target, to)
+        .map_err(|e| AppError::io("Failed to create link", e).with_path(to))
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(from: &Path, to: &Path) -> AppResult<()> {
+    fs::copy(from, to)
+        .map(|_| ())
+        .map_err(|e| AppError::io("Failed to copy", e).with_path(from))
+}
+
+fn write_temp(temp_path: &Path, target: &Path, content: &[u8]) -> AppResult<()> {
+    let mut file = File::create(temp_path)
+        .map_err(|e| AppError::io("Failed to create temp file", e).with_path(target))?;
+    file.write_all(content)
+        .map_err(|e| AppError::io("Failed to write file", e).with_path(target))?;
+
+    if let Ok(metadata) = fs::metadata(target) {
+        file.set_permissions(metadata.permissions())
+            .map_err(|e| AppError::io("Failed to preserve permissions", e).with_path(target))?;
+    }
+
+    file.sync_all()
+        .map_err(|e| AppError::io("Failed to sync file", e).with_path(target))
+}
+
+pub(crate) fn check_expectation(path: &Path, expected: &WriteExpectation) -> AppResult<()> {
+    if expected.is_empty() {
+        return Ok(());
+    }
+
+    let conflict = || {
+        AppError::conflict(format!(
+            "Conflict: {} changed on disk since it was read",
+            path.display()
+        ))
+        .with_path(path)
+    };
+    let current = file_version(path)?.ok_or_else(conflict)?;
+
+    if let Some(hash) = &expected.expected_hash {
+        if !hash.eq_ignore_ascii_case(&current.hash) {
+            return Err(conflict());
+        }
+    }
+    if let Some(mtime) = expected.expected_mtime {
+        if mtime != current.mtime {
+            return Err(conflict());
+        }
+    }
+
+    Ok(())
+}
+
+fn temp_path_for(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}{}", name, uuid::Uuid::new_v4(), TEMP_SUFFIX))
+}
+
+/// The file an `atomic_write` temp file is about to replace, if `path` is one
+pub(crate) fn temp_target(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    let (target, id) = name
+        .strip_prefix('.')?
+        .strip_suffix(TEMP_SUFFIX)?
+        .rsplit_once('.')?;
+    uuid::Uuid::parse_str(id).ok()?;
+    (!target.is_empty()).then(|| path.with_file_name(target))
+}
+
+pub(crate) fn mtime_millis(metadata: &fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Persist the rename itself; best effort since not every platform supports it
+fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    {
+        if let Ok(d) = File::open(dir) {
+            let _ = d.sync_all();
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = dir;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    #[test]
+    fn test_atomic_write_creates_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/file.txt");
+
+        let version = atomic_write(&path, b"hello", &WriteExpectation::default()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello");
+        assert_eq!(version.hash, content_hash(b"hello"));
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+
+        let temp = temp_path_for(&path);
+        assert_eq!(temp_target(&temp), Some(path.clone()));
+        assert_eq!(temp_target(&path), None);
+    }
+
+    #[test]
+    fn test_atomic_write_detects_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        let first = atomic_write(&path, b"one", &WriteExpectation::default()).unwrap();
+
+        let stale = WriteExpectation {
+            expected_hash: Some(content_hash(b"something else")),
+            expected_mtime: None,
+        };
+        let err = atomic_write(&path, b"two", &stale).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Conflict);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one");
+
+        let fresh = WriteExpectation {
+            expected_hash: Some(first.hash),
+            expected_mtime: Some(first.mtime),
+        };
+        assert!(atomic_write(&path, b"two", &fresh).is_ok());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "two");
+    }
+
+    #[test]
+    fn test_concurrent_writers_with_same_expectation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        let base = atomic_write(&path, b"base", &WriteExpectation::default()).unwrap();
+
+        let writers: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                let expected = WriteExpectation {
+                    expected_hash: Some(base.hash.clone()),
+                    expected_mtime: None,
+                };
+                std::thread::spawn(move || {
+                    atomic_write(&path, format!("writer {}", i).as_bytes(), &expected)
+                })
+            })
+            .collect();
+        let results: Vec<_> = writers.into_iter().map(|w| w.join().unwrap()).collect();
+
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+        assert!(results
+            .iter()
+            .filter_map(|r| r.as_ref().err())
+            .all(|e| e.code == ErrorCode::Conflict));
+    }
+
+    #[test]
+    fn test_move_and_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("nested")).unwrap();
+        fs::write(src.join("nested/a.txt"), "a").unwrap();
+
+        copy_path(&src, &dir.path().join("copy")).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("copy/nested/a.txt")).unwrap(),
+            "a"
+        );
+        assert_eq!(
+            copy_path(&src, &dir.path().join("copy")).unwrap_err().code,
+            ErrorCode::AlreadyExists
+        );
+        assert!(move_path(&src, &src.join("nested/inner")).is_err());
+
+        move_path(&src, &dir.path().join("moved/src")).unwrap();
+        assert!(!src.exists());
+        assert!(dir.path().join("moved/src/nested/a.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_atomic_write_preserves_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("script.sh");
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        atomic_write(&path, b"#!/bin/sh\necho hi\n", &WriteExpectation::default()).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+}
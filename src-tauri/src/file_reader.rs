@@ -0,0 +1,551 @@
+// File Reader — R20-02
+// Binary detection, encoding/line-ending detection, byte and line ranged reads
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::error::{AppError, AppResult};
+
+/// Bytes returned by a read with no explicit range
+pub const DEFAULT_MAX_READ_BYTES: u64 = 4 * 1024 * 1024;
+/// Lines returned by a line-ranged read with no `line_count`
+pub const DEFAULT_MAX_READ_LINES: usize = 2000;
+const SNIFF_BYTES: usize = 8192;
+/// UTF-16 is decoded this many bytes at a time while looking for line breaks
+const UTF16_CHUNK_BYTES: usize = 64 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TextEncoding {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "latin-1")]
+    Latin1,
+}
+
+impl TextEncoding {
+    fn encoding(self) -> &'static Encoding {
+        match self {
+            TextEncoding::Utf8 => UTF_8,
+            TextEncoding::Utf16Le => UTF_16LE,
+            TextEncoding::Utf16Be => UTF_16BE,
+            // Windows-1252 is the superset browsers and editors use for "Latin-1"
+            TextEncoding::Latin1 => WINDOWS_1252,
+        }
+    }
+
+    fn is_utf16(self) -> bool {
+        matches!(self, TextEncoding::Utf16Le | TextEncoding::Utf16Be)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    Cr,
+    Mixed,
+    None,
+}
+
+/// Either a byte window or a line window; lines take precedence if both are set
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ReadRange {
+    pub byte_offset: Option<u64>,
+    pub byte_length: Option<u64>,
+    /// 1-based first line to return
+    pub start_line: Option<usize>,
+    /// Defaults to `DEFAULT_MAX_READ_LINES`
+    pub line_count: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileReadResult {
+    /// Decoded text, or `None` for binary files
+    pub content: Option<String>,
+    pub is_binary: bool,
+    pub encoding: Option<TextEncoding>,
+    pub has_bom: bool,
+    pub line_ending: LineEnding,
+    pub total_size: u64,
+    /// Byte window actually returned; `end_byte` is where the next chunk starts
+    pub start_byte: u64,
+    pub end_byte: u64,
+    /// Line window actually returned, for line-ranged reads
+    pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+    pub has_more: bool,
+}
+
+enum Detected {
+    Binary,
+    Text {
+        encoding: TextEncoding,
+        bom_len: u64,
+    },
+}
+
+/// Read a file, or a window of it, decoding text in whatever encoding it uses
+pub fn read_file(path: &Path, range: &ReadRange) -> AppResult<FileReadResult> {
+    let mut file =
+        File::open(path).map_err(|e| AppError::io("Failed to read file", e).with_path(path))?;
+    let total_size = file
+        .metadata()
+        .map_err(|e| AppError::io("Failed to stat file", e).with_path(path))?
+        .len();
+
+    let mut head = Vec::with_capacity(SNIFF_BYTES);
+    (&mut file)
+        .take(SNIFF_BYTES as u64)
+        .read_to_end(&mut head)
+        .map_err(|e| AppError::io("Failed to read file", e).with_path(path))?;
+
+    let (encoding, bom_len) = match detect(&head) {
+        Detected::Binary => {
+            return Ok(FileReadResult {
+                content: None,
+                is_binary: true,
+                encoding: None,
+                has_bom: false,
+                line_ending: LineEnding::None,
+                total_size,
+                start_byte: 0,
+                end_byte: 0,
+                start_line: None,
+                end_line: None,
+                has_more: false,
+            })
+        }
+        Detected::Text { encoding, bom_len } => (encoding, bom_len),
+    };
+
+    let (sample, _) = encoding
+        .encoding()
+        .decode_without_bom_handling(&head[bom_len as usize..]);
+    let line_ending = detect_line_ending(&sample);
+
+    let mut result = FileReadResult {
+        content: None,
+        is_binary: false,
+        encoding: Some(encoding),
+        has_bom: bom_len > 0,
+        line_ending,
+        total_size,
+        start_byte: 0,
+        end_byte: 0,
+        start_line: None,
+        end_line: None,
+        has_more: false,
+    };
+
+    if let Some(start_line) = range.start_line {
+        read_lines(
+            &mut file,
+            encoding,
+            bom_len,
+            start_line.max(1),
+            range.line_count,
+            &mut result,
+        )
+        .map_err(|e| e.with_path(path))?;
+    } else {
+        let offset = range.byte_offset.unwrap_or(0).max(bom_len);
+        let length = range.byte_length.unwrap_or(DEFAULT_MAX_READ_BYTES);
+        read_bytes(&mut file, encoding, offset, length, &mut result)
+            .map_err(|e| e.with_path(path))?;
+    }
+
+    Ok(result)
+}
+
+fn detect(head: &[u8]) -> Detected {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(head) {
+        let encoding = if encoding == UTF_16LE {
+            TextEncoding::Utf16Le
+        } else if encoding == UTF_16BE {
+            TextEncoding::Utf16Be
+        } else {
+            TextEncoding::Utf8
+        };
+        return Detected::Text {
+            encoding,
+            bom_len: bom_len as u64,
+        };
+    }
+
+    if head.contains(&0) {
+        return Detected::Binary;
+    }
+
+    let encoding = match std::str::from_utf8(head) {
+        Ok(_) => TextEncoding::Utf8,
+        // The sample may end in the middle of a multi-byte character
+        Err(e) if e.error_len().is_none() => TextEncoding::Utf8,
+        Err(_) => TextEncoding::Latin1,
+    };
+    Detected::Text {
+        encoding,
+        bom_len: 0,
+    }
+}
+
+fn detect_line_ending(text: &str) -> LineEnding {
+    let bytes = text.as_bytes();
+    let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                crlf += 1;
+                i += 1;
+            }
+            b'\r' => cr += 1,
+            b'\n' => lf += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    match (lf > 0, crlf > 0, cr > 0) {
+        (false, false, false) => LineEnding::None,
+        (true, false, false) => LineEnding::Lf,
+        (false, true, false) => LineEnding::Crlf,
+        (false, false, true) => LineEnding::Cr,
+        _ => LineEnding::Mixed,
+    }
+}
+
+fn read_bytes(
+    file: &mut File,
+    encoding: TextEncoding,
+    offset: u64,
+    length: u64,
+    result: &mut FileReadResult,
+) -> AppResult<()> {
+    let mut start = offset.min(result.total_size);
+    let mut length = length;
+    let unit = if encoding.is_utf16() { 2 } else { 1 };
+    if encoding.is_utf16() {
+        // Keep code units intact
+        start -= start % 2;
+        length -= length % 2;
+    }
+
+    // A window too short for the character at `start` is widened until one
+    // fits, so a client paging by `end_byte` always moves forward
+    let (buf, begin, end) = loop {
+        file.seek(SeekFrom::Start(start))
+            .map_err(|e| AppError::io("Failed to seek file", e))?;
+        let mut buf = Vec::new();
+        file.by_ref()
+            .take(length)
+            .read_to_end(&mut buf)
+            .map_err(|e| AppError::io("Failed to read file", e))?;
+        let (begin, end) = whole_characters(&buf, encoding);
+        if begin < end || start + buf.len() as u64 >= result.total_size {
+            break (buf, begin, end);
+        }
+        length += unit;
+    };
+
+    let (text, _) = encoding
+        .encoding()
+        .decode_without_bom_handling(&buf[begin..end]);
+
+    result.content = Some(text.into_owned());
+    result.start_byte = start + begin as u64;
+    result.end_byte = start + end as u64;
+    result.has_more = result.end_byte < result.total_size;
+    Ok(())
+}
+
+/// The part of `buf` made of whole characters: a partial character at the
+/// start is skipped and one at the end is left for the next chunk
+fn whole_characters(buf: &[u8], encoding: TextEncoding) -> (usize, usize) {
+    let mut begin = 0;
+    let mut end = buf.len();
+    match encoding {
+        TextEncoding::Utf8 => {
+            while begin < end && buf[begin] & 0xC0 == 0x80 {
+                begin += 1;
+            }
+            if let Err(e) = std::str::from_utf8(&buf[begin..end]) {
+                if e.error_len().is_none() {
+                    end = begin + e.valid_up_to();
+                }
+            }
+        }
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+            let unit = |i: usize| {
+                let bytes = [buf[i], buf[i + 1]];
+                match encoding {
+                    TextEncoding::Utf16Le => u16::from_le_bytes(bytes),
+                    _ => u16::from_be_bytes(bytes),
+                }
+            };
+            // Surrogate pairs stay together
+            if end - begin >= 2 && (0xDC00..0xE000).contains(&unit(begin)) {
+                begin += 2;
+            }
+            if end - begin >= 2 && (0xD800..0xDC00).contains(&unit(end - 2)) {
+                end -= 2;
+            }
+        }
+        TextEncoding::Latin1 => {}
+    }
+    (begin, end)
+}
+
+fn read_lines(
+    file: &mut File,
+    encoding: TextEncoding,
+    bom_len: u64,
+    start_line: usize,
+    line_count: Option<usize>,
+    result: &mut FileReadResult,
+) -> AppResult<()> {
+    file.seek(SeekFrom::Start(bom_len))
+        .map_err(|e| AppError::io("Failed to seek file", e))?;
+
+    let wanted = line_count.unwrap_or(DEFAULT_MAX_READ_LINES);
+    let mut content = String::new();
+    let mut line_no = 0;
+    let mut returned = 0;
+    let mut position = bom_len;
+    let mut start_byte = None;
+
+    if encoding.is_utf16() {
+        // Line boundaries cannot be found on raw bytes; decode a chunk at a
+        // time and stop as soon as the window is full
+        let mut decoder = encoding.encoding().new_decoder_without_bom_handling();
+        let mut chunk = vec![0; UTF16_CHUNK_BYTES];
+        let mut text = String::new();
+        while returned < wanted {
+            let n = file
+                .read(&mut chunk)
+                .map_err(|e| AppError::io("Failed to read file", e))?;
+            let last = n == 0;
+            text.reserve(decoder.max_utf8_buffer_length(n).unwrap_or(n * 3));
+            let _ = decoder.decode_to_string(&chunk[..n], &mut text, last);
+
+            let mut consumed = 0;
+            while returned < wanted {
+                let end = match text[consumed..].find('\n') {
+                    Some(i) => consumed + i + 1,
+                    // The final line has no terminator
+                    None if last && consumed < text.len() => text.len(),
+                    None => break,
+                };
+                let line = &text[consumed..end];
+                line_no += 1;
+                if line_no >= start_line {
+                    start_byte.get_or_insert(position);
+                    content.push_str(line);
+                    returned += 1;
+                }
+                position += line.encode_utf16().count() as u64 * 2;
+                consumed = end;
+            }
+            text.drain(..consumed);
+            if last {
+                break;
+            }
+        }
+    } else {
+        let mut reader = BufReader::new(file);
+        let mut buf = Vec::new();
+        while returned < wanted {
+            buf.clear();
+            let n = reader
+                .read_until(b'\n', &mut buf)
+                .map_err(|e| AppError::io("Failed to read file", e))?;
+            if n == 0 {
+                break;
+            }
+            line_no += 1;
+            if line_no >= start_line {
+                start_byte.get_or_insert(position);
+                let (text, _) = encoding.encoding().decode_without_bom_handling(&buf);
+                content.push_str(&text);
+                returned += 1;
+            }
+            position += n as u64;
+        }
+    }
+
+    result.content = Some(content);
+    result.start_byte = start_byte.unwrap_or(position);
+    result.end_byte = position;
+    result.start_line = Some(start_line);
+    result.end_line = if returned > 0 {
+        Some(start_line + returned - 1)
+    } else {
+        None
+    };
+    result.has_more = position < result.total_size;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write(bytes: &[u8]) -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        fs::write(&path, bytes).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn test_read_detects_encodings() {
+        let (_d, path) = write("héllo\r\nworld\r\n".as_bytes());
+        let result = read_file(&path, &ReadRange::default()).unwrap();
+        assert_eq!(result.encoding, Some(TextEncoding::Utf8));
+        assert_eq!(result.line_ending, LineEnding::Crlf);
+        assert_eq!(result.content.as_deref(), Some("héllo\r\nworld\r\n"));
+
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend("hi\n".encode_utf16().flat_map(|u| u.to_le_bytes()));
+        let (_d, path) = write(&utf16);
+        let result = read_file(&path, &ReadRange::default()).unwrap();
+        assert_eq!(result.encoding, Some(TextEncoding::Utf16Le));
+        assert!(result.has_bom);
+        assert_eq!(result.content.as_deref(), Some("hi\n"));
+
+        let (_d, path) = write(b"caf\xe9\n");
+        let result = read_file(&path, &ReadRange::default()).unwrap();
+        assert_eq!(result.encoding, Some(TextEncoding::Latin1));
+        assert_eq!(result.content.as_deref(), Some("café\n"));
+    }
+
+    #[test]
+    fn test_read_detects_binary() {
+        let (_d, path) = write(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR");
+        let result = read_file(&path, &ReadRange::default()).unwrap();
+        assert!(result.is_binary);
+        assert!(result.content.is_none());
+    }
+
+    #[test]
+    fn test_read_line_range() {
+        let (_d, path) = write(b"one\ntwo\nthree\nfour\n");
+        let range = ReadRange {
+            start_line: Some(2),
+            line_count: Some(2),
+            ..Default::default()
+        };
+        let result = read_file(&path, &range).unwrap();
+        assert_eq!(result.content.as_deref(), Some("two\nthree\n"));
+        assert_eq!((result.start_line, result.end_line), (Some(2), Some(3)));
+        assert_eq!((result.start_byte, result.end_byte), (4, 14));
+        assert!(result.has_more);
+
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend(
+            "one\ntwo\nthree"
+                .encode_utf16()
+                .flat_map(|u| u.to_le_bytes()),
+        );
+        let (_d, path) = write(&utf16);
+        let result = read_file(&path, &range).unwrap();
+        assert_eq!(result.content.as_deref(), Some("two\nthree"));
+        assert_eq!((result.start_byte, result.end_byte), (10, 28));
+        assert!(!result.has_more);
+    }
+
+    #[test]
+    fn test_read_line_range_defaults_to_a_page() {
+        let text: String = (1..=DEFAULT_MAX_READ_LINES * 2)
+            .map(|i| format!("line {}\n", i))
+            .collect();
+        let (_d, path) = write(text.as_bytes());
+        let range = ReadRange {
+            start_line: Some(1),
+            ..Default::default()
+        };
+        let result = read_file(&path, &range).unwrap();
+        assert_eq!(result.end_line, Some(DEFAULT_MAX_READ_LINES));
+        assert!(result.has_more);
+
+        let mut utf16 = vec![0xFE, 0xFF];
+        utf16.extend(text.encode_utf16().flat_map(|u| u.to_be_bytes()));
+        let (_d, path) = write(&utf16);
+        let result = read_file(&path, &range).unwrap();
+        assert_eq!(result.end_line, Some(DEFAULT_MAX_READ_LINES));
+        assert!(result.content.unwrap().ends_with("line 2000\n"));
+        assert!(result.has_more);
+
+        // Past the first decoded chunk
+        let tail = ReadRange {
+            start_line: Some(3500),
+            line_count: Some(2),
+            ..Default::default()
+        };
+        let result = read_file(&path, &tail).unwrap();
+        assert_eq!(result.content.as_deref(), Some("line 3500\nline 3501\n"));
+    }
+
+    #[test]
+    fn test_read_byte_range_keeps_characters_whole() {
+        let (_d, path) = write("aé€b".as_bytes());
+        // "é" occupies bytes 1..3 and "€" bytes 3..6; a 4-byte window splits "€"
+        let range = ReadRange {
+            byte_offset: Some(0),
+            byte_length: Some(4),
+            ..Default::default()
+        };
+        let first = read_file(&path, &range).unwrap();
+        assert_eq!(first.content.as_deref(), Some("aé"));
+        assert_eq!(first.end_byte, 3);
+
+        let next = ReadRange {
+            byte_offset: Some(first.end_byte),
+            byte_length: Some(10),
+            ..Default::default()
+        };
+        let rest = read_file(&path, &next).unwrap();
+        assert_eq!(rest.content.as_deref(), Some("€b"));
+        assert!(!rest.has_more);
+
+        // Shorter than the character at the offset: widened to hold it
+        let short = ReadRange {
+            byte_offset: Some(3),
+            byte_length: Some(1),
+            ..Default::default()
+        };
+        let result = read_file(&path, &short).unwrap();
+        assert_eq!(result.content.as_deref(), Some("€"));
+        assert_eq!((result.start_byte, result.end_byte), (3, 6));
+    }
+
+    #[test]
+    fn test_read_byte_range_utf16_always_advances() {
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend("a😀b".encode_utf16().flat_map(|u| u.to_le_bytes()));
+        let (_d, path) = write(&utf16);
+        // "a" is bytes 2..4, the surrogate pair for "😀" 4..8, "b" 8..10
+        let mut offset = 2;
+        let mut pieces = Vec::new();
+        while offset < 10 {
+            let range = ReadRange {
+                byte_offset: Some(offset),
+                byte_length: Some(1),
+                ..Default::default()
+            };
+            let result = read_file(&path, &range).unwrap();
+            assert!(result.end_byte > offset);
+            pieces.push(result.content.unwrap());
+            offset = result.end_byte;
+        }
+        assert_eq!(pieces, vec!["a", "😀", "b"]);
+    }
+}
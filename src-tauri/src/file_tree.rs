@@ -0,0 +1,270 @@
+// File Tree — R20-02
+// Lazy, paginated, ignore-aware directory listing with git status
+
+use git2::{Status, StatusOptions};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::Path;
+
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::file_ops::mtime_millis;
+use crate::git;
+use crate::workspace::{self, to_slash_path};
+
+pub const DEFAULT_PAGE_SIZE: usize = 500;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GitFileStatus {
+    Modified,
+    Added,
+    Deleted,
+    Renamed,
+    Untracked,
+    Conflicted,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TreeEntry {
+    pub name: String,
+    /// Path relative to the workspace root, `/`-separated
+    pub path: String,
+    pub kind: EntryKind,
+    pub size: u64,
+    pub mtime: i64,
+    pub git_status: Option<GitFileStatus>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DirectoryListing {
+    pub path: String,
+    pub entries: Vec<TreeEntry>,
+    pub total: usize,
+    pub offset: usize,
+    /// Offset to pass for the next page, if there is one
+    pub next_offset: Option<usize>,
+}
+
+/// List the immediate children of `dir` (inside `root`), directories first.
+/// Deeper levels are fetched lazily by listing each directory as it is expanded.
+pub fn list_directory(
+    root: &Path,
+    dir: &Path,
+    offset: usize,
+    limit: usize,
+) -> AppResult<DirectoryListing> {
+    if !dir.is_dir() {
+        return Err(
+            AppError::invalid_input(format!("Not a directory: {}", dir.display())).with_path(dir),
+        );
+    }
+
+    let mut entries = Vec::new();
+    for result in workspace::project_walker(dir).max_depth(Some(1)).build() {
+        let entry = result.map_err(|e| {
+            AppError::new(ErrorCode::Io, format!("Failed to list directory: {}", e)).with_path(dir)
+        })?;
+        if entry.depth() == 0 {
+            continue;
+        }
+
+        let metadata = match fs::symlink_metadata(entry.path()) {
+            Ok(m) => m,
+            // Removed between readdir and stat
+            Err(_) => continue,
+        };
+        let kind = if metadata.file_type().is_symlink() {
+            EntryKind::Symlink
+        } else if metadata.is_dir() {
+            EntryKind::Directory
+        } else {
+            EntryKind::File
+        };
+        let rel = entry.path().strip_prefix(root).unwrap_or(entry.path());
+
+        entries.push(TreeEntry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            path: to_slash_path(rel),
+            kind,
+            size: if kind == EntryKind::File { metadata.len() } else { 0 },
+            mtime: mtime_millis(&metadata),
+            git_status: None,
+        });
+    }
+
+    entries.sort_by(|a, b| match (a.kind, b.kind) {
+        (EntryKind::Directory, EntryKind::Directory) => compare_names(&a.name, &b.name),
+        (EntryKind::Directory, _) => Ordering::Less,
+        (_, EntryKind::Directory) => Ordering::Greater,
+        _ => compare_names(&a.name, &b.name),
+    });
+
+    let total = entries.len();
+    let mut page: Vec<TreeEntry> = entries.into_iter().skip(offset).take(limit).collect();
+    annotate_git_status(dir, &mut page, root);
+
+    let end = offset + page.len();
+    Ok(DirectoryListing {
+        path: to_slash_path(dir.strip_prefix(root).unwrap_or(dir)),
+        entries: page,
+        total,
+        offset,
+        next_offset: if end < total { Some(end) } else { None },
+    })
+}
+
+fn compare_names(a: &str, b: &str) -> Ordering {
+    a.to_lowercase().cmp(&b.to_lowercase()).then_with(|| a.cmp(b))
+}
+
+/// Fill in git status for a page of entries; silently skipped outside a repo
+fn annotate_git_status(dir: &Path, entries: &mut [TreeEntry], root: &Path) {
+    let repo = match git::open_repo(dir, Some(root)) {
+        Ok(r) => r,
+        Err(_) => return,
+    };
+    let workdir = match repo.workdir().and_then(|w| fs::canonicalize(w).ok()) {
+        Some(w) => w,
+        None => return,
+    };
+    let dir_rel = match dir.strip_prefix(&workdir) {
+        Ok(rel) => to_slash_path(rel),
+        Err(_) => return,
+    };
+
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .renames_head_to_index(true);
+    if !dir_rel.is_empty() {
+        opts.pathspec(&dir_rel);
+    }
+    let statuses = match repo.statuses(Some(&mut opts)) {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+    let changes: Vec<(String, GitFileStatus)> = statuses
+        .iter()
+        .filter_map(|s| Some((s.path()?.to_string(), classify(s.status())?)))
+        .collect();
+
+    for entry in entries.iter_mut() {
+        let abs = root.join(&entry.path);
+        let rel = match abs.strip_prefix(&workdir) {
+            Ok(rel) => to_slash_path(rel),
+            Err(_) => continue,
+        };
+
+        entry.git_status = if entry.kind == EntryKind::Directory {
+            let prefix = format!("{}/", rel);
+            let mut inside = changes.iter().filter(|(p, _)| p.starts_with(&prefix));
+            match inside.next() {
+                None => None,
+                Some((_, first)) => {
+                    if *first == GitFileStatus::Untracked
+                        && inside.all(|(_, s)| *s == GitFileStatus::Untracked)
+                    {
+                        Some(GitFileStatus::Untracked)
+                    } else {
+                        Some(GitFileStatus::Modified)
+                    }
+                }
+            }
+        } else {
+            // Files inside an untracked directory are reported only via the directory
+            changes
+                .iter()
+                .find(|(p, _)| *p == rel || (p.ends_with('/') && rel.starts_with(p.as_str())))
+                .map(|(_, s)| *s)
+        };
+    }
+}
+
+fn classify(status: Status) -> Option<GitFileStatus> {
+    if status.is_conflicted() {
+        Some(GitFileStatus::Conflicted)
+    } else if status.is_wt_new() {
+        Some(GitFileStatus::Untracked)
+    } else if status.is_index_renamed() || status.is_wt_renamed() {
+        Some(GitFileStatus::Renamed)
+    } else if status.is_index_deleted() || status.is_wt_deleted() {
+        Some(GitFileStatus::Deleted)
+    } else if status.is_index_new() {
+        Some(GitFileStatus::Added)
+    } else if status.is_index_modified()
+        || status.is_wt_modified()
+        || status.is_index_typechange()
+        || status.is_wt_typechange()
+    {
+        Some(GitFileStatus::Modified)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::create_dir_all(root.join(".nova/history")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join(".novaignore"), "*.secret\n").unwrap();
+        fs::write(root.join("b.txt"), "b").unwrap();
+        fs::write(root.join("A.md"), "a").unwrap();
+        fs::write(root.join("keys.secret"), "s").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        (dir, root)
+    }
+
+    #[test]
+    fn test_list_directory_respects_ignores() {
+        let (_dir, root) = project();
+        let listing = list_directory(&root, &root, 0, DEFAULT_PAGE_SIZE).unwrap();
+        let names: Vec<&str> = listing.entries.iter().map(|e| e.name.as_str()).collect();
+
+        assert_eq!(names, vec!["src", ".gitignore", ".novaignore", "A.md", "b.txt"]);
+        assert_eq!(listing.entries[0].kind, EntryKind::Directory);
+        assert_eq!(listing.next_offset, None);
+    }
+
+    #[test]
+    fn test_list_directory_paginates() {
+        let (_dir, root) = project();
+        let first = list_directory(&root, &root, 0, 2).unwrap();
+        assert_eq!(first.entries.len(), 2);
+        assert_eq!(first.total, 5);
+        assert_eq!(first.next_offset, Some(2));
+
+        let last = list_directory(&root, &root, 4, 2).unwrap();
+        assert_eq!(last.entries.len(), 1);
+        assert_eq!(last.next_offset, None);
+    }
+
+    #[test]
+    fn test_list_directory_git_status() {
+        let (_dir, root) = project();
+        git2::Repository::init(&root).unwrap();
+
+        let listing = list_directory(&root, &root, 0, DEFAULT_PAGE_SIZE).unwrap();
+        let src = listing.entries.iter().find(|e| e.name == "src").unwrap();
+        assert_eq!(src.git_status, Some(GitFileStatus::Untracked));
+
+        let nested = list_directory(&root, &root.join("src"), 0, DEFAULT_PAGE_SIZE).unwrap();
+        assert_eq!(nested.entries[0].path, "src/main.rs");
+        assert_eq!(nested.entries[0].git_status, Some(GitFileStatus::Untracked));
+    }
+}
@@ -0,0 +1,627 @@
+// File Watcher — R20-02
+// Debounced recursive project watching with .gitignore/.novaignore filtering
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::file_ops;
+use crate::workspace::{self, NOVA_IGNORE_FILE};
+
+pub const DEFAULT_DEBOUNCE_MS: u64 = 150;
+
+/// How long raw events are ignored for a path the app already reported itself
+const SUPPRESS_FOR: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChangeKind {
+    Create,
+    Modify,
+    Delete,
+    Rename,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileChangeEvent {
+    pub kind: FileChangeKind,
+    pub path: String,
+    /// Source path for renames
+    pub old_path: Option<String>,
+}
+
+/// Debounced set of changes delivered to the webview as one event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileChangeBatch {
+    pub watch_id: String,
+    pub root: String,
+    pub events: Vec<FileChangeEvent>,
+}
+
+pub type ChangeSink = Arc<dyn Fn(FileChangeBatch) + Send + Sync>;
+
+/// Paths changed by the app itself, mapped to when raw events for them stop being ignored
+type Suppressed = Arc<Mutex<HashMap<PathBuf, Instant>>>;
+
+struct WatchHandle {
+    root: PathBuf,
+    sink: ChangeSink,
+    suppressed: Suppressed,
+    // Dropping the watcher closes the event channel, which stops the debounce thread
+    _watcher: RecommendedWatcher,
+}
+
+pub struct FileWatcher {
+    watches: HashMap<String, WatchHandle>,
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        Self {
+            watches: HashMap::new(),
+        }
+    }
+
+    /// Start watching `root` recursively; returns the handle id for `unwatch`.
+    /// Ignore files in `project_root` and the directories down to `root` apply too.
+    pub fn watch(
+        &mut self,
+        root: &Path,
+        project_root: &Path,
+        debounce: Duration,
+        sink: ChangeSink,
+    ) -> AppResult<String> {
+        if !root.is_dir() {
+            return Err(AppError::invalid_input(format!(
+                "Path is not a directory: {}",
+                root.display()
+            ))
+            .with_path(root));
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)
+            .map_err(|e| AppError::internal(format!("Failed to create watcher: {}", e)))?;
+        watcher.watch(root, RecursiveMode::Recursive).map_err(|e| {
+            AppError::new(
+                ErrorCode::Io,
+                format!("Failed to watch {}: {}", root.display(), e),
+            )
+            .with_path(root)
+        })?;
+
+        let watch_id = uuid::Uuid::new_v4().to_string();
+        let suppressed: Suppressed = Arc::new(Mutex::new(HashMap::new()));
+        let worker = Worker {
+            root: root.to_path_buf(),
+            project_root: project_root.to_path_buf(),
+            watch_id: watch_id.clone(),
+            debounce,
+            sink: sink.clone(),
+            suppressed: suppressed.clone(),
+        };
+        thread::Builder::new()
+            .name(format!("nova-watch-{}", &watch_id[..8]))
+            .spawn(move || debounce_loop(rx, worker))
+            .map_err(|e| AppError::io("Failed to start watcher thread", e))?;
+
+        self.watches.insert(
+            watch_id.clone(),
+            WatchHandle {
+                root: root.to_path_buf(),
+                sink,
+                suppressed,
+                _watcher: watcher,
+            },
+        );
+
+        Ok(watch_id)
+    }
+
+    /// Stop a watch started with `watch`
+    pub fn unwatch(&mut self, watch_id: &str) -> AppResult<()> {
+        self.watches
+            .remove(watch_id)
+            .map(|_| ())
+            .ok_or_else(|| AppError::not_found(format!("Unknown watch id: {}", watch_id)))
+    }
+
+    /// Report changes the app made itself (moves, deletes, restores) to every
+    /// watch covering them, and mute the raw events they will also trigger.
+    pub fn notify_changes(&self, events: &[FileChangeEvent]) {
+        for (watch_id, handle) in &self.watches {
+            let covered: Vec<FileChangeEvent> = events
+                .iter()
+                .filter(|e| {
+                    Path::new(&e.path).starts_with(&handle.root)
+                        || e.old_path
+                            .as_ref()
+                            .map(|p| Path::new(p).starts_with(&handle.root))
+                            .unwrap_or(false)
+                })
+                .cloned()
+                .collect();
+            if covered.is_empty() {
+                continue;
+            }
+
+            if let Ok(mut suppressed) = handle.suppressed.lock() {
+                let until = Instant::now() + SUPPRESS_FOR;
+                for event in &covered {
+                    suppressed.insert(PathBuf::from(&event.path), until);
+                    if let Some(old) = &event.old_path {
+                        suppressed.insert(PathBuf::from(old), until);
+                    }
+                }
+            }
+
+            (handle.sink)(FileChangeBatch {
+                watch_id: watch_id.clone(),
+                root: handle.root.to_string_lossy().into_owned(),
+                events: covered,
+            });
+        }
+    }
+}
+
+impl Default for FileWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Every `.gitignore`/`.novaignore` that applies under a watched root: those in
+/// the project root and the directories down to the watched root, and those
+/// nested anywhere below it. The deepest matching file decides, as in git.
+struct IgnoreRules {
+    root: PathBuf,
+    /// One matcher per directory with ignore files, deepest first
+    matchers: Vec<Gitignore>,
+}
+
+impl IgnoreRules {
+    fn load(root: &Path, project_root: &Path) -> Self {
+        let mut dirs: Vec<PathBuf> = root
+            .ancestors()
+            .take_while(|dir| dir.starts_with(project_root))
+            .map(Path::to_path_buf)
+            .collect();
+        for entry in workspace::project_walker(root).build().flatten() {
+            if entry.depth() > 0 && is_ignore_file(entry.path()) {
+                if let Some(dir) = entry.path().parent() {
+                    dirs.push(dir.to_path_buf());
+                }
+            }
+        }
+        dirs.sort();
+        dirs.dedup();
+        dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+
+        Self {
+            root: root.to_path_buf(),
+            matchers: dirs.iter().filter_map(|dir| dir_matcher(dir)).collect(),
+        }
+    }
+
+    /// Whether `path`, or any directory between it and the watched root, is ignored
+    fn is_ignored(&self, path: &Path) -> bool {
+        if workspace::is_internal_path(&self.root, path) {
+            return true;
+        }
+        let rel = match path.strip_prefix(&self.root) {
+            Ok(rel) => rel,
+            Err(_) => return true,
+        };
+        let mut current = self.root.clone();
+        let mut components = rel.components().peekable();
+        while let Some(component) = components.next() {
+            current.push(component);
+            let is_dir = components.peek().is_some() || current.is_dir();
+            if self.matched(&current, is_dir) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn matched(&self, path: &Path, is_dir: bool) -> bool {
+        for matcher in &self.matchers {
+            if path == matcher.path() || !path.starts_with(matcher.path()) {
+                continue;
+            }
+            let matched = matcher.matched(path, is_dir);
+            if matched.is_ignore() {
+                return true;
+            }
+            if matched.is_whitelist() {
+                return false;
+            }
+        }
+        false
+    }
+}
+
+/// Rules from one directory's ignore files; `.novaignore` is added last so its
+/// lines win over `.gitignore`'s
+fn dir_matcher(dir: &Path) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut found = false;
+    for name in [".gitignore", NOVA_IGNORE_FILE] {
+        let path = dir.join(name);
+        if path.is_file() {
+            found |= builder.add(path).is_none();
+        }
+    }
+    if !found {
+        return None;
+    }
+    builder.build().ok()
+}
+
+fn is_ignore_file(path: &Path) -> bool {
+    path.file_name()
+        .map(|n| n == ".gitignore" || n == NOVA_IGNORE_FILE)
+        .unwrap_or(false)
+}
+
+struct Worker {
+    root: PathBuf,
+    project_root: PathBuf,
+    watch_id: String,
+    debounce: Duration,
+    sink: ChangeSink,
+    suppressed: Suppressed,
+}
+
+impl Worker {
+    fn is_suppressed(&self, path: &Path) -> bool {
+        let mut suppressed = match self.suppressed.lock() {
+            Ok(s) => s,
+            Err(_) => return false,
+        };
+        let now = Instant::now();
+        suppressed.retain(|_, until| *until > now);
+        suppressed.keys().any(|p| path.starts_with(p))
+    }
+}
+
+fn debounce_loop(rx: Receiver<notify::Result<notify::Event>>, worker: Worker) {
+    let Worker {
+        ref root,
+        ref project_root,
+        ref watch_id,
+        debounce,
+        ref sink,
+        ..
+    } = worker;
+    let mut ignore = IgnoreRules::load(root, project_root);
+
+    // Block for the first event of a burst, then keep collecting until the
+    // tree has been quiet for `debounce`.
+    while let Ok(first) = rx.recv() {
+        let mut pending = PendingChanges::default();
+        let mut reload_ignore = false;
+        let mut next = Some(first);
+        let mut deadline = Instant::now() + debounce;
+
+        loop {
+            if let Some(Ok(event)) = next.take() {
+                reload_ignore |= event.paths.iter().any(|p| is_ignore_file(p));
+                pending.record(&event);
+                deadline = Instant::now() + debounce;
+            }
+            match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(event) => next = Some(event),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        if reload_ignore {
+            ignore = IgnoreRules::load(root, project_root);
+        }
+
+        let events: Vec<FileChangeEvent> = pending
+            .into_events()
+            .into_iter()
+            .filter(|(_, path, _)| !ignore.is_ignored(path))
+            .filter(|(_, path, old)| {
+                !worker.is_suppressed(path)
+                    && !old
+                        .as_ref()
+                        .map(|o| worker.is_suppressed(o))
+                        .unwrap_or(false)
+            })
+            .map(|(kind, path, old_path)| FileChangeEvent {
+                kind,
+                path: path.to_string_lossy().into_owned(),
+                old_path: old_path.map(|p| p.to_string_lossy().into_owned()),
+            })
+            .collect();
+
+        if !events.is_empty() {
+            sink(FileChangeBatch {
+                watch_id: watch_id.clone(),
+                root: root.to_string_lossy().into_owned(),
+                events,
+            });
+        }
+    }
+}
+
+/// Per-path coalescing of raw notify events within one debounce window
+#[derive(Default)]
+struct PendingChanges {
+    order: Vec<PathBuf>,
+    changes: HashMap<PathBuf, (FileChangeKind, Option<PathBuf>)>,
+    /// Target of an `atomic_write` temp file whose rename-from half just arrived
+    replacing: Option<PathBuf>,
+}
+
+impl PendingChanges {
+    fn record(&mut self, event: &notify::Event) {
+        match event.kind {
+            EventKind::Create(_) => {
+                for path in &event.paths {
+                    self.push(path, FileChangeKind::Create, None);
+                }
+            }
+            EventKind::Remove(_) => {
+                for path in &event.paths {
+                    self.push(path, FileChangeKind::Delete, None);
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                let (from, to) = (&event.paths[0], &event.paths[1]);
+                // The separate From/To halves of this rename may already be queued
+                let previous = self.changes.remove(to).map(|(kind, _)| kind);
+                self.changes.remove(from);
+                if file_ops::temp_target(from).as_ref() == Some(to) {
+                    // An atomic write replacing `to`, not a user-visible rename
+                    let kind = match previous {
+                        Some(FileChangeKind::Create) => FileChangeKind::Create,
+                        _ => FileChangeKind::Modify,
+                    };
+                    self.push(to, kind, None);
+                } else {
+                    self.push(to, FileChangeKind::Rename, Some(from.clone()));
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                for path in &event.paths {
+                    self.replacing = file_ops::temp_target(path);
+                    self.push(path, FileChangeKind::Delete, None);
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                for path in &event.paths {
+                    if self.replacing.take().as_ref() == Some(path) {
+                        self.push(path, FileChangeKind::Modify, None);
+                    } else {
+                        self.push(path, FileChangeKind::Create, None);
+                    }
+                }
+            }
+            EventKind::Modify(_) => {
+                for path in &event.paths {
+                    self.push(path, FileChangeKind::Modify, None);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn push(&mut self, path: &Path, kind: FileChangeKind, old_path: Option<PathBuf>) {
+        use FileChangeKind::*;
+
+        let merged = match self.changes.get(path).map(|(k, _)| *k) {
+            None => Some(kind),
+            Some(Create) => match kind {
+                Delete => None,
+                _ => Some(Create),
+            },
+            Some(Delete) => match kind {
+                Create | Modify => Some(Modify),
+                other => Some(other),
+            },
+            Some(Rename) => match kind {
+                Modify => Some(Rename),
+                other => Some(other),
+            },
+            Some(Modify) => Some(if kind == Create { Modify } else { kind }),
+        };
+
+        let previous_old = self.changes.get(path).and_then(|(_, o)| o.clone());
+        match merged {
+            Some(kind) => {
+                if !self.order.iter().any(|p| p == path) {
+                    self.order.push(path.to_path_buf());
+                }
+                let old_path = old_path.or(if kind == Rename { previous_old } else { None });
+                self.changes.insert(path.to_path_buf(), (kind, old_path));
+            }
+            None => {
+                self.changes.remove(path);
+            }
+        }
+    }
+
+    fn into_events(mut self) -> Vec<(FileChangeKind, PathBuf, Option<PathBuf>)> {
+        self.order
+            .into_iter()
+            .filter_map(|path| {
+                self.changes
+                    .remove(&path)
+                    .map(|(kind, old)| (kind, path, old))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, RemoveKind};
+    use std::sync::Mutex;
+
+    fn event(kind: EventKind, paths: &[&str]) -> notify::Event {
+        notify::Event {
+            kind,
+            paths: paths.iter().map(PathBuf::from).collect(),
+            attrs: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_pending_changes_coalesce() {
+        let mut pending = PendingChanges::default();
+        pending.record(&event(EventKind::Create(CreateKind::File), &["/p/a"]));
+        pending.record(&event(
+            EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+            &["/p/a"],
+        ));
+        pending.record(&event(EventKind::Create(CreateKind::File), &["/p/tmp"]));
+        pending.record(&event(EventKind::Remove(RemoveKind::File), &["/p/tmp"]));
+        pending.record(&event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+            &["/p/old", "/p/new"],
+        ));
+
+        let events = pending.into_events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0], (FileChangeKind::Create, PathBuf::from("/p/a"), None));
+        assert_eq!(
+            events[1],
+            (
+                FileChangeKind::Rename,
+                PathBuf::from("/p/new"),
+                Some(PathBuf::from("/p/old"))
+            )
+        );
+    }
+
+    #[test]
+    fn test_atomic_write_renames_become_modify() {
+        let temp = "/p/.main.rs.67e55044-10b1-426f-9247-bb680e5fe0c8.nova-tmp";
+        let modified = vec![(FileChangeKind::Modify, PathBuf::from("/p/main.rs"), None)];
+
+        // inotify reports both halves and then the paired rename
+        let mut pending = PendingChanges::default();
+        pending.record(&event(EventKind::Create(CreateKind::File), &[temp]));
+        pending.record(&event(
+            EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+            &[temp],
+        ));
+        pending.record(&event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)),
+            &[temp],
+        ));
+        pending.record(&event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)),
+            &["/p/main.rs"],
+        ));
+        pending.record(&event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+            &[temp, "/p/main.rs"],
+        ));
+        assert_eq!(pending.into_events(), modified);
+
+        // Other backends may only report the halves
+        let mut pending = PendingChanges::default();
+        pending.record(&event(EventKind::Create(CreateKind::File), &[temp]));
+        pending.record(&event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)),
+            &[temp],
+        ));
+        pending.record(&event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)),
+            &["/p/main.rs"],
+        ));
+        assert_eq!(pending.into_events(), modified);
+    }
+
+    #[test]
+    fn test_ignore_rules_are_layered() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = std::fs::canonicalize(dir.path()).unwrap();
+        let sub = project.join("sub");
+        std::fs::create_dir_all(sub.join("nested")).unwrap();
+        std::fs::write(project.join(".gitignore"), "*.log\n").unwrap();
+        std::fs::write(sub.join("nested/.gitignore"), "*.tmp\n!keep.log\n").unwrap();
+        std::fs::write(sub.join(NOVA_IGNORE_FILE), "generated/\n").unwrap();
+
+        let rules = IgnoreRules::load(&sub, &project);
+        assert!(rules.is_ignored(&sub.join("debug.log")));
+        assert!(rules.is_ignored(&sub.join("nested/x.tmp")));
+        assert!(!rules.is_ignored(&sub.join("x.tmp")));
+        assert!(!rules.is_ignored(&sub.join("nested/keep.log")));
+        assert!(rules.is_ignored(&sub.join("generated/out.rs")));
+        assert!(!rules.is_ignored(&sub.join("nested/main.rs")));
+    }
+
+    /// Wait until the received events satisfy `done`, or fail after a few seconds
+    fn wait_for(
+        received: &Mutex<Vec<FileChangeEvent>>,
+        done: impl Fn(&[FileChangeEvent]) -> bool,
+    ) -> Vec<FileChangeEvent> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let events = received.lock().unwrap().clone();
+            if done(&events) {
+                return events;
+            }
+            assert!(Instant::now() < deadline, "timed out; got {:?}", events);
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn test_watch_emits_filtered_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = std::fs::canonicalize(dir.path()).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+
+        let received: Arc<Mutex<Vec<FileChangeEvent>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink_received = received.clone();
+        let mut watcher = FileWatcher::new();
+        let id = watcher
+            .watch(
+                &root,
+                &root,
+                Duration::from_millis(50),
+                Arc::new(move |batch| sink_received.lock().unwrap().extend(batch.events)),
+            )
+            .unwrap();
+
+        std::fs::write(root.join("debug.log"), "ignored").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+        let events = wait_for(&received, |events| {
+            events.iter().any(|e| e.path.ends_with("main.rs"))
+        });
+        assert!(!events.iter().any(|e| e.path.ends_with("debug.log")));
+
+        received.lock().unwrap().clear();
+        file_ops::atomic_write(
+            &root.join("main.rs"),
+            b"fn main() { run() }",
+            &Default::default(),
+        )
+        .unwrap();
+        let events = wait_for(&received, |events| !events.is_empty());
+        assert!(events.iter().all(|e| e.old_path.is_none()));
+        assert!(events
+            .iter()
+            .all(|e| e.path.ends_with("main.rs") && e.kind == FileChangeKind::Modify));
+
+        assert!(watcher.unwatch(&id).is_ok());
+        assert!(watcher.unwatch(&id).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+// Git Blame — R20-02
+// Per-line attribution for a file at a revision or as it stands in the working tree
+
+use git2::{BlameOptions, ErrorCode as GitErrorCode, Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::commit::head_commit;
+use super::resolve_commit;
+use crate::error::{AppError, AppResult};
+use crate::workspace::to_slash_path;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BlameRequest {
+    /// Blame the file as of this revision instead of the working tree
+    pub rev: Option<String>,
+    /// 1-based, inclusive
+    pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlameLine {
+    /// 1-based line number in the blamed content
+    pub line: usize,
+    pub content: String,
+    /// `None` for lines that are not committed yet
+    pub commit: Option<String>,
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+    /// Unix milliseconds
+    pub author_time: Option<i64>,
+    pub summary: Option<String>,
+    /// Line number and path in the commit that introduced the line
+    pub orig_line: Option<usize>,
+    pub orig_path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlameResult {
+    pub path: String,
+    pub rev: Option<String>,
+    pub lines: Vec<BlameLine>,
+}
+
+/// Blame `path` (relative to the repository root). Without a revision the working
+/// tree content is blamed, so uncommitted edits show up as uncommitted lines.
+pub fn blame(repo: &Repository, path: &str, request: &BlameRequest) -> AppResult<BlameResult> {
+    let rel = Path::new(path);
+    let (content, newest) = match &request.rev {
+        Some(spec) => {
+            let commit = resolve_commit(repo, spec)?;
+            let entry = commit
+                .tree()
+                .and_then(|tree| tree.get_path(rel))
+                .map_err(|e| {
+                    AppError::git(&format!("'{}' does not exist at {}", path, spec), e)
+                        .with_path(path)
+                })?;
+            let blob = repo
+                .find_blob(entry.id())
+                .map_err(|e| AppError::git("Failed to read file", e).with_path(path))?;
+            (blob.content().to_vec(), Some(commit.id()))
+        }
+        None => {
+            let workdir = repo
+                .workdir()
+                .ok_or_else(|| AppError::invalid_input("Cannot blame in a bare repository"))?;
+            let content = std::fs::read(workdir.join(rel))
+                .map_err(|e| AppError::io("Failed to read file", e).with_path(path))?;
+            (content, None)
+        }
+    };
+
+    let mut options = BlameOptions::new();
+    if let Some(id) = newest {
+        options.newest_commit(id);
+    }
+    // On an unborn branch nothing is committed yet
+    let committed = if newest.is_none() && head_commit(repo)?.is_none() {
+        None
+    } else {
+        match repo.blame_file(rel, Some(&mut options)) {
+            Ok(blame) => Some(blame),
+            // Untracked file
+            Err(e) if newest.is_none() && e.code() == GitErrorCode::NotFound => None,
+            Err(e) => return Err(AppError::git("Failed to blame file", e).with_path(path)),
+        }
+    };
+    let blame = match (&committed, newest) {
+        (Some(blame), None) => Some(
+            blame
+                .blame_buffer(&content)
+                .map_err(|e| AppError::git("Failed to blame file", e).with_path(path))?,
+        ),
+        _ => None,
+    };
+    let blame = blame.as_ref().or(committed.as_ref());
+
+    let text = String::from_utf8_lossy(&content);
+    let total = text.lines().count();
+    let start = request.start_line.unwrap_or(1).max(1);
+    let end = request.end_line.unwrap_or(total).min(total);
+
+    let mut summaries: HashMap<Oid, Option<String>> = HashMap::new();
+    let mut lines = Vec::new();
+    for (index, content) in text.lines().enumerate() {
+        let line = index + 1;
+        if line < start || line > end {
+            continue;
+        }
+
+        let hunk = blame
+            .and_then(|b| b.get_line(line))
+            .filter(|h| !h.final_commit_id().is_zero());
+        let Some(hunk) = hunk else {
+            lines.push(BlameLine {
+                line,
+                content: content.to_string(),
+                commit: None,
+                author_name: None,
+                author_email: None,
+                author_time: None,
+                summary: None,
+                orig_line: None,
+                orig_path: None,
+            });
+            continue;
+        };
+
+        let id = hunk.final_commit_id();
+        let summary = summaries
+            .entry(id)
+            .or_insert_with(|| {
+                repo.find_commit(id)
+                    .ok()
+                    .and_then(|c| c.summary().map(str::to_string))
+            })
+            .clone();
+        let author = hunk.final_signature();
+        lines.push(BlameLine {
+            line,
+            content: content.to_string(),
+            commit: Some(id.to_string()),
+            author_name: author.name().map(str::to_string),
+            author_email: author.email().map(str::to_string),
+            author_time: Some(author.when().seconds() * 1000),
+            summary,
+            orig_line: Some(hunk.orig_start_line() + (line - hunk.final_start_line())),
+            orig_path: hunk.path().map(to_slash_path),
+        });
+    }
+
+    Ok(BlameResult {
+        path: path.to_string(),
+        rev: request.rev.clone(),
+        lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::commit::{commit, CommitOptions, Identity};
+    use crate::git::test_support::{commit_all, init_repo, write};
+
+    #[test]
+    fn test_blame_working_tree_and_revision() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "one\ntwo\n");
+        let first = commit_all(&repo, "initial");
+        write(&root, "a.txt", "zero\none\ntwo\n");
+        let agent = Identity {
+            name: "Nova Agent".to_string(),
+            email: "agent@nova.dev".to_string(),
+        };
+        let second = commit(
+            &repo,
+            "prepend zero",
+            &["a.txt".to_string()],
+            &CommitOptions {
+                author: Some(agent),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        write(&root, "a.txt", "zero\none\ntwo\nthree\n");
+
+        let result = blame(&repo, "a.txt", &BlameRequest::default()).unwrap();
+        let commits: Vec<Option<String>> = result.lines.iter().map(|l| l.commit.clone()).collect();
+        assert_eq!(
+            commits,
+            vec![
+                Some(second.to_string()),
+                Some(first.to_string()),
+                Some(first.to_string()),
+                None
+            ]
+        );
+        assert_eq!(result.lines[0].author_name.as_deref(), Some("Nova Agent"));
+        assert_eq!(result.lines[1].orig_line, Some(1));
+        assert_eq!(result.lines[1].summary.as_deref(), Some("initial"));
+
+        let ranged = blame(
+            &repo,
+            "a.txt",
+            &BlameRequest {
+                rev: Some("HEAD~1".to_string()),
+                start_line: Some(2),
+                end_line: Some(5),
+            },
+        )
+        .unwrap();
+        assert_eq!(ranged.lines.len(), 1);
+        assert_eq!(ranged.lines[0].content, "two");
+        assert_eq!(ranged.lines[0].commit, Some(first.to_string()));
+    }
+
+    #[test]
+    fn test_blame_untracked_file() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "new.txt", "a\nb\n");
+        let result = blame(&repo, "new.txt", &BlameRequest::default()).unwrap();
+        assert_eq!(result.lines.len(), 2);
+        assert!(result.lines.iter().all(|l| l.commit.is_none()));
+
+        write(&root, "a.txt", "a");
+        commit_all(&repo, "initial");
+        write(&root, "other.txt", "x\n");
+        let result = blame(&repo, "other.txt", &BlameRequest::default()).unwrap();
+        assert!(result.lines[0].commit.is_none());
+    }
+}
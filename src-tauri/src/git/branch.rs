@@ -0,0 +1,390 @@
+// Git Branches — R20-02
+// List, create, rename, delete and checkout branches; report HEAD including detached and unborn states
+
+use git2::{Branch, BranchType, ErrorCode as GitErrorCode, Repository};
+use serde::{Deserialize, Serialize};
+
+use super::{commit::head_commit, has_uncommitted_changes, resolve_commit};
+use crate::error::{AppError, AppResult};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BranchKind {
+    Local,
+    Remote,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BranchInfo {
+    /// Short name, e.g. `feature/login` or `origin/main`
+    pub name: String,
+    pub kind: BranchKind,
+    pub is_head: bool,
+    pub commit: Option<String>,
+    pub summary: Option<String>,
+    /// Remote-tracking branch this local branch follows, e.g. `origin/main`
+    pub upstream: Option<String>,
+    pub ahead: Option<usize>,
+    pub behind: Option<usize>,
+}
+
+/// Where HEAD points
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum HeadState {
+    Branch {
+        name: String,
+        commit: String,
+    },
+    /// HEAD points straight at a commit
+    Detached {
+        commit: String,
+    },
+    /// A branch with no commits yet, as in a freshly initialised repository
+    Unborn {
+        name: String,
+    },
+}
+
+impl HeadState {
+    /// The checked-out branch name, if HEAD is on a branch
+    pub fn branch_name(&self) -> Option<&str> {
+        match self {
+            HeadState::Branch { name, .. } | HeadState::Unborn { name } => Some(name),
+            HeadState::Detached { .. } => None,
+        }
+    }
+}
+
+pub fn head_state(repo: &Repository) -> AppResult<HeadState> {
+    match repo.head() {
+        Ok(head) => {
+            let commit = head.target().map(|id| id.to_string()).unwrap_or_default();
+            if head.is_branch() {
+                Ok(HeadState::Branch {
+                    name: head.shorthand().unwrap_or_default().to_string(),
+                    commit,
+                })
+            } else {
+                Ok(HeadState::Detached { commit })
+            }
+        }
+        Err(e) if e.code() == GitErrorCode::UnbornBranch || e.code() == GitErrorCode::NotFound => {
+            let head = repo
+                .find_reference("HEAD")
+                .map_err(|e| AppError::git("Failed to read HEAD", e))?;
+            let target = head.symbolic_target().unwrap_or("HEAD");
+            Ok(HeadState::Unborn {
+                name: target.trim_start_matches("refs/heads/").to_string(),
+            })
+        }
+        Err(e) => Err(AppError::git("Failed to read HEAD", e)),
+    }
+}
+
+/// Local branches followed by remote-tracking branches, each sorted by name
+pub fn list_branches(repo: &Repository) -> AppResult<Vec<BranchInfo>> {
+    let mut branches = Vec::new();
+    for kind in [BranchType::Local, BranchType::Remote] {
+        let iter = repo
+            .branches(Some(kind))
+            .map_err(|e| AppError::git("Failed to list branches", e))?;
+        let mut group = Vec::new();
+        for item in iter {
+            let (branch, _) = item.map_err(|e| AppError::git("Failed to read branch", e))?;
+            // `origin/HEAD` is a pointer to the default branch, not a branch of its own
+            if branch.get().symbolic_target().is_some() {
+                continue;
+            }
+            group.push(branch_info(repo, &branch)?);
+        }
+        group.sort_by(|a, b| a.name.cmp(&b.name));
+        branches.extend(group);
+    }
+    Ok(branches)
+}
+
+/// Create `name` at `start_point` (default HEAD), optionally checking it out
+pub fn create_branch(
+    repo: &Repository,
+    name: &str,
+    start_point: Option<&str>,
+    checkout: bool,
+) -> AppResult<BranchInfo> {
+    validate_name(name)?;
+    let start = match start_point {
+        Some(spec) => resolve_commit(repo, spec)?,
+        None => head_commit(repo)?.ok_or_else(|| {
+            AppError::invalid_input("Cannot create a branch before the first commit")
+        })?,
+    };
+
+    let branch = repo
+        .branch(name, &start, false)
+        .map_err(|e| AppError::git(&format!("Failed to create branch '{}'", name), e))?;
+
+    if checkout {
+        let at_head = head_commit(repo)?.map(|c| c.id()) == Some(start.id());
+        if at_head {
+            // Same commit, so the working tree is already right; just move HEAD
+            let refname = branch.get().name().unwrap_or_default().to_string();
+            repo.set_head(&refname)
+                .map_err(|e| AppError::git("Failed to update HEAD", e))?;
+        } else {
+            checkout_branch(repo, name, false)?;
+        }
+    }
+
+    let branch = find_local(repo, name)?;
+    branch_info(repo, &branch)
+}
+
+pub fn rename_branch(
+    repo: &Repository,
+    old_name: &str,
+    new_name: &str,
+    force: bool,
+) -> AppResult<BranchInfo> {
+    validate_name(new_name)?;
+    let mut branch = find_local(repo, old_name)?;
+    let renamed = branch
+        .rename(new_name, force)
+        .map_err(|e| AppError::git(&format!("Failed to rename branch '{}'", old_name), e))?;
+    branch_info(repo, &renamed)
+}
+
+/// Delete a local branch. Unless forced, it must be merged into HEAD or its upstream.
+pub fn delete_branch(repo: &Repository, name: &str, force: bool) -> AppResult<()> {
+    let mut branch = find_local(repo, name)?;
+    if branch.is_head() {
+        return Err(AppError::conflict(format!(
+            "Cannot delete the checked-out branch '{}'",
+            name
+        )));
+    }
+
+    if !force {
+        if let Some(tip) = branch.get().target() {
+            let mut merged_into = Vec::new();
+            if let Some(head) = head_commit(repo)? {
+                merged_into.push(head.id());
+            }
+            if let Some(upstream) = branch.upstream().ok().and_then(|u| u.get().target()) {
+                merged_into.push(upstream);
+            }
+            let merged = merged_into
+                .iter()
+                .any(|&base| base == tip || repo.graph_descendant_of(base, tip).unwrap_or(false));
+            if !merged {
+                return Err(AppError::conflict(format!(
+                    "Branch '{}' is not fully merged; force the delete to discard it",
+                    name
+                )));
+            }
+        }
+    }
+
+    branch
+        .delete()
+        .map_err(|e| AppError::git(&format!("Failed to delete branch '{}'", name), e))
+}
+
+/// Check out a local branch, or create a tracking branch for a remote one
+/// (`origin/feature` -> `feature`). Refuses when the working tree has
+/// uncommitted changes unless `force` is set, in which case they are discarded.
+pub fn checkout_branch(repo: &Repository, name: &str, force: bool) -> AppResult<HeadState> {
+    if !force && has_uncommitted_changes(repo)? {
+        return Err(AppError::conflict(
+            "Working tree has uncommitted changes; commit or stash them, or force the checkout",
+        ));
+    }
+
+    let branch = match repo.find_branch(name, BranchType::Local) {
+        Ok(branch) => branch,
+        Err(e) if e.code() == GitErrorCode::NotFound => track_remote(repo, name)?,
+        Err(e) => return Err(AppError::git("Failed to find branch", e)),
+    };
+
+    let commit = branch
+        .get()
+        .peel_to_commit()
+        .map_err(|e| AppError::git("Failed to read branch commit", e))?;
+    let mut options = git2::build::CheckoutBuilder::new();
+    if force {
+        options.force();
+    } else {
+        options.safe();
+    }
+    repo.checkout_tree(commit.as_object(), Some(&mut options))
+        .map_err(|e| AppError::git(&format!("Failed to check out '{}'", name), e))?;
+
+    let refname = branch.get().name().unwrap_or_default().to_string();
+    repo.set_head(&refname)
+        .map_err(|e| AppError::git("Failed to update HEAD", e))?;
+
+    head_state(repo)
+}
+
+fn track_remote<'r>(repo: &'r Repository, name: &str) -> AppResult<Branch<'r>> {
+    let remote = repo.find_branch(name, BranchType::Remote).map_err(|e| {
+        if e.code() == GitErrorCode::NotFound {
+            AppError::not_found(format!("Branch '{}' does not exist", name))
+        } else {
+            AppError::git("Failed to find branch", e)
+        }
+    })?;
+    let local_name = name.split_once('/').map(|(_, rest)| rest).unwrap_or(name);
+    let commit = remote
+        .get()
+        .peel_to_commit()
+        .map_err(|e| AppError::git("Failed to read branch commit", e))?;
+
+    let mut local = repo
+        .branch(local_name, &commit, false)
+        .map_err(|e| AppError::git(&format!("Failed to create branch '{}'", local_name), e))?;
+    local
+        .set_upstream(Some(name))
+        .map_err(|e| AppError::git("Failed to set upstream", e))?;
+    Ok(local)
+}
+
+fn find_local<'r>(repo: &'r Repository, name: &str) -> AppResult<Branch<'r>> {
+    repo.find_branch(name, BranchType::Local).map_err(|e| {
+        if e.code() == GitErrorCode::NotFound {
+            AppError::not_found(format!("Branch '{}' does not exist", name))
+        } else {
+            AppError::git("Failed to find branch", e)
+        }
+    })
+}
+
+pub(crate) fn validate_name(name: &str) -> AppResult<()> {
+    match Branch::name_is_valid(name) {
+        Ok(true) => Ok(()),
+        _ => Err(AppError::invalid_input(format!(
+            "Invalid branch name: '{}'",
+            name
+        ))),
+    }
+}
+
+fn branch_info(repo: &Repository, branch: &Branch) -> AppResult<BranchInfo> {
+    let name = branch
+        .name()
+        .map_err(|e| AppError::git("Failed to read branch name", e))?
+        .unwrap_or_default()
+        .to_string();
+    let kind = if branch.get().is_remote() {
+        BranchKind::Remote
+    } else {
+        BranchKind::Local
+    };
+    let commit = branch.get().peel_to_commit().ok();
+
+    let mut info = BranchInfo {
+        name,
+        kind,
+        is_head: branch.is_head(),
+        commit: commit.as_ref().map(|c| c.id().to_string()),
+        summary: commit
+            .as_ref()
+            .and_then(|c| c.summary().map(|s| s.to_string())),
+        upstream: None,
+        ahead: None,
+        behind: None,
+    };
+
+    if kind == BranchKind::Local {
+        if let Some((upstream, counts)) = upstream_of(repo, branch) {
+            info.upstream = Some(upstream);
+            info.ahead = counts.map(|(ahead, _)| ahead);
+            info.behind = counts.map(|(_, behind)| behind);
+        }
+    }
+
+    Ok(info)
+}
+
+/// The upstream of a local branch and, when both resolve, how many commits the
+/// branch is ahead of and behind it
+pub(crate) fn upstream_of(
+    repo: &Repository,
+    branch: &Branch,
+) -> Option<(String, Option<(usize, usize)>)> {
+    let upstream = branch.upstream().ok()?;
+    let name = upstream.name().ok().flatten()?.to_string();
+    let counts = match (branch.get().target(), upstream.get().target()) {
+        (Some(local), Some(remote)) => repo.graph_ahead_behind(local, remote).ok(),
+        _ => None,
+    };
+    Some((name, counts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{commit_all, init_repo, write};
+    use std::fs;
+
+    #[test]
+    fn test_head_state_unborn_branch_and_detached() {
+        let (_dir, root, repo) = init_repo();
+        assert!(matches!(
+            head_state(&repo).unwrap(),
+            HeadState::Unborn { .. }
+        ));
+
+        write(&root, "a.txt", "a");
+        let first = commit_all(&repo, "initial");
+        let state = head_state(&repo).unwrap();
+        assert!(matches!(state, HeadState::Branch { .. }));
+
+        repo.set_head_detached(first).unwrap();
+        assert_eq!(
+            head_state(&repo).unwrap(),
+            HeadState::Detached {
+                commit: first.to_string()
+            }
+        );
+        assert_eq!(head_state(&repo).unwrap().branch_name(), None);
+    }
+
+    #[test]
+    fn test_create_checkout_rename_delete() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "a");
+        commit_all(&repo, "initial");
+        let main = head_state(&repo)
+            .unwrap()
+            .branch_name()
+            .unwrap()
+            .to_string();
+
+        let created = create_branch(&repo, "feature", None, true).unwrap();
+        assert!(created.is_head);
+        write(&root, "a.txt", "feature change");
+        commit_all(&repo, "feature work");
+
+        // Dirty trees are refused unless forced
+        write(&root, "a.txt", "uncommitted");
+        let err = checkout_branch(&repo, &main, false).unwrap_err();
+        assert_eq!(err.code, crate::error::ErrorCode::Conflict);
+        checkout_branch(&repo, &main, true).unwrap();
+        assert_eq!(fs::read_to_string(root.join("a.txt")).unwrap(), "a");
+
+        let renamed = rename_branch(&repo, "feature", "feature-2", false).unwrap();
+        assert_eq!(renamed.name, "feature-2");
+
+        // Not merged into HEAD yet
+        assert!(delete_branch(&repo, "feature-2", false).is_err());
+        delete_branch(&repo, "feature-2", true).unwrap();
+        let names: Vec<String> = list_branches(&repo)
+            .unwrap()
+            .into_iter()
+            .map(|b| b.name)
+            .collect();
+        assert_eq!(names, vec![main]);
+
+        assert!(create_branch(&repo, "bad..name", None, false).is_err());
+    }
+}
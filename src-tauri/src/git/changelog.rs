@@ -0,0 +1,334 @@
+// Git Changelog — R20-02
+// Release notes between two refs, grouped by conventional-commit type
+
+use git2::{Commit, Oid, Repository, Sort};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::resolve_commit;
+use crate::error::{AppError, AppResult};
+
+/// Section order and headings; any other type lands in "Other Changes"
+const SECTIONS: [(&str, &str); 11] = [
+    ("feat", "Features"),
+    ("fix", "Bug Fixes"),
+    ("perf", "Performance"),
+    ("refactor", "Refactoring"),
+    ("revert", "Reverts"),
+    ("docs", "Documentation"),
+    ("test", "Tests"),
+    ("build", "Build"),
+    ("ci", "CI"),
+    ("style", "Style"),
+    ("chore", "Chores"),
+];
+const OTHER_KIND: &str = "other";
+const OTHER_TITLE: &str = "Other Changes";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ChangelogRequest {
+    /// Exclusive start; defaults to the nearest tag behind `to`, or the root
+    pub from: Option<String>,
+    /// Inclusive end (default HEAD)
+    pub to: Option<String>,
+    /// Include merge commits (default false)
+    #[serde(default)]
+    pub include_merges: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChangelogEntry {
+    pub id: String,
+    pub short_id: String,
+    pub kind: String,
+    pub scope: Option<String>,
+    pub description: String,
+    pub breaking: bool,
+    pub author_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChangelogSection {
+    /// Conventional-commit type, e.g. `feat`, or `other`
+    pub kind: String,
+    pub title: String,
+    pub entries: Vec<ChangelogEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Changelog {
+    /// The ref the changelog starts after; `None` when it covers all history
+    pub from: Option<String>,
+    pub to: String,
+    /// Non-empty sections in display order
+    pub sections: Vec<ChangelogSection>,
+    /// Entries marked with `!` or a `BREAKING CHANGE:` footer, also listed in their sections
+    pub breaking: Vec<ChangelogEntry>,
+    /// The same content rendered as Markdown
+    pub markdown: String,
+}
+
+pub fn changelog(repo: &Repository, request: &ChangelogRequest) -> AppResult<Changelog> {
+    let to_spec = request.to.as_deref().unwrap_or("HEAD");
+    let to = resolve_commit(repo, to_spec)?;
+    let from = match &request.from {
+        Some(spec) => Some((spec.clone(), resolve_commit(repo, spec)?.id())),
+        None => nearest_tag(repo, to.id())?,
+    };
+
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| AppError::git("Failed to start history walk", e))?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+        .map_err(|e| AppError::git("Failed to sort history", e))?;
+    walk.push(to.id())
+        .map_err(|e| AppError::git("Failed to start history walk", e))?;
+    if let Some((_, from_id)) = &from {
+        walk.hide(*from_id)
+            .map_err(|e| AppError::git("Failed to limit history walk", e))?;
+    }
+
+    let mut entries = Vec::new();
+    for id in walk {
+        let id = id.map_err(|e| AppError::git("Failed to walk history", e))?;
+        let commit = repo
+            .find_commit(id)
+            .map_err(|e| AppError::git("Failed to read commit", e))?;
+        if commit.parent_count() > 1 && !request.include_merges {
+            continue;
+        }
+        entries.push(entry(&commit));
+    }
+
+    let mut sections: Vec<ChangelogSection> = SECTIONS
+        .iter()
+        .chain(std::iter::once(&(OTHER_KIND, OTHER_TITLE)))
+        .map(|(kind, title)| ChangelogSection {
+            kind: kind.to_string(),
+            title: title.to_string(),
+            entries: Vec::new(),
+        })
+        .collect();
+    let breaking: Vec<ChangelogEntry> = entries.iter().filter(|e| e.breaking).cloned().collect();
+    for entry in entries {
+        let index = sections
+            .iter()
+            .position(|s| s.kind == entry.kind)
+            .unwrap_or(sections.len() - 1);
+        sections[index].entries.push(entry);
+    }
+    sections.retain(|s| !s.entries.is_empty());
+
+    let markdown = render(&sections, &breaking);
+    Ok(Changelog {
+        from: from.map(|(name, _)| name),
+        to: to_spec.to_string(),
+        sections,
+        breaking,
+        markdown,
+    })
+}
+
+/// The closest tagged ancestor of `to`, not counting `to` itself
+fn nearest_tag(repo: &Repository, to: Oid) -> AppResult<Option<(String, Oid)>> {
+    let mut tagged: HashMap<Oid, String> = HashMap::new();
+    let names = repo
+        .tag_names(None)
+        .map_err(|e| AppError::git("Failed to list tags", e))?;
+    for name in names.iter().flatten() {
+        let commit = repo
+            .revparse_single(&format!("refs/tags/{}", name))
+            .and_then(|object| object.peel_to_commit());
+        if let Ok(commit) = commit {
+            tagged
+                .entry(commit.id())
+                .or_insert_with(|| name.to_string());
+        }
+    }
+    if tagged.is_empty() {
+        return Ok(None);
+    }
+
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| AppError::git("Failed to start history walk", e))?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+        .map_err(|e| AppError::git("Failed to sort history", e))?;
+    walk.push(to)
+        .map_err(|e| AppError::git("Failed to start history walk", e))?;
+    for id in walk {
+        let id = id.map_err(|e| AppError::git("Failed to walk history", e))?;
+        if id == to {
+            continue;
+        }
+        if let Some(name) = tagged.get(&id) {
+            return Ok(Some((name.clone(), id)));
+        }
+    }
+    Ok(None)
+}
+
+fn entry(commit: &Commit) -> ChangelogEntry {
+    let message = commit.message().unwrap_or_default();
+    let summary = commit.summary().unwrap_or_default();
+    let id = commit.id().to_string();
+    let (kind, scope, description, bang) = match parse_summary(summary) {
+        Some((kind, scope, description, bang)) => (kind, scope, description, bang),
+        None => (
+            OTHER_KIND.to_string(),
+            None,
+            summary.trim().to_string(),
+            false,
+        ),
+    };
+    let footer_breaking = message
+        .lines()
+        .skip(1)
+        .any(|line| line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:"));
+    ChangelogEntry {
+        short_id: id[..7.min(id.len())].to_string(),
+        id,
+        kind,
+        scope,
+        description,
+        breaking: bang || footer_breaking,
+        author_name: commit.author().name().unwrap_or_default().to_string(),
+    }
+}
+
+/// Split `type(scope)!: description` into its parts; `None` if the summary
+/// is not a conventional commit
+fn parse_summary(summary: &str) -> Option<(String, Option<String>, String, bool)> {
+    let (header, description) = summary.split_once(':')?;
+    let description = description.trim();
+    let (header, bang) = match header.strip_suffix('!') {
+        Some(header) => (header, true),
+        None => (header, false),
+    };
+    let (kind, scope) = match header.split_once('(') {
+        Some((kind, rest)) => {
+            let scope = rest.strip_suffix(')')?.trim();
+            (kind, (!scope.is_empty()).then(|| scope.to_string()))
+        }
+        None => (header, None),
+    };
+    let valid_kind = !kind.is_empty() && kind.chars().all(|c| c.is_ascii_alphabetic() || c == '-');
+    if !valid_kind || description.is_empty() {
+        return None;
+    }
+    Some((
+        kind.to_ascii_lowercase(),
+        scope,
+        description.to_string(),
+        bang,
+    ))
+}
+
+fn render(sections: &[ChangelogSection], breaking: &[ChangelogEntry]) -> String {
+    let line = |entry: &ChangelogEntry| match &entry.scope {
+        Some(scope) => format!(
+            "- **{}:** {} ({})\n",
+            scope, entry.description, entry.short_id
+        ),
+        None => format!("- {} ({})\n", entry.description, entry.short_id),
+    };
+
+    let mut markdown = String::new();
+    if !breaking.is_empty() {
+        markdown.push_str("### Breaking Changes\n\n");
+        for entry in breaking {
+            markdown.push_str(&line(entry));
+        }
+        markdown.push('\n');
+    }
+    for section in sections {
+        markdown.push_str(&format!("### {}\n\n", section.title));
+        for entry in &section.entries {
+            markdown.push_str(&line(entry));
+        }
+        markdown.push('\n');
+    }
+    markdown.truncate(markdown.trim_end().len());
+    if !markdown.is_empty() {
+        markdown.push('\n');
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{commit_all, init_repo, write};
+
+    #[test]
+    fn test_parse_summary() {
+        assert_eq!(
+            parse_summary("feat(editor)!: split panes"),
+            Some((
+                "feat".to_string(),
+                Some("editor".to_string()),
+                "split panes".to_string(),
+                true
+            ))
+        );
+        assert_eq!(
+            parse_summary("Fix: typo"),
+            Some(("fix".to_string(), None, "typo".to_string(), false))
+        );
+        assert_eq!(parse_summary("Update README"), None);
+        assert_eq!(parse_summary("see http://example.com: docs"), None);
+        assert_eq!(parse_summary("feat(: broken"), None);
+    }
+
+    #[test]
+    fn test_changelog_since_nearest_tag() {
+        let (_dir, root, repo) = init_repo();
+        let mut step = 0;
+        let mut commit = |message: &str| {
+            step += 1;
+            write(&root, "a.txt", &format!("{}\n", step));
+            commit_all(&repo, message)
+        };
+        commit("feat: first release");
+        let released = commit("chore: bump version");
+        repo.tag_lightweight("v1.0.0", &repo.find_object(released, None).unwrap(), false)
+            .unwrap();
+        commit("fix(git): handle unborn HEAD");
+        commit("feat(editor): split panes\n\nBREAKING CHANGE: layout API changed");
+        commit("Tidy imports");
+        commit("feat: word diffs");
+
+        let log = changelog(&repo, &ChangelogRequest::default()).unwrap();
+        assert_eq!(log.from.as_deref(), Some("v1.0.0"));
+        let kinds: Vec<_> = log.sections.iter().map(|s| s.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["feat", "fix", "other"]);
+        let features: Vec<_> = log.sections[0]
+            .entries
+            .iter()
+            .map(|e| e.description.as_str())
+            .collect();
+        assert_eq!(features, vec!["word diffs", "split panes"]);
+        assert_eq!(log.breaking.len(), 1);
+        assert_eq!(log.breaking[0].scope.as_deref(), Some("editor"));
+        assert!(log
+            .markdown
+            .starts_with("### Breaking Changes\n\n- **editor:** split panes"));
+        assert!(log
+            .markdown
+            .contains("### Bug Fixes\n\n- **git:** handle unborn HEAD"));
+        assert!(log.markdown.contains("### Other Changes\n\n- Tidy imports"));
+
+        // Explicit bounds; from the root when there is no earlier tag
+        let all = changelog(
+            &repo,
+            &ChangelogRequest {
+                to: Some("v1.0.0".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(all.from, None);
+        let kinds: Vec<_> = all.sections.iter().map(|s| s.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["feat", "chore"]);
+    }
+}
@@ -0,0 +1,470 @@
+// Git Checkpoints — R20-02
+// Snapshots of the whole working tree under refs/nova/checkpoints, taken before agent runs
+
+use git2::build::CheckoutBuilder;
+use git2::{Index, IndexEntry, IndexTime, Oid, Reference, Repository, Signature, StatusOptions};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::commit::head_commit;
+use super::diff::{diff, DiffRequest, DiffResult, DiffTarget};
+use super::hunks::working_mode;
+use crate::error::{AppError, AppResult};
+use crate::workspace::INTERNAL_DIRS;
+
+/// Hidden namespace for checkpoint commits; not a branch, tag or stash
+pub const CHECKPOINT_REF_PREFIX: &str = "refs/nova/checkpoints/";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub run_id: String,
+    pub commit: String,
+    pub message: String,
+    /// HEAD when the checkpoint was taken; `None` before the first commit
+    pub base: Option<String>,
+    /// Unix milliseconds
+    pub time: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RestoreResult {
+    /// Paths written back or removed
+    pub restored: Vec<String>,
+    /// Checkpoint of the working tree just before the restore, to undo it;
+    /// `None` when there was nothing to restore
+    pub backup: Option<Checkpoint>,
+}
+
+/// Commit the working tree as it is on disk, untracked files included and
+/// ignored files left out. HEAD, the index and the stash are not touched.
+pub fn create(repo: &Repository, run_id: &str, message: Option<&str>) -> AppResult<Checkpoint> {
+    let refname = checkpoint_ref(run_id)?;
+    if repo.find_reference(&refname).is_ok() {
+        return Err(AppError::already_exists(format!(
+            "Checkpoint '{}' already exists",
+            run_id
+        )));
+    }
+
+    let base = head_commit(repo)?;
+    let tree_id = snapshot_tree(repo, base.as_ref())?;
+    let tree = repo
+        .find_tree(tree_id)
+        .map_err(|e| AppError::git("Failed to read checkpoint tree", e))?;
+    // Checkpoints must never fail for want of a configured identity
+    let signature = repo
+        .signature()
+        .or_else(|_| Signature::now("Nova", "nova@localhost"))
+        .map_err(|e| AppError::git("Failed to create signature", e))?;
+    let message = match message.map(str::trim).filter(|m| !m.is_empty()) {
+        Some(message) => message.to_string(),
+        None => format!("Checkpoint before run {}", run_id),
+    };
+    let parents: Vec<&git2::Commit> = base.iter().collect();
+    let commit = repo
+        .commit(None, &signature, &signature, &message, &tree, &parents)
+        .map_err(|e| AppError::git("Failed to commit checkpoint", e))?;
+    let reference = repo
+        .reference(&refname, commit, false, &format!("checkpoint: {}", message))
+        .map_err(|e| AppError::git("Failed to record checkpoint", e))?;
+
+    info(&reference)
+}
+
+/// Checkpoints, newest first
+pub fn list(repo: &Repository) -> AppResult<Vec<Checkpoint>> {
+    let references = repo
+        .references_glob(&format!("{}*", CHECKPOINT_REF_PREFIX))
+        .map_err(|e| AppError::git("Failed to list checkpoints", e))?;
+    let mut checkpoints = Vec::new();
+    for reference in references {
+        let reference = reference.map_err(|e| AppError::git("Failed to read checkpoint", e))?;
+        checkpoints.push(info(&reference)?);
+    }
+    checkpoints.sort_by(|a, b| b.time.cmp(&a.time).then_with(|| a.run_id.cmp(&b.run_id)));
+    Ok(checkpoints)
+}
+
+/// Changes from the checkpoint to the working tree as it is now
+pub fn diff_to_workdir(
+    repo: &Repository,
+    run_id: &str,
+    request: &DiffRequest,
+) -> AppResult<DiffResult> {
+    let commit = find(repo, run_id)?;
+    diff(
+        repo,
+        &DiffTarget::RevisionToWorkingTree {
+            revision: commit.to_string(),
+        },
+        request,
+    )
+}
+
+/// Write the checkpoint's version of `paths` (every file when empty) back to
+/// the working tree. Files created since the checkpoint are removed; ignored
+/// files, `.nova`, HEAD and the index are left alone. The working tree is
+/// first saved as checkpoint `<run_id>-before-restore`, so nothing is lost.
+pub fn restore(repo: &Repository, run_id: &str, paths: &[String]) -> AppResult<RestoreResult> {
+    let commit = find(repo, run_id)?;
+    let changes = diff_to_workdir(
+        repo,
+        run_id,
+        &DiffRequest {
+            paths: paths.to_vec(),
+            ..Default::default()
+        },
+    )?;
+    let mut changed: Vec<String> = changes
+        .files
+        .iter()
+        .flat_map(|f| f.old_path.iter().chain(f.new_path.iter()))
+        .filter(|path| !is_internal(path))
+        .cloned()
+        .collect();
+    changed.sort();
+    changed.dedup();
+    if changed.is_empty() {
+        return Ok(RestoreResult {
+            restored: changed,
+            backup: None,
+        });
+    }
+    let backup = create(
+        repo,
+        &backup_run_id(repo, run_id),
+        Some(&format!("Before restoring checkpoint {}", run_id)),
+    )?;
+
+    let target = repo
+        .find_object(commit, None)
+        .map_err(|e| AppError::git("Failed to read checkpoint", e))?;
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force().remove_untracked(true).update_index(false);
+    for path in &changed {
+        checkout.path(path);
+    }
+    repo.checkout_tree(&target, Some(&mut checkout))
+        .map_err(|e| AppError::git(&format!("Failed to restore checkpoint '{}'", run_id), e))?;
+    Ok(RestoreResult {
+        restored: changed,
+        backup: Some(backup),
+    })
+}
+
+/// `<run_id>-before-restore`, numbered when an earlier restore already took it
+fn backup_run_id(repo: &Repository, run_id: &str) -> String {
+    let base = format!("{}-before-restore", run_id);
+    let mut id = base.clone();
+    let mut n = 1;
+    while repo
+        .find_reference(&format!("{}{}", CHECKPOINT_REF_PREFIX, id))
+        .is_ok()
+    {
+        n += 1;
+        id = format!("{}-{}", base, n);
+    }
+    id
+}
+
+pub fn delete(repo: &Repository, run_id: &str) -> AppResult<()> {
+    let refname = checkpoint_ref(run_id)?;
+    let mut reference = repo
+        .find_reference(&refname)
+        .map_err(|e| AppError::git(&format!("Failed to find checkpoint '{}'", run_id), e))?;
+    reference
+        .delete()
+        .map_err(|e| AppError::git(&format!("Failed to delete checkpoint '{}'", run_id), e))
+}
+
+fn find(repo: &Repository, run_id: &str) -> AppResult<Oid> {
+    let refname = checkpoint_ref(run_id)?;
+    repo.refname_to_id(&refname)
+        .map_err(|e| AppError::git(&format!("Failed to find checkpoint '{}'", run_id), e))
+}
+
+fn checkpoint_ref(run_id: &str) -> AppResult<String> {
+    let refname = format!("{}{}", CHECKPOINT_REF_PREFIX, run_id);
+    if run_id.is_empty() || run_id.contains('/') || !Reference::is_valid_name(&refname) {
+        return Err(AppError::invalid_input(format!(
+            "Invalid checkpoint run id '{}'",
+            run_id
+        )));
+    }
+    Ok(refname)
+}
+
+fn info(reference: &Reference) -> AppResult<Checkpoint> {
+    let commit = reference
+        .peel_to_commit()
+        .map_err(|e| AppError::git("Failed to read checkpoint", e))?;
+    let name = reference.name().unwrap_or_default();
+    Ok(Checkpoint {
+        run_id: name
+            .strip_prefix(CHECKPOINT_REF_PREFIX)
+            .unwrap_or(name)
+            .to_string(),
+        commit: commit.id().to_string(),
+        message: commit.message().unwrap_or_default().to_string(),
+        base: commit.parent_id(0).ok().map(|id| id.to_string()),
+        time: commit.time().seconds() * 1000,
+    })
+}
+
+/// Build a tree of the working tree in a scratch index seeded from HEAD, so
+/// the repository's own index is never read into or written
+fn snapshot_tree(repo: &Repository, base: Option<&git2::Commit>) -> AppResult<Oid> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| AppError::invalid_input("Bare repositories have no working tree"))?;
+    let mut index = Index::new().map_err(|e| AppError::git("Failed to create index", e))?;
+    if let Some(base) = base {
+        let tree = base
+            .tree()
+            .map_err(|e| AppError::git("Failed to read HEAD tree", e))?;
+        index
+            .read_tree(&tree)
+            .map_err(|e| AppError::git("Failed to read HEAD tree", e))?;
+    }
+
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false)
+        .exclude_submodules(true);
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .map_err(|e| AppError::git("Failed to get statuses", e))?;
+
+    for entry in statuses.iter() {
+        let Some(path) = entry.path() else {
+            continue;
+        };
+        // Nested repositories show up as a directory; leave them out, along
+        // with the app's own history and trash
+        if path.ends_with('/') || is_internal(path) {
+            continue;
+        }
+        let full = workdir.join(path);
+        match fs::symlink_metadata(&full) {
+            Ok(meta) => {
+                let (mode, id) = if meta.file_type().is_symlink() {
+                    let target = fs::read_link(&full)
+                        .map_err(|e| AppError::io("Failed to read link", e).with_path(path))?;
+                    let id = repo
+                        .blob(target.to_string_lossy().as_bytes())
+                        .map_err(|e| AppError::git("Failed to store link", e).with_path(path))?;
+                    (0o120000, id)
+                } else {
+                    let id = repo
+                        .blob_path(&full)
+                        .map_err(|e| AppError::git("Failed to store file", e).with_path(path))?;
+                    (working_mode(repo, path), id)
+                };
+                index
+                    .add(&IndexEntry {
+                        ctime: IndexTime::new(0, 0),
+                        mtime: IndexTime::new(0, 0),
+                        dev: 0,
+                        ino: 0,
+                        mode,
+                        uid: 0,
+                        gid: 0,
+                        file_size: 0,
+                        id,
+                        flags: 0,
+                        flags_extended: 0,
+                        path: path.as_bytes().to_vec(),
+                    })
+                    .map_err(|e| AppError::git("Failed to add file", e).with_path(path))?;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let _ = index.remove_path(Path::new(path));
+            }
+            Err(e) => return Err(AppError::io("Failed to read file", e).with_path(path)),
+        }
+    }
+
+    index
+        .write_tree_to(repo)
+        .map_err(|e| AppError::git("Failed to write checkpoint tree", e))
+}
+
+/// Whether a repository-relative path lies in `.git` or `.nova`, which are
+/// excluded from status only once the app has written there
+fn is_internal(path: &str) -> bool {
+    let first = path.split('/').next().unwrap_or_default();
+    INTERNAL_DIRS.contains(&first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::git::test_support::{commit_all, init_repo, write};
+
+    fn index_tree(repo: &Repository) -> Oid {
+        repo.index().unwrap().write_tree().unwrap()
+    }
+
+    #[test]
+    fn test_create_leaves_head_index_and_stash_alone() {
+        let (_dir, root, mut repo) = init_repo();
+        write(&root, "a.txt", "one\n");
+        write(&root, "gone.txt", "bye\n");
+        let head = commit_all(&repo, "initial");
+        write(&root, "a.txt", "staged\n");
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        write(&root, "a.txt", "working\n");
+        write(&root, "notes/todo.txt", "untracked\n");
+        fs::remove_file(root.join("gone.txt")).unwrap();
+        let staged = index_tree(&repo);
+
+        let checkpoint = create(&repo, "run-1", None).unwrap();
+        assert_eq!(checkpoint.run_id, "run-1");
+        assert_eq!(checkpoint.base, Some(head.to_string()));
+        assert_eq!(checkpoint.message, "Checkpoint before run run-1");
+
+        assert_eq!(repo.head().unwrap().target(), Some(head));
+        assert_eq!(index_tree(&repo), staged);
+        let mut stashes = 0;
+        repo.stash_foreach(|_, _, _| {
+            stashes += 1;
+            true
+        })
+        .unwrap();
+        assert_eq!(stashes, 0);
+
+        let tree = repo
+            .find_commit(Oid::from_str(&checkpoint.commit).unwrap())
+            .unwrap()
+            .tree()
+            .unwrap();
+        let content = |path: &str| {
+            let entry = tree.get_path(Path::new(path)).unwrap();
+            let blob = repo.find_blob(entry.id()).unwrap();
+            String::from_utf8(blob.content().to_vec()).unwrap()
+        };
+        assert_eq!(content("a.txt"), "working\n");
+        assert_eq!(content("notes/todo.txt"), "untracked\n");
+        assert!(tree.get_path(Path::new("gone.txt")).is_err());
+
+        let err = create(&repo, "run-1", None).unwrap_err();
+        assert_eq!(err.code, ErrorCode::AlreadyExists);
+        assert_eq!(
+            create(&repo, "../run", None).unwrap_err().code,
+            ErrorCode::InvalidInput
+        );
+        assert_eq!(list(&repo).unwrap(), vec![checkpoint]);
+        // Checkpoints are not branches
+        assert_eq!(repo.branches(None).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_diff_and_restore() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "one\n");
+        write(&root, "b.txt", "two\n");
+        let head = commit_all(&repo, "initial");
+        write(&root, "scratch.txt", "untracked\n");
+        let staged = index_tree(&repo);
+        create(&repo, "run-1", Some("before refactor")).unwrap();
+
+        write(&root, "a.txt", "agent edit\n");
+        write(&root, "b.txt", "agent edit\n");
+        write(&root, "new.txt", "agent file\n");
+        fs::remove_file(root.join("scratch.txt")).unwrap();
+
+        let changes = diff_to_workdir(&repo, "run-1", &DiffRequest::default()).unwrap();
+        let mut paths: Vec<_> = changes
+            .files
+            .iter()
+            .map(|f| f.new_path.clone().or(f.old_path.clone()).unwrap())
+            .collect();
+        paths.sort();
+        assert_eq!(paths, vec!["a.txt", "b.txt", "new.txt", "scratch.txt"]);
+
+        let restored = restore(&repo, "run-1", &["a.txt".to_string()]).unwrap();
+        assert_eq!(restored.restored, vec!["a.txt"]);
+        assert_eq!(restored.backup.unwrap().run_id, "run-1-before-restore");
+        assert_eq!(fs::read_to_string(root.join("a.txt")).unwrap(), "one\n");
+        assert_eq!(
+            fs::read_to_string(root.join("b.txt")).unwrap(),
+            "agent edit\n"
+        );
+
+        let restored = restore(&repo, "run-1", &[]).unwrap();
+        assert_eq!(restored.restored, vec!["b.txt", "new.txt", "scratch.txt"]);
+        assert_eq!(fs::read_to_string(root.join("b.txt")).unwrap(), "two\n");
+        assert_eq!(
+            fs::read_to_string(root.join("scratch.txt")).unwrap(),
+            "untracked\n"
+        );
+        assert!(!root.join("new.txt").exists());
+        assert_eq!(repo.head().unwrap().target(), Some(head));
+        assert_eq!(index_tree(&repo), staged);
+        let again = restore(&repo, "run-1", &[]).unwrap();
+        assert!(again.restored.is_empty() && again.backup.is_none());
+
+        // The agent's work overwritten by the restore is kept in the backup
+        let backup = restored.backup.unwrap();
+        assert_eq!(backup.run_id, "run-1-before-restore-2");
+        let paths = vec!["b.txt".to_string(), "new.txt".to_string()];
+        restore(&repo, &backup.run_id, &paths).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("b.txt")).unwrap(),
+            "agent edit\n"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("new.txt")).unwrap(),
+            "agent file\n"
+        );
+    }
+
+    #[test]
+    fn test_internal_dirs_are_left_alone() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "one\n");
+        commit_all(&repo, "initial");
+        // Written directly, so `.nova` is not excluded from status yet
+        write(&root, ".nova/history/old", "kept\n");
+        let checkpoint = create(&repo, "run-1", None).unwrap();
+        let tree = repo
+            .find_commit(Oid::from_str(&checkpoint.commit).unwrap())
+            .unwrap()
+            .tree()
+            .unwrap();
+        assert!(tree.get_path(Path::new(".nova")).is_err());
+
+        write(&root, "a.txt", "agent edit\n");
+        write(&root, ".nova/trash/new", "trashed\n");
+        let restored = restore(&repo, "run-1", &[]).unwrap();
+        assert_eq!(restored.restored, vec!["a.txt"]);
+        assert!(root.join(".nova/history/old").exists());
+        assert!(root.join(".nova/trash/new").exists());
+    }
+
+    #[test]
+    fn test_unborn_repo_and_delete() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "one\n");
+        let checkpoint = create(&repo, "first", None).unwrap();
+        assert_eq!(checkpoint.base, None);
+        assert!(repo.head().is_err());
+
+        delete(&repo, "first").unwrap();
+        assert!(list(&repo).unwrap().is_empty());
+        assert_eq!(
+            delete(&repo, "first").unwrap_err().code,
+            ErrorCode::NotFound
+        );
+        assert_eq!(
+            restore(&repo, "first", &[]).unwrap_err().code,
+            ErrorCode::NotFound
+        );
+    }
+}
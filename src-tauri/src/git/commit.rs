@@ -0,0 +1,304 @@
+// Git Commit — R20-02
+// Commits that handle unborn branches, deletions, amend, sign-off and explicit identities
+
+use git2::{Commit, ErrorCode as GitErrorCode, Oid, Repository, Signature};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::workdir_entry_path;
+use crate::error::{AppError, AppResult};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CommitOptions {
+    /// Replace the HEAD commit instead of adding a new one
+    #[serde(default)]
+    pub amend: bool,
+    /// Append a `Signed-off-by` trailer for the committer
+    #[serde(default)]
+    pub sign_off: bool,
+    /// Commit even if the tree is unchanged from the parent
+    #[serde(default)]
+    pub allow_empty: bool,
+    /// Defaults to the repository's configured user (or, when amending, the original author)
+    pub author: Option<Identity>,
+    /// Defaults to the repository's configured user, then to the author
+    pub committer: Option<Identity>,
+}
+
+/// Stage `files` (additions, modifications and deletions alike) and commit the
+/// index on HEAD. With no files, whatever is already staged is committed.
+pub fn commit(
+    repo: &Repository,
+    message: &str,
+    files: &[String],
+    options: &CommitOptions,
+) -> AppResult<Oid> {
+    if message.trim().is_empty() {
+        return Err(AppError::invalid_input("Commit message is empty"));
+    }
+
+    let mut index = repo
+        .index()
+        .map_err(|e| AppError::git("Failed to get index", e))?;
+    // The handle may be cached; pick up anything staged by other tools since
+    index
+        .read(false)
+        .map_err(|e| AppError::git("Failed to read index", e))?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| AppError::invalid_input("Cannot commit in a bare repository"))?;
+
+    for file in files {
+        // Links are staged as links, so only the directories above must stay inside
+        let file = &workdir_entry_path(repo, file)?;
+        let path = Path::new(file);
+        if std::fs::symlink_metadata(workdir.join(path)).is_ok() {
+            index
+                .add_path(path)
+                .map_err(|e| AppError::git("Failed to stage file", e).with_path(file))?;
+        } else if index.get_path(path, 0).is_some() {
+            index
+                .remove_path(path)
+                .map_err(|e| AppError::git("Failed to stage deletion", e).with_path(file))?;
+        } else {
+            return Err(AppError::not_found(format!(
+                "Path is neither in the working tree nor the index: {}",
+                file
+            ))
+            .with_path(file));
+        }
+    }
+    index
+        .write()
+        .map_err(|e| AppError::git("Failed to write index", e))?;
+
+    let tree_id = index
+        .write_tree()
+        .map_err(|e| AppError::git("Failed to write tree", e))?;
+    let tree = repo
+        .find_tree(tree_id)
+        .map_err(|e| AppError::git("Failed to find tree", e))?;
+
+    let head = head_commit(repo)?;
+    let author = match &options.author {
+        Some(identity) => signature(identity)?,
+        None => match (&head, options.amend) {
+            (Some(previous), true) => previous.author().to_owned(),
+            _ => repo
+                .signature()
+                .map_err(|e| AppError::git("Failed to get signature", e))?,
+        },
+    };
+    let committer = match &options.committer {
+        Some(identity) => signature(identity)?,
+        None => repo.signature().unwrap_or_else(|_| author.clone()),
+    };
+
+    let message = if options.sign_off {
+        add_sign_off(message, &committer)
+    } else {
+        message.to_string()
+    };
+
+    if options.amend {
+        let previous =
+            head.ok_or_else(|| AppError::invalid_input("There is no commit to amend"))?;
+        return previous
+            .amend(
+                Some("HEAD"),
+                Some(&author),
+                Some(&committer),
+                None,
+                Some(&message),
+                Some(&tree),
+            )
+            .map_err(|e| AppError::git("Failed to amend commit", e));
+    }
+
+    if !options.allow_empty {
+        let parent_tree = head.as_ref().map(|c| c.tree_id());
+        let unchanged = match parent_tree {
+            Some(id) => id == tree_id,
+            None => tree.is_empty(),
+        };
+        if unchanged {
+            return Err(AppError::invalid_input("Nothing to commit"));
+        }
+    }
+
+    let parents: Vec<&Commit> = head.iter().collect();
+    repo.commit(Some("HEAD"), &author, &committer, &message, &tree, &parents)
+        .map_err(|e| AppError::git("Failed to create commit", e))
+}
+
+/// The commit HEAD points at, or `None` on an unborn branch
+pub fn head_commit(repo: &Repository) -> AppResult<Option<Commit<'_>>> {
+    match repo.head() {
+        Ok(head) => head
+            .peel_to_commit()
+            .map(Some)
+            .map_err(|e| AppError::git("Failed to get parent", e)),
+        Err(e) if e.code() == GitErrorCode::UnbornBranch || e.code() == GitErrorCode::NotFound => {
+            Ok(None)
+        }
+        Err(e) => Err(AppError::git("Failed to read HEAD", e)),
+    }
+}
+
+fn signature(identity: &Identity) -> AppResult<Signature<'static>> {
+    Signature::now(&identity.name, &identity.email)
+        .map_err(|e| AppError::git("Invalid author or committer", e))
+}
+
+/// Append a `Signed-off-by` trailer, joining an existing trailer block if there is one
+fn add_sign_off(message: &str, signer: &Signature) -> String {
+    let trailer = format!(
+        "Signed-off-by: {} <{}>",
+        signer.name().unwrap_or_default(),
+        signer.email().unwrap_or_default()
+    );
+    let body = message.trim_end();
+    if body.lines().any(|line| line == trailer) {
+        return format!("{}\n", body);
+    }
+
+    let last_paragraph = body.rsplit("\n\n").next().unwrap_or("");
+    let has_trailers = body.contains("\n\n")
+        && last_paragraph.lines().all(|line| {
+            line.split_once(": ")
+                .map(|(key, _)| !key.is_empty() && !key.contains(' '))
+                .unwrap_or(false)
+        });
+    let separator = if has_trailers { "\n" } else { "\n\n" };
+    format!("{}{}{}\n", body, separator, trailer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::git::test_support::{init_repo, write};
+    use std::fs;
+
+    fn files(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_initial_commit_and_deletion() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "a");
+        write(&root, "b.txt", "b");
+
+        let first = commit(
+            &repo,
+            "initial",
+            &files(&["a.txt", "b.txt"]),
+            &CommitOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(repo.find_commit(first).unwrap().parent_count(), 0);
+
+        fs::remove_file(root.join("b.txt")).unwrap();
+        let second = commit(
+            &repo,
+            "remove b",
+            &files(&["b.txt"]),
+            &CommitOptions::default(),
+        )
+        .unwrap();
+        let tree = repo.find_commit(second).unwrap().tree().unwrap();
+        assert!(tree.get_name("b.txt").is_none());
+        assert!(tree.get_name("a.txt").is_some());
+
+        let err = commit(&repo, "again", &[], &CommitOptions::default()).unwrap_err();
+        assert_eq!(err.message, "Nothing to commit");
+    }
+
+    #[test]
+    fn test_files_must_be_in_working_tree() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "a");
+        let elsewhere = tempfile::tempdir().unwrap();
+        write(elsewhere.path(), "secret", "secret");
+
+        for path in ["/etc/passwd", "../secret", "src/../a.txt"] {
+            let err = commit(&repo, "escape", &files(&[path]), &CommitOptions::default());
+            assert_eq!(err.unwrap_err().code, ErrorCode::InvalidInput, "{}", path);
+        }
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(elsewhere.path(), root.join("link")).unwrap();
+            let err = commit(
+                &repo,
+                "escape",
+                &files(&["link/secret"]),
+                &CommitOptions::default(),
+            )
+            .unwrap_err();
+            assert_eq!(err.code, ErrorCode::OutsideWorkspace);
+
+            // The link itself is committed as a link, not followed
+            let id = commit(&repo, "link", &files(&["link"]), &CommitOptions::default()).unwrap();
+            let tree = repo.find_commit(id).unwrap().tree().unwrap();
+            assert_eq!(tree.get_name("link").unwrap().filemode(), 0o120000);
+        }
+    }
+
+    #[test]
+    fn test_amend_with_author_and_sign_off() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "a");
+        let first = commit(
+            &repo,
+            "initial",
+            &files(&["a.txt"]),
+            &CommitOptions::default(),
+        )
+        .unwrap();
+        write(&root, "a.txt", "a2");
+        commit(&repo, "wip", &files(&["a.txt"]), &CommitOptions::default()).unwrap();
+
+        let agent = Identity {
+            name: "Nova Agent".to_string(),
+            email: "agent@nova.dev".to_string(),
+        };
+        let amended = commit(
+            &repo,
+            "Update a",
+            &[],
+            &CommitOptions {
+                amend: true,
+                sign_off: true,
+                author: Some(agent.clone()),
+                committer: Some(agent),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let commit = repo.find_commit(amended).unwrap();
+        assert_eq!(commit.parent_id(0).unwrap(), first);
+        assert_eq!(commit.author().name(), Some("Nova Agent"));
+        assert_eq!(
+            commit.message(),
+            Some("Update a\n\nSigned-off-by: Nova Agent <agent@nova.dev>\n")
+        );
+    }
+
+    #[test]
+    fn test_sign_off_joins_trailer_block() {
+        let signer = Signature::now("A", "a@x.dev").unwrap();
+        assert_eq!(
+            add_sign_off("Fix\n\nCo-authored-by: B <b@x.dev>", &signer),
+            "Fix\n\nCo-authored-by: B <b@x.dev>\nSigned-off-by: A <a@x.dev>\n"
+        );
+    }
+}
@@ -0,0 +1,546 @@
+// Git Diff — R20-02
+// Structured working-tree, staged and commit diffs with rename detection and word diffs
+
+use git2::{Delta, Diff, DiffFindOptions, Patch, Repository};
+use serde::{Deserialize, Serialize};
+
+use super::commit::head_commit;
+use super::resolve_commit;
+use crate::error::{AppError, AppResult};
+use crate::workspace::to_slash_path;
+
+/// Lines longer than this (in tokens) are marked as wholly changed instead of word-diffed
+const MAX_WORD_DIFF_TOKENS: usize = 400;
+
+/// What to compare
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DiffTarget {
+    /// Working tree against the index (unstaged changes)
+    WorkingTree,
+    /// Index against HEAD (staged changes)
+    Staged,
+    /// Two revisions; `from` defaults to the first parent of `to`
+    Commits { from: Option<String>, to: String },
+    /// A revision against the working tree directly, bypassing the index
+    RevisionToWorkingTree { revision: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DiffRequest {
+    /// Limit the diff to these pathspecs
+    #[serde(default)]
+    pub paths: Vec<String>,
+    pub context_lines: Option<u32>,
+    /// Include untracked files in working-tree diffs (default true)
+    pub include_untracked: Option<bool>,
+    /// Annotate changed line pairs with intra-line segments
+    #[serde(default)]
+    pub word_diff: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffFileStatus {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    Copied,
+    TypeChange,
+    Untracked,
+}
+
+impl From<Delta> for DiffFileStatus {
+    fn from(delta: Delta) -> Self {
+        match delta {
+            Delta::Added => Self::Added,
+            Delta::Deleted => Self::Deleted,
+            Delta::Renamed => Self::Renamed,
+            Delta::Copied => Self::Copied,
+            Delta::Typechange => Self::TypeChange,
+            Delta::Untracked => Self::Untracked,
+            _ => Self::Modified,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffLineKind {
+    Context,
+    Addition,
+    Deletion,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WordSegment {
+    pub text: String,
+    pub changed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub old_line: Option<u32>,
+    pub new_line: Option<u32>,
+    /// Line content without its trailing newline
+    pub content: String,
+    /// Intra-line segments, set only for paired changed lines when word diffs are requested
+    pub segments: Option<Vec<WordSegment>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DiffHunk {
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileDiff {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub status: DiffFileStatus,
+    pub is_binary: bool,
+    pub additions: usize,
+    pub deletions: usize,
+    pub hunks: Vec<DiffHunk>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DiffResult {
+    pub files: Vec<FileDiff>,
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// Compute a structured diff for `target`
+pub fn diff(
+    repo: &Repository,
+    target: &DiffTarget,
+    request: &DiffRequest,
+) -> AppResult<DiffResult> {
+    let mut options = git2::DiffOptions::new();
+    for path in &request.paths {
+        options.pathspec(path);
+    }
+    if let Some(lines) = request.context_lines {
+        options.context_lines(lines);
+    }
+
+    let mut diff = match target {
+        DiffTarget::WorkingTree => {
+            let untracked = request.include_untracked.unwrap_or(true);
+            options
+                .include_untracked(untracked)
+                .recurse_untracked_dirs(untracked)
+                .show_untracked_content(untracked);
+            repo.diff_index_to_workdir(None, Some(&mut options))
+        }
+        DiffTarget::Staged => {
+            // Before the first commit everything in the index is new
+            let head = match head_commit(repo)? {
+                Some(commit) => Some(
+                    commit
+                        .tree()
+                        .map_err(|e| AppError::git("Failed to read HEAD tree", e))?,
+                ),
+                None => None,
+            };
+            repo.diff_tree_to_index(head.as_ref(), None, Some(&mut options))
+        }
+        DiffTarget::Commits { from, to } => {
+            let new = resolve_commit(repo, to)?;
+            let old = match from {
+                Some(spec) => Some(resolve_commit(repo, spec)?),
+                None => new.parents().next(),
+            };
+            let old_tree = match &old {
+                Some(commit) => Some(
+                    commit
+                        .tree()
+                        .map_err(|e| AppError::git("Failed to read commit tree", e))?,
+                ),
+                None => None,
+            };
+            let new_tree = new
+                .tree()
+                .map_err(|e| AppError::git("Failed to read commit tree", e))?;
+            repo.diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), Some(&mut options))
+        }
+        DiffTarget::RevisionToWorkingTree { revision } => {
+            let tree = resolve_commit(repo, revision)?
+                .tree()
+                .map_err(|e| AppError::git("Failed to read commit tree", e))?;
+            let untracked = request.include_untracked.unwrap_or(true);
+            options
+                .include_untracked(untracked)
+                .recurse_untracked_dirs(untracked)
+                .show_untracked_content(untracked);
+            repo.diff_tree_to_workdir(Some(&tree), Some(&mut options))
+        }
+    }
+    .map_err(|e| AppError::git("Failed to compute diff", e))?;
+
+    let mut find = DiffFindOptions::new();
+    find.renames(true).for_untracked(true);
+    diff.find_similar(Some(&mut find))
+        .map_err(|e| AppError::git("Failed to detect renames", e))?;
+
+    collect(&diff, request.word_diff)
+}
+
+fn collect(diff: &Diff, word_diff: bool) -> AppResult<DiffResult> {
+    let mut files = Vec::new();
+    for index in 0..diff.deltas().len() {
+        let patch =
+            Patch::from_diff(diff, index).map_err(|e| AppError::git("Failed to build patch", e))?;
+        let delta = diff.get_delta(index).expect("delta index in range");
+
+        let status = DiffFileStatus::from(delta.status());
+        let path_of = |file: git2::DiffFile| file.path().map(to_slash_path);
+        let (old_path, new_path) = match status {
+            DiffFileStatus::Added | DiffFileStatus::Untracked => (None, path_of(delta.new_file())),
+            DiffFileStatus::Deleted => (path_of(delta.old_file()), None),
+            _ => (path_of(delta.old_file()), path_of(delta.new_file())),
+        };
+
+        let mut file = FileDiff {
+            old_path,
+            new_path,
+            status,
+            is_binary: false,
+            additions: 0,
+            deletions: 0,
+            hunks: Vec::new(),
+        };
+
+        if let Some(patch) = patch {
+            file.is_binary = patch.delta().flags().is_binary();
+            let (_, additions, deletions) = patch
+                .line_stats()
+                .map_err(|e| AppError::git("Failed to count lines", e))?;
+            file.additions = additions;
+            file.deletions = deletions;
+            file.hunks = collect_hunks(&patch, word_diff)?;
+        } else {
+            file.is_binary = delta.flags().is_binary();
+        }
+        files.push(file);
+    }
+
+    let stats = diff
+        .stats()
+        .map_err(|e| AppError::git("Failed to compute diff stats", e))?;
+    Ok(DiffResult {
+        files,
+        files_changed: stats.files_changed(),
+        insertions: stats.insertions(),
+        deletions: stats.deletions(),
+    })
+}
+
+fn collect_hunks(patch: &Patch, word_diff: bool) -> AppResult<Vec<DiffHunk>> {
+    let mut hunks = Vec::new();
+    for h in 0..patch.num_hunks() {
+        let (hunk, line_count) = patch
+            .hunk(h)
+            .map_err(|e| AppError::git("Failed to read hunk", e))?;
+        let mut lines = Vec::with_capacity(line_count);
+        for l in 0..line_count {
+            let line = patch
+                .line_in_hunk(h, l)
+                .map_err(|e| AppError::git("Failed to read diff line", e))?;
+            let kind = match line.origin() {
+                ' ' => DiffLineKind::Context,
+                '+' => DiffLineKind::Addition,
+                '-' => DiffLineKind::Deletion,
+                // End-of-file newline markers carry no content of their own
+                _ => continue,
+            };
+            let content = String::from_utf8_lossy(line.content());
+            lines.push(DiffLine {
+                kind,
+                old_line: line.old_lineno(),
+                new_line: line.new_lineno(),
+                content: content.trim_end_matches(['\n', '\r']).to_string(),
+                segments: None,
+            });
+        }
+        if word_diff {
+            annotate_word_diffs(&mut lines);
+        }
+
+        hunks.push(DiffHunk {
+            header: String::from_utf8_lossy(hunk.header())
+                .trim_end()
+                .to_string(),
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            lines,
+        });
+    }
+    Ok(hunks)
+}
+
+/// Pair each run of deletions with the additions that follow it, line by line,
+/// and mark which words of each pair actually changed
+fn annotate_word_diffs(lines: &mut [DiffLine]) {
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].kind != DiffLineKind::Deletion {
+            i += 1;
+            continue;
+        }
+        let deleted = i;
+        while i < lines.len() && lines[i].kind == DiffLineKind::Deletion {
+            i += 1;
+        }
+        let added = i;
+        while i < lines.len() && lines[i].kind == DiffLineKind::Addition {
+            i += 1;
+        }
+
+        for k in 0..(added - deleted).min(i - added) {
+            let (old, new) = word_diff(&lines[deleted + k].content, &lines[added + k].content);
+            lines[deleted + k].segments = Some(old);
+            lines[added + k].segments = Some(new);
+        }
+    }
+}
+
+/// Split into runs of word characters, runs of whitespace and single punctuation marks
+fn tokenize(line: &str) -> Vec<&str> {
+    #[derive(PartialEq)]
+    enum Class {
+        Word,
+        Space,
+        Other,
+    }
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            Class::Word
+        } else if c.is_whitespace() {
+            Class::Space
+        } else {
+            Class::Other
+        }
+    };
+
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut current: Option<Class> = None;
+    for (i, c) in line.char_indices() {
+        let next = class(c);
+        let split = match &current {
+            Some(Class::Other) => true,
+            Some(prev) => *prev != next,
+            None => false,
+        };
+        if split {
+            tokens.push(&line[start..i]);
+            start = i;
+        }
+        current = Some(next);
+    }
+    if start < line.len() {
+        tokens.push(&line[start..]);
+    }
+    tokens
+}
+
+/// Token-level LCS diff of two lines
+fn word_diff(old: &str, new: &str) -> (Vec<WordSegment>, Vec<WordSegment>) {
+    let a = tokenize(old);
+    let b = tokenize(new);
+    if a.len() > MAX_WORD_DIFF_TOKENS || b.len() > MAX_WORD_DIFF_TOKENS {
+        return (
+            vec![WordSegment {
+                text: old.to_string(),
+                changed: true,
+            }],
+            vec![WordSegment {
+                text: new.to_string(),
+                changed: true,
+            }],
+        );
+    }
+
+    // lcs[i][j] = length of the LCS of a[i..] and b[j..]
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut old_segments, mut new_segments) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            push_segment(&mut old_segments, a[i], false);
+            push_segment(&mut new_segments, b[j], false);
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            push_segment(&mut old_segments, a[i], true);
+            i += 1;
+        } else {
+            push_segment(&mut new_segments, b[j], true);
+            j += 1;
+        }
+    }
+    (old_segments, new_segments)
+}
+
+fn push_segment(segments: &mut Vec<WordSegment>, text: &str, changed: bool) {
+    match segments.last_mut() {
+        Some(last) if last.changed == changed => last.text.push_str(text),
+        _ => segments.push(WordSegment {
+            text: text.to_string(),
+            changed,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{commit_all, init_repo, write};
+    use std::fs;
+
+    #[test]
+    fn test_working_tree_and_staged_diffs() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "one\ntwo\nthree\n");
+        commit_all(&repo, "initial");
+
+        write(&root, "a.txt", "one\n2\nthree\n");
+        write(&root, "new.txt", "fresh\n");
+        let result = diff(&repo, &DiffTarget::WorkingTree, &DiffRequest::default()).unwrap();
+        assert_eq!(result.files.len(), 2);
+
+        let a = &result.files[0];
+        assert_eq!(a.status, DiffFileStatus::Modified);
+        assert_eq!((a.additions, a.deletions), (1, 1));
+        let lines = &a.hunks[0].lines;
+        let removed = lines
+            .iter()
+            .find(|l| l.kind == DiffLineKind::Deletion)
+            .unwrap();
+        assert_eq!(
+            (removed.content.as_str(), removed.old_line),
+            ("two", Some(2))
+        );
+        let added = lines
+            .iter()
+            .find(|l| l.kind == DiffLineKind::Addition)
+            .unwrap();
+        assert_eq!((added.old_line, added.new_line), (None, Some(2)));
+
+        assert_eq!(result.files[1].status, DiffFileStatus::Untracked);
+        assert_eq!(result.files[1].new_path.as_deref(), Some("new.txt"));
+
+        let mut index = repo.index().unwrap();
+        index.add_path(std::path::Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        let staged = diff(&repo, &DiffTarget::Staged, &DiffRequest::default()).unwrap();
+        assert_eq!(staged.files.len(), 1);
+        assert_eq!(staged.insertions, 1);
+
+        // An unreadable HEAD is an error, not an empty history
+        fs::write(repo.path().join("refs/heads/master"), "garbage\n").unwrap();
+        assert!(diff(&repo, &DiffTarget::Staged, &DiffRequest::default()).is_err());
+    }
+
+    #[test]
+    fn test_commit_diff_detects_renames_and_binaries() {
+        let (_dir, root, repo) = init_repo();
+        let body: String = (0..20).map(|i| format!("line {}\n", i)).collect();
+        write(&root, "old_name.txt", &body);
+        fs::write(root.join("image.bin"), [0u8, 1, 2, 3]).unwrap();
+        commit_all(&repo, "initial");
+
+        fs::rename(root.join("old_name.txt"), root.join("new_name.txt")).unwrap();
+        fs::write(root.join("image.bin"), [0u8, 9, 9, 9]).unwrap();
+        commit_all(&repo, "rename");
+
+        let result = diff(
+            &repo,
+            &DiffTarget::Commits {
+                from: None,
+                to: "HEAD".to_string(),
+            },
+            &DiffRequest::default(),
+        )
+        .unwrap();
+
+        let binary = result
+            .files
+            .iter()
+            .find(|f| f.new_path.as_deref() == Some("image.bin"))
+            .unwrap();
+        assert!(binary.is_binary);
+        assert!(binary.hunks.is_empty());
+
+        let renamed = result
+            .files
+            .iter()
+            .find(|f| f.status == DiffFileStatus::Renamed)
+            .unwrap();
+        assert_eq!(renamed.old_path.as_deref(), Some("old_name.txt"));
+        assert_eq!(renamed.new_path.as_deref(), Some("new_name.txt"));
+    }
+
+    #[test]
+    fn test_word_diff_segments() {
+        let (old, new) = word_diff("let total = a + b;", "let sum = a + b;");
+        assert_eq!(
+            old.iter()
+                .filter(|s| s.changed)
+                .map(|s| s.text.as_str())
+                .collect::<Vec<_>>(),
+            vec!["total"]
+        );
+        assert_eq!(
+            new.iter().map(|s| s.text.as_str()).collect::<String>(),
+            "let sum = a + b;"
+        );
+
+        let mut lines: Vec<DiffLine> = [
+            (DiffLineKind::Deletion, "x = 1"),
+            (DiffLineKind::Addition, "x = 2"),
+            (DiffLineKind::Context, "y"),
+        ]
+        .iter()
+        .map(|(kind, content)| DiffLine {
+            kind: *kind,
+            old_line: None,
+            new_line: None,
+            content: content.to_string(),
+            segments: None,
+        })
+        .collect();
+        annotate_word_diffs(&mut lines);
+        let added = lines[1].segments.as_ref().unwrap();
+        assert_eq!(
+            added.last().unwrap(),
+            &WordSegment {
+                text: "2".to_string(),
+                changed: true
+            }
+        );
+        assert!(lines[2].segments.is_none());
+    }
+}
@@ -0,0 +1,545 @@
+// Git Partial Staging — R20-02
+// Stage, unstage and discard selected hunks or lines of a file's diff
+
+use git2::{DiffOptions, IndexEntry, IndexTime, Oid, Patch, Repository};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use super::commit::head_commit;
+use crate::error::{AppError, AppResult};
+use crate::file_ops::{self, WriteExpectation};
+
+const DEFAULT_CONTEXT_LINES: u32 = 3;
+
+/// A hunk, identified by its header as shown by `git_diff`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct HunkRef {
+    pub old_start: u32,
+    pub new_start: u32,
+}
+
+/// A changed line: a deletion by `old_line`, an addition by `new_line`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct LineRef {
+    pub old_line: Option<u32>,
+    pub new_line: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LineSelection {
+    /// Hunks to take whole
+    #[serde(default)]
+    pub hunks: Vec<HunkRef>,
+    /// Individual changed lines
+    #[serde(default)]
+    pub lines: Vec<LineRef>,
+    /// Context the hunks were computed with (default 3, as in `git_diff`)
+    pub context_lines: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Change {
+    Deletion(usize),
+    Addition(usize),
+}
+
+/// Stage the selected parts of the working tree changes to `path`
+pub fn stage(repo: &Repository, path: &str, selection: &LineSelection) -> AppResult<()> {
+    let mut index = fresh_index(repo)?;
+    let staged = index_blob(repo, &index, path)?;
+    let working = working_content(repo, path)?;
+    if staged.is_none() && working.is_none() {
+        return Err(
+            AppError::not_found(format!("'{}' has no changes to stage", path)).with_path(path),
+        );
+    }
+
+    let old = staged
+        .as_ref()
+        .map(|(c, _)| c.as_slice())
+        .unwrap_or_default();
+    let new = working.as_deref().unwrap_or_default();
+    let (selected, total) = resolve_selection(old, new, selection, path)?;
+    let result = apply_changes(old, new, |c| selected.contains(&c))?;
+
+    if working.is_none() && selected.len() == total {
+        index
+            .remove_path(Path::new(path))
+            .map_err(|e| AppError::git("Failed to stage deletion", e).with_path(path))?;
+    } else {
+        let mode = match &staged {
+            Some((_, mode)) => *mode,
+            None => working_mode(repo, path),
+        };
+        write_entry(&mut index, path, mode, &result)?;
+    }
+    index
+        .write()
+        .map_err(|e| AppError::git("Failed to write index", e))
+}
+
+/// Remove the selected parts of the staged changes to `path` from the index
+pub fn unstage(repo: &Repository, path: &str, selection: &LineSelection) -> AppResult<()> {
+    let mut index = fresh_index(repo)?;
+    let committed = head_blob(repo, path)?;
+    let staged = index_blob(repo, &index, path)?;
+    if committed.is_none() && staged.is_none() {
+        return Err(
+            AppError::not_found(format!("'{}' has no staged changes", path)).with_path(path),
+        );
+    }
+
+    let old = committed
+        .as_ref()
+        .map(|(c, _)| c.as_slice())
+        .unwrap_or_default();
+    let new = staged
+        .as_ref()
+        .map(|(c, _)| c.as_slice())
+        .unwrap_or_default();
+    let (selected, total) = resolve_selection(old, new, selection, path)?;
+    let result = apply_changes(old, new, |c| !selected.contains(&c))?;
+
+    if committed.is_none() && selected.len() == total {
+        index
+            .remove_path(Path::new(path))
+            .map_err(|e| AppError::git("Failed to unstage file", e).with_path(path))?;
+    } else {
+        let mode = staged
+            .as_ref()
+            .or(committed.as_ref())
+            .map(|(_, mode)| *mode)
+            .expect("one side exists");
+        write_entry(&mut index, path, mode, &result)?;
+    }
+    index
+        .write()
+        .map_err(|e| AppError::git("Failed to write index", e))
+}
+
+/// Revert the selected parts of the working tree changes to `path` back to the
+/// index. `keep` gets the content about to be replaced before anything is
+/// written, so callers can save a copy; if it fails, nothing is discarded.
+pub fn discard<F>(
+    repo: &Repository,
+    path: &str,
+    selection: &LineSelection,
+    keep: F,
+) -> AppResult<()>
+where
+    F: FnOnce(&[u8]) -> AppResult<()>,
+{
+    let index = fresh_index(repo)?;
+    let staged = index_blob(repo, &index, path)?;
+    let working = working_content(repo, path)?;
+    if staged.is_none() && working.is_none() {
+        return Err(
+            AppError::not_found(format!("'{}' has no changes to discard", path)).with_path(path),
+        );
+    }
+
+    let old = staged
+        .as_ref()
+        .map(|(c, _)| c.as_slice())
+        .unwrap_or_default();
+    let new = working.as_deref().unwrap_or_default();
+    let (selected, total) = resolve_selection(old, new, selection, path)?;
+    let result = apply_changes(old, new, |c| !selected.contains(&c))?;
+
+    let full = workdir(repo)?.join(path);
+    keep(new)?;
+    if staged.is_none() && selected.len() == total {
+        fs::remove_file(&full)
+            .map_err(|e| AppError::io("Failed to discard file", e).with_path(path))?;
+    } else {
+        if let Some(parent) = full.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| AppError::io("Failed to restore file", e).with_path(path))?;
+        }
+        file_ops::atomic_write(&full, &result, &WriteExpectation::default())?;
+    }
+    Ok(())
+}
+
+/// The set of changes the selection covers, and how many changes there are in total.
+/// Anything selected that is not in the current diff means the caller's view is stale.
+fn resolve_selection(
+    old: &[u8],
+    new: &[u8],
+    selection: &LineSelection,
+    path: &str,
+) -> AppResult<(HashSet<Change>, usize)> {
+    let patch = make_patch(
+        old,
+        new,
+        selection.context_lines.unwrap_or(DEFAULT_CONTEXT_LINES),
+    )?;
+
+    let mut all = HashSet::new();
+    let mut selected = HashSet::new();
+    let mut hunks_found = 0;
+    for h in 0..patch.num_hunks() {
+        let (hunk, count) = patch
+            .hunk(h)
+            .map_err(|e| AppError::git("Failed to read hunk", e))?;
+        let whole = selection
+            .hunks
+            .iter()
+            .any(|r| r.old_start == hunk.old_start() && r.new_start == hunk.new_start());
+        if whole {
+            hunks_found += 1;
+        }
+        for l in 0..count {
+            let line = patch
+                .line_in_hunk(h, l)
+                .map_err(|e| AppError::git("Failed to read hunk line", e))?;
+            let change = match (line.origin(), line.old_lineno(), line.new_lineno()) {
+                ('-', Some(old), _) => Change::Deletion(old as usize),
+                ('+', _, Some(new)) => Change::Addition(new as usize),
+                _ => continue,
+            };
+            all.insert(change);
+            if whole {
+                selected.insert(change);
+            }
+        }
+    }
+
+    let mut stale = hunks_found < selection.hunks.len();
+    for line in &selection.lines {
+        let change = match (line.old_line, line.new_line) {
+            (Some(old), None) => Change::Deletion(old as usize),
+            (None, Some(new)) => Change::Addition(new as usize),
+            _ => {
+                return Err(AppError::invalid_input(
+                    "Each selected line needs exactly one of old_line (deletion) or new_line (addition)",
+                ))
+            }
+        };
+        stale |= !all.contains(&change);
+        selected.insert(change);
+    }
+    if stale {
+        return Err(AppError::conflict(
+            "The selection does not match the current diff; refresh it and try again",
+        )
+        .with_path(path));
+    }
+    if selected.is_empty() {
+        return Err(AppError::invalid_input("Nothing is selected"));
+    }
+    Ok((selected, all.len()))
+}
+
+/// `old` with the changes towards `new` for which `take` is true applied
+fn apply_changes(old: &[u8], new: &[u8], take: impl Fn(Change) -> bool) -> AppResult<Vec<u8>> {
+    let old_lines: Vec<&[u8]> = old.split_inclusive(|&b| b == b'\n').collect();
+    let new_lines: Vec<&[u8]> = new.split_inclusive(|&b| b == b'\n').collect();
+    let patch = make_patch(old, new, 0)?;
+
+    let mut out = Vec::with_capacity(new.len().max(old.len()));
+    let mut next = 0;
+    for h in 0..patch.num_hunks() {
+        let (hunk, count) = patch
+            .hunk(h)
+            .map_err(|e| AppError::git("Failed to read hunk", e))?;
+        // A pure insertion goes after `old_start`; otherwise the hunk replaces from it
+        let unchanged_until = if hunk.old_lines() == 0 {
+            hunk.old_start() as usize
+        } else {
+            hunk.old_start() as usize - 1
+        };
+        while next < unchanged_until {
+            push_line(&mut out, old_lines[next]);
+            next += 1;
+        }
+        for l in 0..count {
+            let line = patch
+                .line_in_hunk(h, l)
+                .map_err(|e| AppError::git("Failed to read hunk line", e))?;
+            match (line.origin(), line.old_lineno(), line.new_lineno()) {
+                ('-', Some(number), _) => {
+                    let number = number as usize;
+                    if !take(Change::Deletion(number)) {
+                        push_line(&mut out, old_lines[number - 1]);
+                    }
+                    next = number;
+                }
+                ('+', _, Some(number)) => {
+                    let number = number as usize;
+                    if take(Change::Addition(number)) {
+                        push_line(&mut out, new_lines[number - 1]);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    while next < old_lines.len() {
+        push_line(&mut out, old_lines[next]);
+        next += 1;
+    }
+    Ok(out)
+}
+
+/// Append a line, terminating the previous one if it was the unterminated last line
+fn push_line(out: &mut Vec<u8>, line: &[u8]) {
+    if out.last().is_some_and(|&b| b != b'\n') {
+        out.push(b'\n');
+    }
+    out.extend_from_slice(line);
+}
+
+fn make_patch<'a>(old: &'a [u8], new: &'a [u8], context: u32) -> AppResult<Patch<'a>> {
+    if old.contains(&0) || new.contains(&0) {
+        return Err(AppError::invalid_input(
+            "Binary files can only be staged or discarded whole",
+        ));
+    }
+    let mut options = DiffOptions::new();
+    options.context_lines(context);
+    Patch::from_buffers(old, None, new, None, Some(&mut options))
+        .map_err(|e| AppError::git("Failed to compute diff", e))
+}
+
+fn fresh_index(repo: &Repository) -> AppResult<git2::Index> {
+    let mut index = repo
+        .index()
+        .map_err(|e| AppError::git("Failed to get index", e))?;
+    index
+        .read(false)
+        .map_err(|e| AppError::git("Failed to read index", e))?;
+    Ok(index)
+}
+
+fn workdir(repo: &Repository) -> AppResult<&Path> {
+    repo.workdir()
+        .ok_or_else(|| AppError::invalid_input("Bare repositories have no working tree"))
+}
+
+/// Content and file mode of `path` in the index
+fn index_blob(
+    repo: &Repository,
+    index: &git2::Index,
+    path: &str,
+) -> AppResult<Option<(Vec<u8>, u32)>> {
+    match index.get_path(Path::new(path), 0) {
+        Some(entry) => Ok(Some((blob_content(repo, entry.id, path)?, entry.mode))),
+        None => Ok(None),
+    }
+}
+
+/// Content and file mode of `path` in the HEAD commit
+fn head_blob(repo: &Repository, path: &str) -> AppResult<Option<(Vec<u8>, u32)>> {
+    let Some(head) = head_commit(repo)? else {
+        return Ok(None);
+    };
+    let tree = head
+        .tree()
+        .map_err(|e| AppError::git("Failed to read HEAD tree", e))?;
+    match tree.get_path(Path::new(path)) {
+        Ok(entry) => Ok(Some((
+            blob_content(repo, entry.id(), path)?,
+            entry.filemode() as u32,
+        ))),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(AppError::git("Failed to read HEAD tree", e).with_path(path)),
+    }
+}
+
+fn blob_content(repo: &Repository, id: Oid, path: &str) -> AppResult<Vec<u8>> {
+    repo.find_blob(id)
+        .map(|blob| blob.content().to_vec())
+        .map_err(|e| AppError::git("Failed to read blob", e).with_path(path))
+}
+
+fn working_content(repo: &Repository, path: &str) -> AppResult<Option<Vec<u8>>> {
+    match fs::read(workdir(repo)?.join(path)) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(AppError::io("Failed to read file", e).with_path(path)),
+    }
+}
+
+pub(crate) fn working_mode(repo: &Repository, path: &str) -> u32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Some(workdir) = repo.workdir() {
+            if let Ok(meta) = fs::metadata(workdir.join(path)) {
+                if meta.permissions().mode() & 0o111 != 0 {
+                    return 0o100755;
+                }
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (repo, path);
+    0o100644
+}
+
+fn write_entry(index: &mut git2::Index, path: &str, mode: u32, content: &[u8]) -> AppResult<()> {
+    let entry = IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode,
+        uid: 0,
+        gid: 0,
+        file_size: 0,
+        id: Oid::zero(),
+        flags: 0,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    };
+    index
+        .add_frombuffer(&entry, content)
+        .map_err(|e| AppError::git("Failed to stage content", e).with_path(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{commit_all, init_repo, write};
+
+    fn staged(repo: &Repository, path: &str) -> String {
+        let index = fresh_index(repo).unwrap();
+        let (content, _) = index_blob(repo, &index, path).unwrap().unwrap();
+        String::from_utf8(content).unwrap()
+    }
+
+    fn added(line: u32) -> LineRef {
+        LineRef {
+            old_line: None,
+            new_line: Some(line),
+        }
+    }
+
+    #[test]
+    fn test_apply_changes_selects_lines() {
+        let old = b"a\nb\nc\n";
+        let new = b"a\nB\nc\nd";
+        let all = apply_changes(old, new, |_| true).unwrap();
+        assert_eq!(all, new.to_vec());
+        let none = apply_changes(old, new, |_| false).unwrap();
+        assert_eq!(none, old.to_vec());
+        // Take the replacement of b but not the trailing addition
+        let some = apply_changes(old, new, |c| c != Change::Addition(4)).unwrap();
+        assert_eq!(some, b"a\nB\nc\n".to_vec());
+        // Drop b without adding B
+        let delete_only = apply_changes(old, new, |c| c == Change::Deletion(2)).unwrap();
+        assert_eq!(delete_only, b"a\nc\n".to_vec());
+    }
+
+    #[test]
+    fn test_stage_unstage_and_discard_lines() {
+        let (_dir, root, repo) = init_repo();
+        let original = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        write(&root, "a.txt", original);
+        commit_all(&repo, "initial");
+        write(
+            &root,
+            "a.txt",
+            "top\n1\n2\n3\n4\n5\n6\n7\n8\n9\n10\nbottom\n",
+        );
+
+        // Two separate hunks; stage only the first
+        stage(
+            &repo,
+            "a.txt",
+            &LineSelection {
+                hunks: vec![HunkRef {
+                    old_start: 1,
+                    new_start: 1,
+                }],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(staged(&repo, "a.txt"), format!("top\n{}", original));
+
+        // The diff has moved on, so the old selection is refused
+        let stale = stage(
+            &repo,
+            "a.txt",
+            &LineSelection {
+                lines: vec![added(1)],
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(stale.code, crate::error::ErrorCode::Conflict);
+
+        unstage(
+            &repo,
+            "a.txt",
+            &LineSelection {
+                lines: vec![added(1)],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(staged(&repo, "a.txt"), original);
+
+        let selection = LineSelection {
+            lines: vec![added(12)],
+            ..Default::default()
+        };
+        let refused = discard(&repo, "a.txt", &selection, |_| {
+            Err(AppError::internal("no copy kept"))
+        });
+        assert!(refused.is_err());
+        assert!(fs::read_to_string(root.join("a.txt"))
+            .unwrap()
+            .ends_with("bottom\n"));
+
+        let mut previous = Vec::new();
+        discard(&repo, "a.txt", &selection, |content| {
+            previous = content.to_vec();
+            Ok(())
+        })
+        .unwrap();
+        assert!(String::from_utf8(previous).unwrap().ends_with("bottom\n"));
+        assert_eq!(
+            fs::read_to_string(root.join("a.txt")).unwrap(),
+            format!("top\n{}", original)
+        );
+    }
+
+    #[test]
+    fn test_stage_new_file_lines() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "keep.txt", "k\n");
+        commit_all(&repo, "initial");
+        write(&root, "new.txt", "one\ntwo\n");
+
+        stage(
+            &repo,
+            "new.txt",
+            &LineSelection {
+                lines: vec![added(2)],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(staged(&repo, "new.txt"), "two\n");
+
+        unstage(
+            &repo,
+            "new.txt",
+            &LineSelection {
+                lines: vec![LineRef {
+                    old_line: None,
+                    new_line: Some(1),
+                }],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let index = fresh_index(&repo).unwrap();
+        assert!(index.get_path(Path::new("new.txt"), 0).is_none());
+    }
+}
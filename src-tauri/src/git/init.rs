@@ -0,0 +1,190 @@
+// Git Init — R20-02
+// Create a repository with a chosen initial branch and a .gitignore for the detected project type
+
+use git2::{Repository, RepositoryInitOptions};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::branch::{head_state, validate_name, HeadState};
+use crate::error::{AppError, AppResult};
+
+pub const DEFAULT_INITIAL_BRANCH: &str = "main";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectType {
+    Rust,
+    Node,
+    Python,
+    Go,
+    Java,
+}
+
+impl ProjectType {
+    const ALL: [ProjectType; 5] = [
+        ProjectType::Rust,
+        ProjectType::Node,
+        ProjectType::Python,
+        ProjectType::Go,
+        ProjectType::Java,
+    ];
+
+    /// Files whose presence at the project root identify the type
+    fn markers(self) -> &'static [&'static str] {
+        match self {
+            ProjectType::Rust => &["Cargo.toml"],
+            ProjectType::Node => &["package.json"],
+            ProjectType::Python => &["pyproject.toml", "requirements.txt", "setup.py", "Pipfile"],
+            ProjectType::Go => &["go.mod"],
+            ProjectType::Java => &["pom.xml", "build.gradle", "build.gradle.kts"],
+        }
+    }
+
+    fn ignore_section(self) -> &'static str {
+        match self {
+            ProjectType::Rust => "# Rust\ntarget/\n",
+            ProjectType::Node => {
+                "# Node\nnode_modules/\ndist/\nbuild/\n.next/\nnpm-debug.log*\nyarn-error.log*\n"
+            }
+            ProjectType::Python => {
+                "# Python\n__pycache__/\n*.py[cod]\n.venv/\nvenv/\n*.egg-info/\n.pytest_cache/\n"
+            }
+            ProjectType::Go => "# Go\n/bin/\n*.test\n*.out\n",
+            ProjectType::Java => "# Java\ntarget/\nbuild/\n.gradle/\n*.class\n",
+        }
+    }
+}
+
+/// Always ignored, whatever the project type
+const COMMON_IGNORES: &str = "\
+# Editors and OS
+.DS_Store
+Thumbs.db
+.idea/
+.vscode/
+*.swp
+
+# Environment
+.env
+.env.local
+
+# Nova26
+/.nova/
+";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InitResult {
+    pub path: String,
+    pub head: HeadState,
+    pub project_types: Vec<ProjectType>,
+    /// False when the directory already had a `.gitignore`, which is left as is
+    pub gitignore_created: bool,
+}
+
+/// Project types detected from marker files at the top of `dir`
+pub fn detect_project_types(dir: &Path) -> Vec<ProjectType> {
+    ProjectType::ALL
+        .into_iter()
+        .filter(|kind| kind.markers().iter().any(|m| dir.join(m).exists()))
+        .collect()
+}
+
+pub fn gitignore_for(types: &[ProjectType]) -> String {
+    let mut content = String::new();
+    for kind in types {
+        content.push_str(kind.ignore_section());
+        content.push('\n');
+    }
+    content.push_str(COMMON_IGNORES);
+    content
+}
+
+/// Initialise a repository in `dir` on `initial_branch` (default `main`),
+/// writing a `.gitignore` unless one exists or `gitignore` is false
+pub fn init(dir: &Path, initial_branch: Option<&str>, gitignore: bool) -> AppResult<InitResult> {
+    if dir.join(".git").exists() {
+        return Err(AppError::already_exists(format!(
+            "{} is already a git repository",
+            dir.display()
+        ))
+        .with_path(dir));
+    }
+    let branch = initial_branch.unwrap_or(DEFAULT_INITIAL_BRANCH);
+    validate_name(branch)?;
+
+    let mut options = RepositoryInitOptions::new();
+    options.initial_head(branch).mkpath(true);
+    let repo = Repository::init_opts(dir, &options)
+        .map_err(|e| AppError::git("Failed to initialise repository", e).with_path(dir))?;
+
+    let project_types = detect_project_types(dir);
+    let ignore_path = dir.join(".gitignore");
+    let gitignore_created = gitignore && !ignore_path.exists();
+    if gitignore_created {
+        fs::write(&ignore_path, gitignore_for(&project_types))
+            .map_err(|e| AppError::io("Failed to write .gitignore", e).with_path(&ignore_path))?;
+    }
+
+    Ok(InitResult {
+        path: dir.to_string_lossy().into_owned(),
+        head: head_state(&repo)?,
+        project_types,
+        gitignore_created,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    #[test]
+    fn test_init_with_branch_and_gitignore() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+        fs::write(root.join("package.json"), "{}\n").unwrap();
+
+        let result = init(&root, Some("trunk"), true).unwrap();
+        assert_eq!(
+            result.head,
+            HeadState::Unborn {
+                name: "trunk".to_string()
+            }
+        );
+        assert_eq!(
+            result.project_types,
+            vec![ProjectType::Rust, ProjectType::Node]
+        );
+        assert!(result.gitignore_created);
+        let ignore = fs::read_to_string(root.join(".gitignore")).unwrap();
+        assert!(ignore.contains("target/") && ignore.contains("node_modules/"));
+        assert!(ignore.contains("/.nova/"));
+
+        let again = init(&root, None, true).unwrap_err();
+        assert_eq!(again.code, ErrorCode::AlreadyExists);
+    }
+
+    #[test]
+    fn test_init_keeps_existing_gitignore() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::write(root.join(".gitignore"), "custom\n").unwrap();
+
+        let result = init(&root, None, true).unwrap();
+        assert_eq!(result.head.branch_name(), Some(DEFAULT_INITIAL_BRANCH));
+        assert!(result.project_types.is_empty());
+        assert!(!result.gitignore_created);
+        assert_eq!(
+            fs::read_to_string(root.join(".gitignore")).unwrap(),
+            "custom\n"
+        );
+
+        let nested = root.join("new");
+        let err = init(&nested, Some("bad..name"), true).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+        init(&nested, None, false).unwrap();
+        assert!(!nested.join(".gitignore").exists());
+    }
+}
@@ -0,0 +1,388 @@
+// Git Log — R20-02
+// Paginated history walk with path, author and date filters and per-commit file stats
+
+use git2::{Commit, Delta, Oid, Patch, Repository, Sort};
+use serde::{Deserialize, Serialize};
+
+use super::commit::head_commit;
+use super::diff::DiffFileStatus;
+use super::resolve_commit;
+use crate::error::{AppError, AppResult};
+use crate::workspace::to_slash_path;
+
+pub const DEFAULT_LOG_LIMIT: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LogQuery {
+    /// Where to start walking (default HEAD)
+    pub rev: Option<String>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+    /// Only commits that touch this file or directory
+    pub path: Option<String>,
+    /// Case-insensitive match against the author name or email
+    pub author: Option<String>,
+    /// Inclusive bounds on the commit time, in Unix milliseconds
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    /// Include per-file change stats (default true)
+    pub include_stats: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileStat {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub status: DiffFileStatus,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommitStats {
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub files: Vec<FileStat>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogEntry {
+    pub id: String,
+    pub short_id: String,
+    pub parents: Vec<String>,
+    pub author_name: String,
+    pub author_email: String,
+    /// Unix milliseconds
+    pub author_time: i64,
+    pub committer_name: String,
+    pub committer_email: String,
+    pub commit_time: i64,
+    pub summary: String,
+    pub message: String,
+    pub stats: Option<CommitStats>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogPage {
+    pub commits: Vec<LogEntry>,
+    /// Pass back as `cursor` to fetch the next page; `None` at the end of history
+    pub next_cursor: Option<String>,
+}
+
+/// A position in a walk: the commit it started from and how many commits were consumed.
+/// Pinning the start keeps pages stable while new commits land on the branch.
+struct Cursor {
+    start: Oid,
+    position: usize,
+}
+
+impl Cursor {
+    fn parse(raw: &str) -> AppResult<Self> {
+        let invalid = || AppError::invalid_input(format!("Invalid log cursor: {}", raw));
+        let (start, position) = raw.split_once(':').ok_or_else(invalid)?;
+        Ok(Self {
+            start: Oid::from_str(start).map_err(|_| invalid())?,
+            position: position.parse().map_err(|_| invalid())?,
+        })
+    }
+
+    fn encode(&self) -> String {
+        format!("{}:{}", self.start, self.position)
+    }
+}
+
+pub fn log(repo: &Repository, query: &LogQuery) -> AppResult<LogPage> {
+    let cursor = match &query.cursor {
+        Some(raw) => Cursor::parse(raw)?,
+        None => {
+            let start = match &query.rev {
+                Some(spec) => Some(resolve_commit(repo, spec)?),
+                None => head_commit(repo)?,
+            };
+            match start {
+                Some(commit) => Cursor {
+                    start: commit.id(),
+                    position: 0,
+                },
+                // Nothing committed yet
+                None => {
+                    return Ok(LogPage {
+                        commits: Vec::new(),
+                        next_cursor: None,
+                    })
+                }
+            }
+        }
+    };
+
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| AppError::git("Failed to start history walk", e))?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+        .map_err(|e| AppError::git("Failed to sort history", e))?;
+    walk.push(cursor.start)
+        .map_err(|e| AppError::git("Failed to start history walk", e))?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_LOG_LIMIT).max(1);
+    let author = query.author.as_ref().map(|a| a.to_lowercase());
+    let include_stats = query.include_stats.unwrap_or(true);
+
+    let mut commits = Vec::new();
+    let mut position = cursor.position;
+    let mut exhausted = true;
+
+    for id in walk.skip(cursor.position) {
+        if commits.len() == limit {
+            exhausted = false;
+            break;
+        }
+        position += 1;
+
+        let id = id.map_err(|e| AppError::git("Failed to walk history", e))?;
+        let commit = repo
+            .find_commit(id)
+            .map_err(|e| AppError::git("Failed to read commit", e))?;
+
+        let time = commit.time().seconds() * 1000;
+        if query.since.is_some_and(|since| time < since)
+            || query.until.is_some_and(|until| time > until)
+        {
+            continue;
+        }
+        if let Some(needle) = &author {
+            let signature = commit.author();
+            let name = signature.name().unwrap_or_default().to_lowercase();
+            let email = signature.email().unwrap_or_default().to_lowercase();
+            if !name.contains(needle.as_str()) && !email.contains(needle.as_str()) {
+                continue;
+            }
+        }
+        if let Some(path) = &query.path {
+            if !touches_path(repo, &commit, path)? {
+                continue;
+            }
+        }
+
+        let stats = if include_stats {
+            Some(commit_stats(repo, &commit)?)
+        } else {
+            None
+        };
+        commits.push(entry(&commit, stats));
+    }
+
+    Ok(LogPage {
+        commits,
+        next_cursor: if exhausted {
+            None
+        } else {
+            Some(
+                Cursor {
+                    start: cursor.start,
+                    position,
+                }
+                .encode(),
+            )
+        },
+    })
+}
+
+fn entry(commit: &Commit, stats: Option<CommitStats>) -> LogEntry {
+    let author = commit.author();
+    let committer = commit.committer();
+    let id = commit.id().to_string();
+    LogEntry {
+        short_id: id[..7].to_string(),
+        id,
+        parents: commit.parent_ids().map(|p| p.to_string()).collect(),
+        author_name: author.name().unwrap_or_default().to_string(),
+        author_email: author.email().unwrap_or_default().to_string(),
+        author_time: author.when().seconds() * 1000,
+        committer_name: committer.name().unwrap_or_default().to_string(),
+        committer_email: committer.email().unwrap_or_default().to_string(),
+        commit_time: commit.time().seconds() * 1000,
+        summary: commit.summary().unwrap_or_default().to_string(),
+        message: commit.message().unwrap_or_default().to_string(),
+        stats,
+    }
+}
+
+/// Diff a commit against its first parent (or the empty tree for a root commit)
+fn diff_to_parent<'r>(
+    repo: &'r Repository,
+    commit: &Commit,
+    path: Option<&str>,
+) -> AppResult<git2::Diff<'r>> {
+    let tree = commit
+        .tree()
+        .map_err(|e| AppError::git("Failed to read commit tree", e))?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(
+            parent
+                .tree()
+                .map_err(|e| AppError::git("Failed to read commit tree", e))?,
+        ),
+        Err(_) => None,
+    };
+
+    let mut options = git2::DiffOptions::new();
+    if let Some(path) = path {
+        options.pathspec(path);
+    }
+    repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut options))
+        .map_err(|e| AppError::git("Failed to diff commit", e))
+}
+
+fn touches_path(repo: &Repository, commit: &Commit, path: &str) -> AppResult<bool> {
+    Ok(diff_to_parent(repo, commit, Some(path))?.deltas().len() > 0)
+}
+
+fn commit_stats(repo: &Repository, commit: &Commit) -> AppResult<CommitStats> {
+    let mut diff = diff_to_parent(repo, commit, None)?;
+    diff.find_similar(None)
+        .map_err(|e| AppError::git("Failed to detect renames", e))?;
+
+    let mut files = Vec::new();
+    for index in 0..diff.deltas().len() {
+        let delta = diff.get_delta(index).expect("delta index in range");
+        let (insertions, deletions) = match Patch::from_diff(&diff, index) {
+            Ok(Some(patch)) => patch
+                .line_stats()
+                .map(|(_, added, removed)| (added, removed))
+                .unwrap_or((0, 0)),
+            _ => (0, 0),
+        };
+        let path = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .map(to_slash_path)
+            .unwrap_or_default();
+        let old_path = match delta.status() {
+            Delta::Renamed | Delta::Copied => delta.old_file().path().map(to_slash_path),
+            _ => None,
+        };
+        files.push(FileStat {
+            path,
+            old_path,
+            status: delta.status().into(),
+            insertions,
+            deletions,
+        });
+    }
+
+    Ok(CommitStats {
+        files_changed: files.len(),
+        insertions: files.iter().map(|f| f.insertions).sum(),
+        deletions: files.iter().map(|f| f.deletions).sum(),
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::commit::{commit, CommitOptions, Identity};
+    use crate::git::test_support::{commit_all, init_repo, write};
+
+    #[test]
+    fn test_log_pages_and_stats() {
+        let (_dir, root, repo) = init_repo();
+        for i in 0..5 {
+            write(&root, &format!("f{}.txt", i), "one\ntwo\n");
+            commit_all(&repo, &format!("commit {}", i));
+        }
+
+        let first = log(
+            &repo,
+            &LogQuery {
+                limit: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(first.commits.len(), 2);
+        assert_eq!(first.commits[0].summary, "commit 4");
+        let stats = first.commits[0].stats.as_ref().unwrap();
+        assert_eq!((stats.files_changed, stats.insertions), (1, 2));
+        assert_eq!(stats.files[0].path, "f4.txt");
+
+        // New commits after the first page do not shift later pages
+        write(&root, "late.txt", "late");
+        commit_all(&repo, "late");
+
+        let mut summaries = Vec::new();
+        let mut cursor = first.next_cursor;
+        while let Some(c) = cursor {
+            let page = log(
+                &repo,
+                &LogQuery {
+                    cursor: Some(c),
+                    limit: Some(2),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            summaries.extend(page.commits.into_iter().map(|c| c.summary));
+            cursor = page.next_cursor;
+        }
+        assert_eq!(summaries, vec!["commit 2", "commit 1", "commit 0"]);
+    }
+
+    #[test]
+    fn test_log_filters() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "src/a.rs", "a");
+        commit_all(&repo, "add a");
+        write(&root, "docs/readme.md", "docs");
+        commit(
+            &repo,
+            "add docs",
+            &["docs/readme.md".to_string()],
+            &CommitOptions {
+                author: Some(Identity {
+                    name: "Nova Agent".to_string(),
+                    email: "agent@nova.dev".to_string(),
+                }),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let by_path = log(
+            &repo,
+            &LogQuery {
+                path: Some("src".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(by_path.commits.len(), 1);
+        assert_eq!(by_path.commits[0].summary, "add a");
+
+        let by_author = log(
+            &repo,
+            &LogQuery {
+                author: Some("AGENT".to_string()),
+                include_stats: Some(false),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(by_author.commits.len(), 1);
+        assert!(by_author.commits[0].stats.is_none());
+
+        let future = log(
+            &repo,
+            &LogQuery {
+                since: Some(chrono::Utc::now().timestamp_millis() + 60_000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(future.commits.is_empty());
+        assert!(future.next_cursor.is_none());
+    }
+}
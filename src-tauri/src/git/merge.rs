@@ -0,0 +1,529 @@
+// Git Merge — R20-02
+// Merge a branch into HEAD, expose conflicts in detail, and resolve, conclude or abort
+
+use git2::build::CheckoutBuilder;
+use git2::{AnnotatedCommit, Commit, Oid, Repository, RepositoryState, ResetType};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::commit::head_commit;
+use super::{conflicted_paths, has_uncommitted_changes, workdir_path};
+use crate::error::{AppError, AppResult};
+use crate::file_ops::{self, WriteExpectation};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MergeOptions {
+    /// Defaults to `Merge branch '<name>'`
+    pub message: Option<String>,
+    /// Always create a merge commit, even when a fast-forward is possible
+    #[serde(default)]
+    pub no_ff: bool,
+    /// Fail unless the merge can be done as a fast-forward
+    #[serde(default)]
+    pub ff_only: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum MergeOutcome {
+    UpToDate,
+    FastForward {
+        commit: String,
+    },
+    /// Merged without conflicts and committed
+    Merged {
+        commit: String,
+    },
+    /// The merge is in progress; resolve the files, then conclude or abort
+    Conflicted {
+        conflicts: Vec<ConflictFile>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConflictFile {
+    pub path: String,
+    /// Full file content on each side; `None` if the side deleted the file or it is binary
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+    pub base: Option<String>,
+    pub is_binary: bool,
+    /// Conflict marker blocks in the working tree copy
+    pub regions: Vec<ConflictRegion>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConflictRegion {
+    /// 1-based lines of the `<<<<<<<` and `>>>>>>>` markers
+    pub start_line: usize,
+    pub end_line: usize,
+    pub ours: String,
+    /// Present when the file uses diff3-style markers
+    pub base: Option<String>,
+    pub theirs: String,
+}
+
+/// Merge `branch` (a branch name or any revision) into the current branch
+pub fn merge(repo: &Repository, branch: &str, options: &MergeOptions) -> AppResult<MergeOutcome> {
+    if options.no_ff && options.ff_only {
+        return Err(AppError::invalid_input(
+            "no_ff and ff_only cannot be used together",
+        ));
+    }
+    ensure_clean_state(repo)?;
+    if has_uncommitted_changes(repo)? {
+        return Err(AppError::conflict(
+            "Working tree has uncommitted changes; commit or stash them before merging",
+        ));
+    }
+
+    let incoming = annotated(repo, branch)?;
+    let (analysis, _) = repo
+        .merge_analysis(&[&incoming])
+        .map_err(|e| AppError::git("Failed to analyse merge", e))?;
+
+    if analysis.is_up_to_date() {
+        return Ok(MergeOutcome::UpToDate);
+    }
+    if (analysis.is_fast_forward() || analysis.is_unborn()) && !options.no_ff {
+        fast_forward(repo, incoming.id())?;
+        return Ok(MergeOutcome::FastForward {
+            commit: incoming.id().to_string(),
+        });
+    }
+    if options.ff_only {
+        return Err(AppError::conflict(format!(
+            "Cannot fast-forward to '{}'; the branches have diverged",
+            branch
+        )));
+    }
+
+    let mut checkout = CheckoutBuilder::new();
+    checkout.safe().allow_conflicts(true);
+    repo.merge(&[&incoming], None, Some(&mut checkout))
+        .map_err(|e| AppError::git(&format!("Failed to merge '{}'", branch), e))?;
+
+    let index = repo
+        .index()
+        .map_err(|e| AppError::git("Failed to get index", e))?;
+    if index.has_conflicts() {
+        return Ok(MergeOutcome::Conflicted {
+            conflicts: conflicts(repo)?,
+        });
+    }
+
+    let commit = conclude(repo, options.message.as_deref())?;
+    Ok(MergeOutcome::Merged {
+        commit: commit.to_string(),
+    })
+}
+
+/// Details of every conflicted file in the merge in progress
+pub fn conflicts(repo: &Repository) -> AppResult<Vec<ConflictFile>> {
+    let index = fresh_index(repo)?;
+    let workdir = repo.workdir();
+
+    let mut files = Vec::new();
+    for conflict in index
+        .conflicts()
+        .map_err(|e| AppError::git("Failed to read conflicts", e))?
+    {
+        let conflict = conflict.map_err(|e| AppError::git("Failed to read conflicts", e))?;
+        let path = match conflict
+            .our
+            .as_ref()
+            .or(conflict.their.as_ref())
+            .or(conflict.ancestor.as_ref())
+        {
+            Some(entry) => String::from_utf8_lossy(&entry.path).into_owned(),
+            None => continue,
+        };
+
+        let mut is_binary = false;
+        let mut side = |entry: Option<&git2::IndexEntry>| -> AppResult<Option<String>> {
+            let Some(entry) = entry else {
+                return Ok(None);
+            };
+            let blob = repo
+                .find_blob(entry.id)
+                .map_err(|e| AppError::git("Failed to read conflict side", e).with_path(&path))?;
+            if blob.is_binary() {
+                is_binary = true;
+                return Ok(None);
+            }
+            Ok(Some(String::from_utf8_lossy(blob.content()).into_owned()))
+        };
+        let ours = side(conflict.our.as_ref())?;
+        let theirs = side(conflict.their.as_ref())?;
+        let base = side(conflict.ancestor.as_ref())?;
+
+        let regions = match workdir {
+            Some(workdir) if !is_binary => std::fs::read_to_string(workdir.join(&path))
+                .map(|text| parse_regions(&text))
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+
+        files.push(ConflictFile {
+            path,
+            ours,
+            theirs,
+            base,
+            is_binary,
+            regions,
+        });
+    }
+    Ok(files)
+}
+
+/// Find `<<<<<<<` / `|||||||` / `=======` / `>>>>>>>` blocks
+fn parse_regions(text: &str) -> Vec<ConflictRegion> {
+    enum Section {
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    let mut regions = Vec::new();
+    let mut open: Option<(Section, ConflictRegion)> = None;
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        if line.starts_with("<<<<<<<") {
+            let region = ConflictRegion {
+                start_line: number,
+                end_line: number,
+                ours: String::new(),
+                base: None,
+                theirs: String::new(),
+            };
+            open = Some((Section::Ours, region));
+            continue;
+        }
+        let Some((section, region)) = open.as_mut() else {
+            continue;
+        };
+        if line.starts_with("|||||||") {
+            *section = Section::Base;
+            region.base = Some(String::new());
+        } else if line.starts_with("=======") {
+            *section = Section::Theirs;
+        } else if line.starts_with(">>>>>>>") {
+            let (_, mut region) = open.take().expect("region is open");
+            region.end_line = number;
+            regions.push(region);
+        } else {
+            let target = match section {
+                Section::Ours => &mut region.ours,
+                Section::Base => region.base.get_or_insert_with(String::new),
+                Section::Theirs => &mut region.theirs,
+            };
+            target.push_str(line);
+            target.push('\n');
+        }
+    }
+    regions
+}
+
+/// Mark `path` resolved, first writing `content` to it if given. `keep` gets
+/// the file's current content before it is replaced; if it fails, nothing is
+/// written. A path that no longer exists in the working tree is resolved as
+/// deleted. Returns the paths still in conflict.
+pub fn resolve<F>(
+    repo: &Repository,
+    path: &str,
+    content: Option<&str>,
+    keep: F,
+) -> AppResult<Vec<String>>
+where
+    F: FnOnce(&[u8]) -> AppResult<()>,
+{
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| AppError::invalid_input("Cannot resolve conflicts in a bare repository"))?;
+    let path = &workdir_path(repo, path)?;
+    let mut index = fresh_index(repo)?;
+    if !conflicted_paths(&index)?.iter().any(|p| p == path) {
+        return Err(
+            AppError::invalid_input(format!("'{}' is not in conflict", path)).with_path(path),
+        );
+    }
+
+    let full = workdir.join(path);
+    if let Some(content) = content {
+        match std::fs::read(&full) {
+            Ok(previous) => keep(&previous)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(AppError::io("Failed to read file", e).with_path(path)),
+        }
+        file_ops::atomic_write(&full, content.as_bytes(), &WriteExpectation::default())?;
+    }
+    let rel = Path::new(path);
+    if full.exists() {
+        index
+            .add_path(rel)
+            .map_err(|e| AppError::git("Failed to stage resolution", e).with_path(path))?;
+    } else {
+        index
+            .remove_path(rel)
+            .map_err(|e| AppError::git("Failed to stage resolution", e).with_path(path))?;
+    }
+    index
+        .write()
+        .map_err(|e| AppError::git("Failed to write index", e))?;
+
+    conflicted_paths(&index)
+}
+
+/// Commit the merge in progress once every conflict is resolved
+pub fn conclude(repo: &Repository, message: Option<&str>) -> AppResult<Oid> {
+    if repo.state() != RepositoryState::Merge {
+        return Err(AppError::invalid_input("No merge in progress"));
+    }
+    let mut index = fresh_index(repo)?;
+    let remaining = conflicted_paths(&index)?;
+    if !remaining.is_empty() {
+        return Err(AppError::conflict(format!(
+            "Unresolved conflicts remain: {}",
+            remaining.join(", ")
+        )));
+    }
+
+    // Read directly: git2 only walks MERGE_HEAD through a mutable repository
+    let merge_head = std::fs::read_to_string(repo.path().join("MERGE_HEAD"))
+        .map_err(|e| AppError::io("Failed to read MERGE_HEAD", e))?;
+    let merge_heads = merge_head
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Oid::from_str(line.trim()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::git("Failed to read MERGE_HEAD", e))?;
+
+    let head = head_commit(repo)?;
+    let mut parents: Vec<Commit> = head.into_iter().collect();
+    for id in merge_heads {
+        parents.push(
+            repo.find_commit(id)
+                .map_err(|e| AppError::git("Failed to read merged commit", e))?,
+        );
+    }
+    let parent_refs: Vec<&Commit> = parents.iter().collect();
+
+    let message = match message.filter(|m| !m.trim().is_empty()) {
+        Some(message) => message.to_string(),
+        None => repo
+            .message()
+            .unwrap_or_else(|_| "Merge commit".to_string()),
+    };
+    let tree_id = index
+        .write_tree()
+        .map_err(|e| AppError::git("Failed to write tree", e))?;
+    let tree = repo
+        .find_tree(tree_id)
+        .map_err(|e| AppError::git("Failed to find tree", e))?;
+    let signature = repo
+        .signature()
+        .map_err(|e| AppError::git("Failed to get signature", e))?;
+
+    let id = repo
+        .commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            &message,
+            &tree,
+            &parent_refs,
+        )
+        .map_err(|e| AppError::git("Failed to create merge commit", e))?;
+    repo.cleanup_state()
+        .map_err(|e| AppError::git("Failed to clear merge state", e))?;
+    Ok(id)
+}
+
+/// Throw away the merge in progress and return to HEAD
+pub fn abort(repo: &Repository) -> AppResult<()> {
+    if repo.state() != RepositoryState::Merge {
+        return Err(AppError::invalid_input("No merge in progress"));
+    }
+    let head = head_commit(repo)?
+        .ok_or_else(|| AppError::invalid_input("There is no commit to return to"))?;
+    repo.reset(head.as_object(), ResetType::Hard, None)
+        .map_err(|e| AppError::git("Failed to reset to HEAD", e))?;
+    repo.cleanup_state()
+        .map_err(|e| AppError::git("Failed to clear merge state", e))
+}
+
+fn ensure_clean_state(repo: &Repository) -> AppResult<()> {
+    match repo.state() {
+        RepositoryState::Clean => Ok(()),
+        RepositoryState::Merge => Err(AppError::conflict(
+            "A merge is already in progress; conclude or abort it first",
+        )),
+        state => Err(AppError::conflict(format!(
+            "Repository is busy with another operation ({:?})",
+            state
+        ))),
+    }
+}
+
+/// The repository index, re-read in case another tool changed it since the handle was cached
+fn fresh_index(repo: &Repository) -> AppResult<git2::Index> {
+    let mut index = repo
+        .index()
+        .map_err(|e| AppError::git("Failed to get index", e))?;
+    index
+        .read(false)
+        .map_err(|e| AppError::git("Failed to read index", e))?;
+    Ok(index)
+}
+
+/// Prefer the branch reference so the merge message can name it
+fn annotated<'r>(repo: &'r Repository, name: &str) -> AppResult<AnnotatedCommit<'r>> {
+    if let Ok(reference) = repo.resolve_reference_from_short_name(name) {
+        return repo
+            .reference_to_annotated_commit(&reference)
+            .map_err(|e| AppError::git(&format!("Failed to resolve '{}'", name), e));
+    }
+    let commit = super::resolve_commit(repo, name)?;
+    repo.find_annotated_commit(commit.id())
+        .map_err(|e| AppError::git(&format!("Failed to resolve '{}'", name), e))
+}
+
+pub(crate) fn fast_forward(repo: &Repository, target: Oid) -> AppResult<()> {
+    let object = repo
+        .find_object(target, None)
+        .map_err(|e| AppError::git("Failed to read merge target", e))?;
+    let mut checkout = CheckoutBuilder::new();
+    checkout.safe();
+    repo.checkout_tree(&object, Some(&mut checkout))
+        .map_err(|e| AppError::git("Failed to fast-forward working tree", e))?;
+
+    let head = repo
+        .find_reference("HEAD")
+        .map_err(|e| AppError::git("Failed to read HEAD", e))?;
+    // HEAD may be a branch with no commits yet, so update whatever it resolves to by name
+    let target_ref = match head.symbolic_target() {
+        Some(name) => name.to_string(),
+        None => "HEAD".to_string(),
+    };
+    repo.reference(&target_ref, target, true, "merge: fast-forward")
+        .map_err(|e| AppError::git("Failed to fast-forward branch", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::branch::{checkout_branch, create_branch};
+    use crate::git::test_support::{commit_all, init_repo, write};
+    use std::fs;
+
+    fn diverge(root: &Path, repo: &Repository, ours: &str, theirs: &str) {
+        write(root, "a.txt", "one\ntwo\nthree\n");
+        commit_all(repo, "initial");
+        create_branch(repo, "feature", None, true).unwrap();
+        write(root, "a.txt", theirs);
+        commit_all(repo, "feature change");
+        checkout_branch(repo, "master", false).unwrap();
+        write(root, "a.txt", ours);
+        commit_all(repo, "master change");
+    }
+
+    #[test]
+    fn test_fast_forward_and_clean_merge() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "a\n");
+        commit_all(&repo, "initial");
+        create_branch(&repo, "feature", None, true).unwrap();
+        write(&root, "b.txt", "b\n");
+        let tip = commit_all(&repo, "add b");
+        checkout_branch(&repo, "master", false).unwrap();
+
+        let outcome = merge(&repo, "feature", &MergeOptions::default()).unwrap();
+        assert_eq!(
+            outcome,
+            MergeOutcome::FastForward {
+                commit: tip.to_string()
+            }
+        );
+        assert!(root.join("b.txt").exists());
+        assert_eq!(
+            merge(&repo, "feature", &MergeOptions::default()).unwrap(),
+            MergeOutcome::UpToDate
+        );
+
+        checkout_branch(&repo, "feature", false).unwrap();
+        write(&root, "c.txt", "c\n");
+        commit_all(&repo, "add c");
+        checkout_branch(&repo, "master", false).unwrap();
+        write(&root, "d.txt", "d\n");
+        commit_all(&repo, "add d");
+
+        let MergeOutcome::Merged { commit } =
+            merge(&repo, "feature", &MergeOptions::default()).unwrap()
+        else {
+            panic!("expected a merge commit");
+        };
+        let commit = repo.find_commit(Oid::from_str(&commit).unwrap()).unwrap();
+        assert_eq!(commit.parent_count(), 2);
+        assert_eq!(commit.summary(), Some("Merge branch 'feature'"));
+        assert_eq!(repo.state(), RepositoryState::Clean);
+    }
+
+    #[test]
+    fn test_conflict_resolve_and_conclude() {
+        let (_dir, root, repo) = init_repo();
+        diverge(&root, &repo, "one\nours\nthree\n", "one\ntheirs\nthree\n");
+
+        let MergeOutcome::Conflicted { conflicts } =
+            merge(&repo, "feature", &MergeOptions::default()).unwrap()
+        else {
+            panic!("expected conflicts");
+        };
+        assert_eq!(conflicts.len(), 1);
+        let file = &conflicts[0];
+        assert_eq!(file.path, "a.txt");
+        assert_eq!(file.base.as_deref(), Some("one\ntwo\nthree\n"));
+        assert_eq!(file.regions.len(), 1);
+        assert_eq!(file.regions[0].ours, "ours\n");
+        assert_eq!(file.regions[0].theirs, "theirs\n");
+        assert_eq!(file.regions[0].start_line, 2);
+
+        assert!(conclude(&repo, None).is_err());
+        let err = resolve(&repo, "../a.txt", Some("x"), |_| Ok(())).unwrap_err();
+        assert_eq!(err.code, crate::error::ErrorCode::InvalidInput);
+        let mut previous = String::new();
+        let remaining = resolve(&repo, "a.txt", Some("one\nboth\nthree\n"), |content| {
+            previous = String::from_utf8_lossy(content).into_owned();
+            Ok(())
+        })
+        .unwrap();
+        assert!(remaining.is_empty());
+        assert!(previous.contains("<<<<<<<"));
+        let id = conclude(&repo, None).unwrap();
+        assert_eq!(repo.find_commit(id).unwrap().parent_count(), 2);
+        assert_eq!(repo.state(), RepositoryState::Clean);
+        assert_eq!(
+            fs::read_to_string(root.join("a.txt")).unwrap(),
+            "one\nboth\nthree\n"
+        );
+    }
+
+    #[test]
+    fn test_abort_restores_head() {
+        let (_dir, root, repo) = init_repo();
+        diverge(&root, &repo, "one\nours\nthree\n", "one\ntheirs\nthree\n");
+        let head = repo.head().unwrap().target().unwrap();
+
+        merge(&repo, "feature", &MergeOptions::default()).unwrap();
+        assert!(merge(&repo, "feature", &MergeOptions::default()).is_err());
+        abort(&repo).unwrap();
+
+        assert_eq!(repo.state(), RepositoryState::Clean);
+        assert_eq!(repo.head().unwrap().target().unwrap(), head);
+        assert_eq!(
+            fs::read_to_string(root.join("a.txt")).unwrap(),
+            "one\nours\nthree\n"
+        );
+        assert!(abort(&repo).is_err());
+    }
+}
@@ -0,0 +1,315 @@
+// Git — R20-02
+// Repository access and revision helpers shared by the git commands
+
+pub mod blame;
+pub mod branch;
+pub mod changelog;
+pub mod checkpoint;
+pub mod commit;
+pub mod diff;
+pub mod hunks;
+pub mod init;
+pub mod log;
+pub mod merge;
+pub mod remote;
+pub mod stash;
+pub mod status;
+pub mod tag;
+pub mod worktree;
+
+use git2::{Commit, Repository, RepositoryOpenFlags};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, TryLockError};
+
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::workspace::{resolve_entry_within, resolve_within, to_slash_path, WorkspaceError};
+
+/// A repository handle that can be moved onto a blocking task
+pub type SharedRepo = Arc<Mutex<Repository>>;
+
+/// Opened repositories, keyed by the directory they were requested for
+pub struct RepoCache {
+    repos: HashMap<PathBuf, SharedRepo>,
+}
+
+impl RepoCache {
+    pub fn new() -> Self {
+        Self {
+            repos: HashMap::new(),
+        }
+    }
+
+    /// Reuse the handle for `path`, or open the repository containing it without
+    /// searching above `ceiling` (the workspace root)
+    pub fn open(&mut self, path: &Path, ceiling: Option<&Path>) -> AppResult<SharedRepo> {
+        if let Some(repo) = self.repos.get(path) {
+            let alive = match repo.try_lock() {
+                Ok(repo) => repo.path().exists(),
+                // In use by another command, so it certainly still exists
+                Err(TryLockError::WouldBlock) => true,
+                Err(TryLockError::Poisoned(_)) => false,
+            };
+            if alive {
+                return Ok(repo.clone());
+            }
+            self.repos.remove(path);
+        }
+
+        let repo = Arc::new(Mutex::new(open_repo(path, ceiling)?));
+        self.repos.insert(path.to_path_buf(), repo.clone());
+        Ok(repo)
+    }
+}
+
+impl Default for RepoCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Open the repository containing `path`. With a `ceiling`, the search never
+/// leaves it, so a project nested in some unrelated repo is not mistaken for it.
+pub fn open_repo(path: &Path, ceiling: Option<&Path>) -> AppResult<Repository> {
+    let ceilings: Vec<&Path> = ceiling.and_then(|c| c.parent()).into_iter().collect();
+    Repository::open_ext(path, RepositoryOpenFlags::empty(), ceilings).map_err(|e| {
+        if e.code() == git2::ErrorCode::NotFound {
+            AppError::new(
+                ErrorCode::RepoNotFound,
+                format!("Not a git repository: {}", path.display()),
+            )
+            .with_path(path)
+        } else {
+            AppError::git("Failed to open repository", e).with_path(path)
+        }
+    })
+}
+
+/// Resolve a revision (`HEAD~2`, a branch name, a sha) to the commit it points at
+pub fn resolve_commit<'r>(repo: &'r Repository, spec: &str) -> AppResult<Commit<'r>> {
+    repo.revparse_single(spec)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| AppError::git(&format!("Failed to resolve '{}'", spec), e))
+}
+
+/// Check a path from the webview that names a file in the working tree, and
+/// return it relative to the repository root with `/` separators. Absolute
+/// paths, `..` and paths that lead out of the working tree through a symlink
+/// are refused.
+pub fn workdir_path(repo: &Repository, path: &str) -> AppResult<String> {
+    checked_workdir_path(repo, path, resolve_within)
+}
+
+/// Like `workdir_path`, but a symlink in the final component is not followed,
+/// for operations such as staging that record the link itself
+pub fn workdir_entry_path(repo: &Repository, path: &str) -> AppResult<String> {
+    checked_workdir_path(repo, path, resolve_entry_within)
+}
+
+fn checked_workdir_path(
+    repo: &Repository,
+    path: &str,
+    resolve: fn(&Path, &Path) -> Result<PathBuf, WorkspaceError>,
+) -> AppResult<String> {
+    let rel = Path::new(path);
+    let invalid = || {
+        AppError::invalid_input(format!(
+            "Path must be relative to the repository root: {}",
+            path
+        ))
+        .with_path(path)
+    };
+    if rel.is_absolute()
+        || rel
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(invalid());
+    }
+    let rel: PathBuf = rel
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .collect();
+    if rel.as_os_str().is_empty() {
+        return Err(invalid());
+    }
+
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| AppError::invalid_input("Bare repositories have no working tree"))?;
+    let workdir = fs::canonicalize(workdir)
+        .map_err(|e| AppError::io("Failed to resolve working tree", e).with_path(workdir))?;
+    resolve(&workdir, &rel)?;
+    Ok(to_slash_path(&rel))
+}
+
+/// True if tracked files differ from HEAD in the index or working tree.
+/// Untracked and ignored files do not count.
+pub fn has_uncommitted_changes(repo: &Repository) -> AppResult<bool> {
+    let mut options = git2::StatusOptions::new();
+    options
+        .include_untracked(false)
+        .include_ignored(false)
+        .exclude_submodules(true);
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .map_err(|e| AppError::git("Failed to get statuses", e))?;
+    Ok(!statuses.is_empty())
+}
+
+/// Keep the app's `.nova` directory (history, trash, worktrees) out of the
+/// repository's status so it never shows up as untracked or gets staged
+pub fn exclude_nova_dir(repo: &Repository) -> AppResult<()> {
+    let exclude = repo.path().join("info").join("exclude");
+    let current = fs::read_to_string(&exclude).unwrap_or_default();
+    if current.lines().any(|line| line.trim() == "/.nova/") {
+        return Ok(());
+    }
+    let mut updated = current;
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push_str("/.nova/\n");
+    if let Some(parent) = exclude.parent() {
+        fs::create_dir_all(parent).map_err(|e| AppError::io("Failed to update excludes", e))?;
+    }
+    fs::write(&exclude, updated).map_err(|e| AppError::io("Failed to update excludes", e))
+}
+
+/// `exclude_nova_dir` for the repository at `root`, if there is one. Best
+/// effort: called before the app first writes into `.nova`.
+pub fn exclude_nova_dir_at(root: &Path) {
+    if let Ok(repo) = Repository::open(root) {
+        let _ = exclude_nova_dir(&repo);
+    }
+}
+
+/// Paths with unresolved conflicts in the index
+pub fn conflicted_paths(index: &git2::Index) -> AppResult<Vec<String>> {
+    let mut paths = Vec::new();
+    if !index.has_conflicts() {
+        return Ok(paths);
+    }
+    for conflict in index
+        .conflicts()
+        .map_err(|e| AppError::git("Failed to read conflicts", e))?
+    {
+        let conflict = conflict.map_err(|e| AppError::git("Failed to read conflicts", e))?;
+        let entry = conflict.our.or(conflict.their).or(conflict.ancestor);
+        if let Some(entry) = entry {
+            paths.push(String::from_utf8_lossy(&entry.path).into_owned());
+        }
+    }
+    Ok(paths)
+}
+
+/// Helpers for building throwaway repositories in tests
+#[cfg(test)]
+pub(crate) mod test_support {
+    use git2::{Oid, Repository, RepositoryInitOptions, Signature};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// A repository whose first branch is `master`, whatever `init.defaultBranch` says
+    pub fn init_repo() -> (tempfile::TempDir, PathBuf, Repository) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let repo =
+            Repository::init_opts(&root, RepositoryInitOptions::new().initial_head("master"))
+                .unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Nova Test").unwrap();
+        config.set_str("user.email", "test@nova.dev").unwrap();
+        (dir, root, repo)
+    }
+
+    pub fn write(root: &Path, rel: &str, content: &str) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// Stage everything (including deletions) and commit it on HEAD
+    pub fn commit_all(repo: &Repository, message: &str) -> Oid {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.update_all(["*"].iter(), None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Nova Test", "test@nova.dev").unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_repo_cache_reuses_handles() {
+        let (_dir, root, _repo) = test_support::init_repo();
+        fs::create_dir_all(root.join("src")).unwrap();
+
+        let mut cache = RepoCache::new();
+        let first = cache.open(&root, Some(&root)).unwrap();
+        let again = cache.open(&root, Some(&root)).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        // A subdirectory resolves to the same repository
+        let nested = cache.open(&root.join("src"), Some(&root)).unwrap();
+        assert_eq!(nested.lock().unwrap().path(), first.lock().unwrap().path());
+    }
+
+    #[test]
+    fn test_workdir_path_stays_in_working_tree() {
+        let (_dir, root, repo) = test_support::init_repo();
+        test_support::write(&root, "src/a.rs", "a\n");
+
+        assert_eq!(workdir_path(&repo, "./src/a.rs").unwrap(), "src/a.rs");
+        assert_eq!(workdir_path(&repo, "src/new.rs").unwrap(), "src/new.rs");
+        let outside = root.join("../secret").to_string_lossy().into_owned();
+        for path in ["/etc/passwd", "../../secret", "src/../a.rs", ".", &outside] {
+            assert_eq!(
+                workdir_path(&repo, path).unwrap_err().code,
+                ErrorCode::InvalidInput,
+                "{}",
+                path
+            );
+        }
+
+        #[cfg(unix)]
+        {
+            let elsewhere = tempfile::tempdir().unwrap();
+            std::os::unix::fs::symlink(elsewhere.path(), root.join("link")).unwrap();
+            assert_eq!(
+                workdir_path(&repo, "link/secret").unwrap_err().code,
+                ErrorCode::OutsideWorkspace
+            );
+        }
+    }
+
+    #[test]
+    fn test_open_stops_at_workspace_root() {
+        let (_dir, root, _repo) = test_support::init_repo();
+        let project = root.join("project");
+        fs::create_dir_all(&project).unwrap();
+
+        assert!(open_repo(&project, None).is_ok());
+        let err = open_repo(&project, Some(&project)).err().unwrap();
+        assert_eq!(err.code, ErrorCode::RepoNotFound);
+        assert!(err.message.starts_with("Not a git repository"));
+    }
+}
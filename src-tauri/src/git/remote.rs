@@ -0,0 +1,1176 @@
+// Git Remotes — R20-02
+// Fetch, pull and push with progress reporting and SSH agent, key file or token credentials
+
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{
+    AutotagOption, BranchType, Cred, CredentialType, FetchOptions, FetchPrune, PushOptions,
+    RebaseOptions, RemoteCallbacks, Repository, RepositoryState,
+};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+
+use super::branch::{head_state, validate_name, HeadState};
+use super::merge::fast_forward;
+use super::{conflicted_paths, has_uncommitted_changes};
+use crate::error::{AppError, AppResult};
+
+pub const DEFAULT_REMOTE: &str = "origin";
+
+/// Credentials the frontend passes in from the app settings. SSH remotes try
+/// the SSH agent before the key file; HTTPS remotes use the token.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RemoteAuth {
+    /// Overrides the user name in the remote URL (`git` for SSH when neither is set)
+    pub username: Option<String>,
+    /// Personal access token, sent as the HTTPS password
+    pub token: Option<String>,
+    pub ssh_key_path: Option<String>,
+    pub ssh_passphrase: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteOperation {
+    Clone,
+    Fetch,
+    Pull,
+    Push,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressStage {
+    /// Downloading objects; `bytes` is the amount received so far
+    Receiving,
+    /// Resolving deltas in the received pack
+    Resolving,
+    /// Building the pack to upload
+    Packing,
+    /// Uploading objects; `bytes` is the amount sent so far
+    Sending,
+    /// Writing files after a clone
+    CheckingOut,
+    /// Text from the remote, such as `Counting objects`
+    Remote,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RemoteProgress {
+    pub operation: RemoteOperation,
+    pub remote: String,
+    pub stage: ProgressStage,
+    pub current: usize,
+    pub total: usize,
+    pub bytes: usize,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PullMode {
+    /// Fail if the local branch has commits the remote doesn't
+    #[default]
+    FastForward,
+    /// Replay local commits on top of the remote branch
+    Rebase,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RefUpdate {
+    pub name: String,
+    /// `None` for refs that did not exist before
+    pub old: Option<String>,
+    /// `None` for refs that were pruned
+    pub new: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FetchResult {
+    pub remote: String,
+    pub updated: Vec<RefUpdate>,
+    pub received_objects: usize,
+    pub received_bytes: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum PullOutcome {
+    UpToDate,
+    FastForward {
+        commit: String,
+    },
+    Rebased {
+        commit: String,
+        /// Local commits replayed onto the remote branch
+        rebased: usize,
+    },
+    /// The rebase hit conflicts and was rolled back; the branch is unchanged
+    Conflicted {
+        conflicts: Vec<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PullResult {
+    pub fetch: FetchResult,
+    /// Remote-tracking branch that was integrated, e.g. `origin/main`
+    pub upstream: String,
+    pub outcome: PullOutcome,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PushRequest {
+    /// Defaults to the branch's upstream remote, then `origin`
+    pub remote: Option<String>,
+    /// Defaults to the current branch
+    pub branch: Option<String>,
+    /// Overwrite the remote branch even if that discards commits on it
+    #[serde(default)]
+    pub force: bool,
+    /// Track the pushed branch as the local branch's upstream
+    #[serde(default)]
+    pub set_upstream: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PushResult {
+    pub remote: String,
+    pub branch: String,
+    pub remote_branch: String,
+    pub upstream_set: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CloneOptions {
+    /// Branch to check out instead of the remote's default
+    pub branch: Option<String>,
+    /// Fetch only this many commits of history. libgit2 ignores this for
+    /// local paths and `file://` URLs, which are always cloned in full.
+    pub depth: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CloneResult {
+    pub path: String,
+    pub head: HeadState,
+    /// Whether history was actually truncated by `depth`
+    pub shallow: bool,
+}
+
+/// Clone `url` into `destination`, which must not exist or be an empty directory
+pub fn clone(
+    url: &str,
+    destination: &Path,
+    options: &CloneOptions,
+    auth: &RemoteAuth,
+    progress: &mut dyn FnMut(RemoteProgress),
+) -> AppResult<CloneResult> {
+    let occupied = fs::read_dir(destination)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(destination.exists());
+    if occupied {
+        return Err(AppError::already_exists(format!(
+            "Destination is not empty: {}",
+            destination.display()
+        ))
+        .with_path(destination));
+    }
+    if let Some(branch) = &options.branch {
+        validate_name(branch)?;
+    }
+    if options.depth == Some(0) {
+        return Err(AppError::invalid_input("Clone depth must be at least 1"));
+    }
+
+    let reporter = RefCell::new(Reporter::new(
+        RemoteOperation::Clone,
+        DEFAULT_REMOTE,
+        progress,
+    ));
+    let mut fetch = FetchOptions::new();
+    fetch.remote_callbacks(remote_callbacks(auth, &reporter));
+    if let Some(depth) = options.depth {
+        fetch.depth(depth.min(i32::MAX as u32) as i32);
+    }
+    let mut checkout = CheckoutBuilder::new();
+    checkout.progress(|_, current, total| {
+        reporter
+            .borrow_mut()
+            .report(ProgressStage::CheckingOut, current, total, 0);
+    });
+    let mut builder = RepoBuilder::new();
+    builder.fetch_options(fetch).with_checkout(checkout);
+    if let Some(branch) = &options.branch {
+        builder.branch(branch);
+    }
+
+    let repo = builder.clone(url, destination).map_err(|e| {
+        remote_error(&format!("Failed to clone '{}'", url), e).with_path(destination)
+    })?;
+    Ok(CloneResult {
+        path: destination.to_string_lossy().into_owned(),
+        head: head_state(&repo)?,
+        shallow: repo.is_shallow(),
+    })
+}
+
+/// Fetch from `remote` (default: the current branch's remote, then `origin`)
+/// using its configured refspecs
+pub fn fetch(
+    repo: &Repository,
+    remote: Option<&str>,
+    prune: bool,
+    auth: &RemoteAuth,
+    progress: &mut dyn FnMut(RemoteProgress),
+) -> AppResult<FetchResult> {
+    let remote_name = match remote {
+        Some(name) => name.to_string(),
+        None => default_remote(repo),
+    };
+    fetch_from(
+        repo,
+        &remote_name,
+        prune,
+        auth,
+        RemoteOperation::Fetch,
+        progress,
+    )
+}
+
+fn fetch_from(
+    repo: &Repository,
+    remote_name: &str,
+    prune: bool,
+    auth: &RemoteAuth,
+    operation: RemoteOperation,
+    progress: &mut dyn FnMut(RemoteProgress),
+) -> AppResult<FetchResult> {
+    let mut remote = find_remote(repo, remote_name)?;
+    let reporter = RefCell::new(Reporter::new(operation, remote_name, progress));
+    let updated = RefCell::new(Vec::new());
+
+    let mut callbacks = remote_callbacks(auth, &reporter);
+    callbacks.update_tips(|name, old, new| {
+        updated.borrow_mut().push(RefUpdate {
+            name: name.to_string(),
+            old: (!old.is_zero()).then(|| old.to_string()),
+            new: (!new.is_zero()).then(|| new.to_string()),
+        });
+        true
+    });
+    let mut options = FetchOptions::new();
+    options
+        .remote_callbacks(callbacks)
+        .download_tags(AutotagOption::Auto)
+        .prune(if prune {
+            FetchPrune::On
+        } else {
+            FetchPrune::Unspecified
+        });
+
+    remote
+        .fetch(&[] as &[&str], Some(&mut options), None)
+        .map_err(|e| remote_error(&format!("Failed to fetch from '{}'", remote_name), e))?;
+    drop(options);
+
+    let stats = remote.stats();
+    Ok(FetchResult {
+        remote: remote_name.to_string(),
+        updated: updated.into_inner(),
+        received_objects: stats.received_objects(),
+        received_bytes: stats.received_bytes(),
+    })
+}
+
+/// Fetch, then bring the current branch up to date with its upstream (or
+/// `<remote>/<branch>` when it has none) by fast-forwarding or rebasing
+pub fn pull(
+    repo: &Repository,
+    remote: Option<&str>,
+    mode: PullMode,
+    auth: &RemoteAuth,
+    progress: &mut dyn FnMut(RemoteProgress),
+) -> AppResult<PullResult> {
+    let fetched = pull_fetch(repo, remote, auth, progress)?;
+    pull_integrate(repo, fetched, mode)
+}
+
+/// What the fetch half of a pull found, for `pull_integrate`
+pub struct PullFetch {
+    branch: String,
+    tracking_ref: String,
+    fetch: FetchResult,
+}
+
+/// The network half of `pull`, which can run on its own repository handle
+pub fn pull_fetch(
+    repo: &Repository,
+    remote: Option<&str>,
+    auth: &RemoteAuth,
+    progress: &mut dyn FnMut(RemoteProgress),
+) -> AppResult<PullFetch> {
+    let branch = pullable_branch(repo)?;
+    let (remote_name, tracking_ref) = pull_source(repo, &branch, remote)?;
+    let fetch = fetch_from(
+        repo,
+        &remote_name,
+        false,
+        auth,
+        RemoteOperation::Pull,
+        progress,
+    )?;
+    Ok(PullFetch {
+        branch,
+        tracking_ref,
+        fetch,
+    })
+}
+
+/// The local half of `pull`: fast-forward or rebase onto what `pull_fetch`
+/// fetched. The checks are repeated since the repository may have changed
+/// during the transfer.
+pub fn pull_integrate(
+    repo: &Repository,
+    fetched: PullFetch,
+    mode: PullMode,
+) -> AppResult<PullResult> {
+    let PullFetch {
+        branch,
+        tracking_ref,
+        fetch,
+    } = fetched;
+    if pullable_branch(repo)? != branch {
+        return Err(AppError::conflict(format!(
+            "Branch '{}' is no longer checked out; pull again",
+            branch
+        )));
+    }
+
+    let upstream = tracking_ref
+        .strip_prefix("refs/remotes/")
+        .unwrap_or(&tracking_ref)
+        .to_string();
+    let reference = repo.find_reference(&tracking_ref).map_err(|e| {
+        if e.code() == git2::ErrorCode::NotFound {
+            AppError::not_found(format!("Remote branch '{}' does not exist", upstream))
+        } else {
+            AppError::git("Failed to read remote branch", e)
+        }
+    })?;
+    let incoming = repo
+        .reference_to_annotated_commit(&reference)
+        .map_err(|e| AppError::git("Failed to read remote branch", e))?;
+
+    let (analysis, _) = repo
+        .merge_analysis(&[&incoming])
+        .map_err(|e| AppError::git("Failed to analyse pull", e))?;
+    let outcome = if analysis.is_up_to_date() {
+        PullOutcome::UpToDate
+    } else if analysis.is_fast_forward() || analysis.is_unborn() {
+        fast_forward(repo, incoming.id())?;
+        PullOutcome::FastForward {
+            commit: incoming.id().to_string(),
+        }
+    } else if mode == PullMode::FastForward {
+        return Err(AppError::conflict(format!(
+            "Branch '{}' has diverged from '{}'; pull with rebase instead",
+            branch, upstream
+        )));
+    } else {
+        rebase_onto(repo, &incoming)?
+    };
+
+    Ok(PullResult {
+        fetch,
+        upstream,
+        outcome,
+    })
+}
+
+/// The checked-out branch, if nothing stops it from being pulled into
+fn pullable_branch(repo: &Repository) -> AppResult<String> {
+    let branch = head_state(repo)?
+        .branch_name()
+        .map(str::to_string)
+        .ok_or_else(|| AppError::invalid_input("HEAD is detached; check out a branch to pull"))?;
+    if repo.state() != RepositoryState::Clean {
+        return Err(AppError::conflict(
+            "Another operation is in progress; finish or abort it before pulling",
+        ));
+    }
+    if has_uncommitted_changes(repo)? {
+        return Err(AppError::conflict(
+            "Working tree has uncommitted changes; commit or stash them before pulling",
+        ));
+    }
+    Ok(branch)
+}
+
+/// The remote to pull from and the remote-tracking ref to integrate
+fn pull_source(
+    repo: &Repository,
+    branch: &str,
+    remote: Option<&str>,
+) -> AppResult<(String, String)> {
+    let local_ref = format!("refs/heads/{}", branch);
+    let upstream_remote = repo
+        .branch_upstream_remote(&local_ref)
+        .ok()
+        .and_then(|buf| buf.as_str().map(str::to_string));
+    let upstream_ref = repo
+        .branch_upstream_name(&local_ref)
+        .ok()
+        .and_then(|buf| buf.as_str().map(str::to_string));
+
+    match (remote, upstream_remote, upstream_ref) {
+        (None, Some(remote), Some(upstream)) => Ok((remote, upstream)),
+        (Some(remote), Some(configured), Some(upstream)) if remote == configured => {
+            Ok((configured, upstream))
+        }
+        (remote, _, _) => {
+            let remote = remote.unwrap_or(DEFAULT_REMOTE).to_string();
+            let tracking = format!("refs/remotes/{}/{}", remote, branch);
+            Ok((remote, tracking))
+        }
+    }
+}
+
+/// Replay the current branch onto `upstream`, rolling back if any commit conflicts
+fn rebase_onto(repo: &Repository, upstream: &git2::AnnotatedCommit) -> AppResult<PullOutcome> {
+    let committer = repo
+        .signature()
+        .map_err(|e| AppError::git("Failed to get signature", e))?;
+    let mut options = RebaseOptions::new();
+    let mut rebase = repo
+        .rebase(None, Some(upstream), None, Some(&mut options))
+        .map_err(|e| AppError::git("Failed to start rebase", e))?;
+
+    let mut rebased = 0;
+    while let Some(operation) = rebase.next() {
+        let step = operation
+            .map_err(|e| AppError::git("Failed to apply commit during rebase", e))
+            .and_then(|_| {
+                let index = repo
+                    .index()
+                    .map_err(|e| AppError::git("Failed to get index", e))?;
+                if index.has_conflicts() {
+                    return Ok(Some(conflicted_paths(&index)?));
+                }
+                match rebase.commit(None, &committer, None) {
+                    Ok(_) => rebased += 1,
+                    // The remote already has this change; drop the empty commit
+                    Err(e) if e.code() == git2::ErrorCode::Applied => {}
+                    Err(e) => return Err(AppError::git("Failed to commit during rebase", e)),
+                }
+                Ok(None)
+            });
+        match step {
+            Ok(None) => {}
+            Ok(Some(conflicts)) => {
+                rebase
+                    .abort()
+                    .map_err(|e| AppError::git("Failed to abort rebase", e))?;
+                return Ok(PullOutcome::Conflicted { conflicts });
+            }
+            Err(e) => {
+                let _ = rebase.abort();
+                return Err(e);
+            }
+        }
+    }
+    rebase
+        .finish(Some(&committer))
+        .map_err(|e| AppError::git("Failed to finish rebase", e))?;
+
+    let head = repo
+        .head()
+        .ok()
+        .and_then(|h| h.target())
+        .ok_or_else(|| AppError::internal("HEAD is missing after rebase"))?;
+    Ok(PullOutcome::Rebased {
+        commit: head.to_string(),
+        rebased,
+    })
+}
+
+/// Push a branch to the same-named branch on the remote, or to its upstream
+/// there. Rejections fail with a conflict error.
+pub fn push(
+    repo: &Repository,
+    request: &PushRequest,
+    auth: &RemoteAuth,
+    progress: &mut dyn FnMut(RemoteProgress),
+) -> AppResult<PushResult> {
+    let branch = match request.branch.as_deref() {
+        Some(branch) => branch.to_string(),
+        None => head_state(repo)?
+            .branch_name()
+            .map(str::to_string)
+            .ok_or_else(|| AppError::invalid_input("HEAD is detached; name the branch to push"))?,
+    };
+    let local = repo
+        .find_branch(&branch, BranchType::Local)
+        .map_err(|e| AppError::git(&format!("Failed to find branch '{}'", branch), e))?;
+    if local.get().target().is_none() {
+        return Err(AppError::invalid_input(format!(
+            "Branch '{}' has no commits to push",
+            branch
+        )));
+    }
+
+    let local_ref = format!("refs/heads/{}", branch);
+    let remote_name = match request.remote.as_deref() {
+        Some(name) => name.to_string(),
+        None => repo
+            .branch_upstream_remote(&local_ref)
+            .ok()
+            .and_then(|buf| buf.as_str().map(str::to_string))
+            .unwrap_or_else(|| DEFAULT_REMOTE.to_string()),
+    };
+    // Keep pushing to the upstream's branch name when it lives on this remote
+    let tracking_prefix = format!("refs/remotes/{}/", remote_name);
+    let remote_branch = repo
+        .branch_upstream_name(&local_ref)
+        .ok()
+        .and_then(|buf| buf.as_str().map(str::to_string))
+        .and_then(|name| name.strip_prefix(&tracking_prefix).map(str::to_string))
+        .unwrap_or_else(|| branch.clone());
+
+    let mut remote = find_remote(repo, &remote_name)?;
+    let reporter = RefCell::new(Reporter::new(RemoteOperation::Push, &remote_name, progress));
+    let rejected = RefCell::new(Vec::new());
+    let mut callbacks = remote_callbacks(auth, &reporter);
+    callbacks.push_update_reference(|name, status| {
+        if let Some(status) = status {
+            rejected.borrow_mut().push(format!("{} ({})", name, status));
+        }
+        Ok(())
+    });
+    let mut options = PushOptions::new();
+    options.remote_callbacks(callbacks);
+
+    let refspec = format!(
+        "{}{}:refs/heads/{}",
+        if request.force { "+" } else { "" },
+        local_ref,
+        remote_branch
+    );
+    remote
+        .push(&[refspec.as_str()], Some(&mut options))
+        .map_err(|e| {
+            if e.code() == git2::ErrorCode::NotFastForward {
+                AppError::conflict(format!(
+                    "Push to '{}/{}' was rejected because the remote has commits you don't; pull first",
+                    remote_name, remote_branch
+                ))
+            } else {
+                remote_error(&format!("Failed to push to '{}'", remote_name), e)
+            }
+        })?;
+    drop(options);
+
+    let rejected = rejected.into_inner();
+    if !rejected.is_empty() {
+        return Err(AppError::conflict(format!(
+            "Remote rejected {}",
+            rejected.join(", ")
+        )));
+    }
+
+    if request.set_upstream {
+        let mut local = repo
+            .find_branch(&branch, BranchType::Local)
+            .map_err(|e| AppError::git(&format!("Failed to find branch '{}'", branch), e))?;
+        local
+            .set_upstream(Some(&format!("{}/{}", remote_name, remote_branch)))
+            .map_err(|e| AppError::git("Failed to set upstream", e))?;
+    }
+
+    Ok(PushResult {
+        remote: remote_name,
+        branch,
+        remote_branch,
+        upstream_set: request.set_upstream,
+    })
+}
+
+fn default_remote(repo: &Repository) -> String {
+    head_state(repo)
+        .ok()
+        .and_then(|head| {
+            head.branch_name()
+                .map(|name| format!("refs/heads/{}", name))
+        })
+        .and_then(|local_ref| repo.branch_upstream_remote(&local_ref).ok())
+        .and_then(|buf| buf.as_str().map(str::to_string))
+        .unwrap_or_else(|| DEFAULT_REMOTE.to_string())
+}
+
+fn find_remote<'r>(repo: &'r Repository, name: &str) -> AppResult<git2::Remote<'r>> {
+    repo.find_remote(name).map_err(|e| {
+        if e.code() == git2::ErrorCode::NotFound {
+            AppError::not_found(format!("Remote '{}' does not exist", name))
+        } else {
+            AppError::git(&format!("Failed to find remote '{}'", name), e)
+        }
+    })
+}
+
+/// Like `AppError::git`, with a hint when authentication was the problem
+fn remote_error(context: &str, err: git2::Error) -> AppError {
+    if err.code() == git2::ErrorCode::Auth {
+        let mut error = AppError::git(context, err);
+        error
+            .message
+            .push_str(" (check the SSH agent, key file or access token in settings)");
+        error
+    } else {
+        AppError::git(context, err)
+    }
+}
+
+/// Callbacks shared by fetch and push: credentials and progress
+fn remote_callbacks<'a>(
+    auth: &'a RemoteAuth,
+    reporter: &'a RefCell<Reporter<'_>>,
+) -> RemoteCallbacks<'a> {
+    let mut credentials = CredentialState::new(auth);
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| credentials.next(url, username, allowed));
+    callbacks.transfer_progress(|stats| {
+        let mut reporter = reporter.borrow_mut();
+        if stats.received_objects() < stats.total_objects() {
+            reporter.report(
+                ProgressStage::Receiving,
+                stats.received_objects(),
+                stats.total_objects(),
+                stats.received_bytes(),
+            );
+        } else {
+            reporter.report(
+                ProgressStage::Resolving,
+                stats.indexed_deltas(),
+                stats.total_deltas(),
+                stats.received_bytes(),
+            );
+        }
+        true
+    });
+    callbacks.sideband_progress(|data| {
+        let text = String::from_utf8_lossy(data);
+        let last_line = text
+            .split(['\r', '\n'])
+            .map(str::trim)
+            .rfind(|line| !line.is_empty());
+        if let Some(line) = last_line {
+            reporter.borrow_mut().message(line);
+        }
+        true
+    });
+    callbacks.pack_progress(|_, current, total| {
+        reporter
+            .borrow_mut()
+            .report(ProgressStage::Packing, current, total, 0);
+    });
+    callbacks.push_transfer_progress(|current, total, bytes| {
+        reporter
+            .borrow_mut()
+            .report(ProgressStage::Sending, current, total, bytes);
+    });
+    callbacks
+}
+
+/// Forwards progress, dropping updates that don't move a stage by at least a percent
+struct Reporter<'p> {
+    operation: RemoteOperation,
+    remote: String,
+    sink: &'p mut dyn FnMut(RemoteProgress),
+    last: Option<(ProgressStage, usize)>,
+}
+
+impl<'p> Reporter<'p> {
+    fn new(
+        operation: RemoteOperation,
+        remote: &str,
+        sink: &'p mut dyn FnMut(RemoteProgress),
+    ) -> Self {
+        Self {
+            operation,
+            remote: remote.to_string(),
+            sink,
+            last: None,
+        }
+    }
+
+    fn report(&mut self, stage: ProgressStage, current: usize, total: usize, bytes: usize) {
+        let percent = (current * 100).checked_div(total).unwrap_or(100);
+        if self.last == Some((stage, percent)) {
+            return;
+        }
+        self.last = Some((stage, percent));
+        (self.sink)(RemoteProgress {
+            operation: self.operation,
+            remote: self.remote.clone(),
+            stage,
+            current,
+            total,
+            bytes,
+            message: None,
+        });
+    }
+
+    fn message(&mut self, line: &str) {
+        (self.sink)(RemoteProgress {
+            operation: self.operation,
+            remote: self.remote.clone(),
+            stage: ProgressStage::Remote,
+            current: 0,
+            total: 0,
+            bytes: 0,
+            message: Some(line.to_string()),
+        });
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CredentialKind {
+    SshAgent,
+    SshKey,
+    Token,
+    /// Platform default, e.g. NTLM or Kerberos
+    Default,
+}
+
+/// libgit2 asks again after every rejected credential, so each kind is offered
+/// once and the callback fails when none are left
+struct CredentialState<'a> {
+    auth: &'a RemoteAuth,
+    tried: Vec<CredentialKind>,
+}
+
+impl<'a> CredentialState<'a> {
+    fn new(auth: &'a RemoteAuth) -> Self {
+        Self {
+            auth,
+            tried: Vec::new(),
+        }
+    }
+
+    fn next(
+        &mut self,
+        url: &str,
+        url_username: Option<&str>,
+        allowed: CredentialType,
+    ) -> Result<Cred, git2::Error> {
+        let username = self
+            .auth
+            .username
+            .as_deref()
+            .or(url_username)
+            .unwrap_or("git");
+        if allowed.contains(CredentialType::USERNAME) {
+            return Cred::username(username);
+        }
+
+        while let Some(kind) = self.next_kind(allowed) {
+            self.tried.push(kind);
+            let cred = match kind {
+                CredentialKind::SshAgent => Cred::ssh_key_from_agent(username),
+                CredentialKind::SshKey => Cred::ssh_key(
+                    username,
+                    None,
+                    Path::new(self.auth.ssh_key_path.as_deref().unwrap_or_default()),
+                    self.auth.ssh_passphrase.as_deref(),
+                ),
+                CredentialKind::Token => Cred::userpass_plaintext(
+                    self.auth
+                        .username
+                        .as_deref()
+                        .or(url_username)
+                        .unwrap_or("x-access-token"),
+                    self.auth.token.as_deref().unwrap_or_default(),
+                ),
+                CredentialKind::Default => Cred::default(),
+            };
+            // An unusable kind (e.g. no agent running) moves on to the next one
+            if let Ok(cred) = cred {
+                return Ok(cred);
+            }
+        }
+        Err(git2::Error::new(
+            git2::ErrorCode::Auth,
+            git2::ErrorClass::Callback,
+            format!("No usable credentials for {}", url),
+        ))
+    }
+
+    fn next_kind(&self, allowed: CredentialType) -> Option<CredentialKind> {
+        let mut candidates = Vec::new();
+        if allowed.contains(CredentialType::SSH_KEY) {
+            candidates.push(CredentialKind::SshAgent);
+            if self.auth.ssh_key_path.is_some() {
+                candidates.push(CredentialKind::SshKey);
+            }
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) && self.auth.token.is_some() {
+            candidates.push(CredentialKind::Token);
+        }
+        if allowed.contains(CredentialType::DEFAULT) {
+            candidates.push(CredentialKind::Default);
+        }
+        candidates
+            .into_iter()
+            .find(|kind| !self.tried.contains(kind))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::branch::create_branch;
+    use crate::git::test_support::{commit_all, init_repo, write};
+    use std::path::PathBuf;
+
+    /// A bare "server" repository and a clone of it with an identity configured
+    fn bare_remote() -> (tempfile::TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut options = git2::RepositoryInitOptions::new();
+        options.bare(true).initial_head("master");
+        Repository::init_opts(dir.path(), &options).unwrap();
+        let url = format!("file://{}", fs::canonicalize(dir.path()).unwrap().display());
+        (dir, url)
+    }
+
+    fn working_clone(url: &str) -> (tempfile::TempDir, PathBuf, Repository) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let repo = Repository::clone(url, &root).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Nova Test").unwrap();
+        config.set_str("user.email", "test@nova.dev").unwrap();
+        (dir, root, repo)
+    }
+
+    fn no_progress() -> impl FnMut(RemoteProgress) {
+        |_| {}
+    }
+
+    /// A repository with one commit pushed to a fresh bare remote as `origin/master`
+    fn published() -> (
+        tempfile::TempDir,
+        String,
+        tempfile::TempDir,
+        PathBuf,
+        Repository,
+    ) {
+        let (server, url) = bare_remote();
+        let (dir, root, repo) = init_repo();
+        write(&root, "a.txt", "one\n");
+        commit_all(&repo, "initial");
+        repo.remote(DEFAULT_REMOTE, &url).unwrap();
+        push(
+            &repo,
+            &PushRequest {
+                set_upstream: true,
+                ..Default::default()
+            },
+            &RemoteAuth::default(),
+            &mut no_progress(),
+        )
+        .unwrap();
+        (server, url, dir, root, repo)
+    }
+
+    #[test]
+    fn test_push_fetch_and_fast_forward_pull() {
+        let (_server, url, _dir, root, repo) = published();
+        let upstream = repo
+            .find_branch("master", BranchType::Local)
+            .unwrap()
+            .upstream()
+            .unwrap();
+        assert_eq!(upstream.name().unwrap(), Some("origin/master"));
+
+        let (_other_dir, other_root, other) = working_clone(&url);
+        write(&other_root, "b.txt", "two\n");
+        let tip = commit_all(&other, "from the clone");
+        let mut events = Vec::new();
+        let pushed = push(
+            &other,
+            &PushRequest::default(),
+            &RemoteAuth::default(),
+            &mut |p| events.push(p),
+        )
+        .unwrap();
+        assert_eq!(pushed.remote_branch, "master");
+        assert!(events.iter().all(|p| p.operation == RemoteOperation::Push));
+
+        let mut events = Vec::new();
+        let fetched = fetch(&repo, None, false, &RemoteAuth::default(), &mut |p| {
+            events.push(p)
+        })
+        .unwrap();
+        assert_eq!(fetched.remote, "origin");
+        assert_eq!(
+            fetched.updated,
+            vec![RefUpdate {
+                name: "refs/remotes/origin/master".to_string(),
+                old: Some(repo.head().unwrap().target().unwrap().to_string()),
+                new: Some(tip.to_string()),
+            }]
+        );
+        assert!(fetched.received_objects > 0);
+        assert!(events
+            .iter()
+            .any(|p| p.stage == ProgressStage::Receiving || p.stage == ProgressStage::Resolving));
+
+        let pulled = pull(
+            &repo,
+            None,
+            PullMode::FastForward,
+            &RemoteAuth::default(),
+            &mut no_progress(),
+        )
+        .unwrap();
+        assert_eq!(pulled.upstream, "origin/master");
+        assert_eq!(
+            pulled.outcome,
+            PullOutcome::FastForward {
+                commit: tip.to_string()
+            }
+        );
+        assert_eq!(fs::read_to_string(root.join("b.txt")).unwrap(), "two\n");
+
+        let again = pull(
+            &repo,
+            None,
+            PullMode::FastForward,
+            &RemoteAuth::default(),
+            &mut no_progress(),
+        )
+        .unwrap();
+        assert_eq!(again.outcome, PullOutcome::UpToDate);
+        assert!(again.fetch.updated.is_empty());
+    }
+
+    #[test]
+    fn test_pull_fetches_on_a_separate_handle() {
+        let (_server, url, _dir, root, repo) = published();
+        let (_other_dir, other_root, other) = working_clone(&url);
+        write(&other_root, "b.txt", "two\n");
+        let tip = commit_all(&other, "from the clone");
+        let auth = RemoteAuth::default();
+        push(&other, &PushRequest::default(), &auth, &mut no_progress()).unwrap();
+
+        let transfer = Repository::open(repo.path()).unwrap();
+        let fetched = pull_fetch(&transfer, None, &auth, &mut no_progress()).unwrap();
+        let pulled = pull_integrate(&repo, fetched, PullMode::FastForward).unwrap();
+        assert_eq!(
+            pulled.outcome,
+            PullOutcome::FastForward {
+                commit: tip.to_string()
+            }
+        );
+        assert_eq!(fs::read_to_string(root.join("b.txt")).unwrap(), "two\n");
+
+        // The branch changed while the transfer ran
+        let fetched = pull_fetch(&transfer, None, &auth, &mut no_progress()).unwrap();
+        create_branch(&repo, "elsewhere", None, true).unwrap();
+        let err = pull_integrate(&repo, fetched, PullMode::FastForward).unwrap_err();
+        assert_eq!(err.code, crate::error::ErrorCode::Conflict);
+    }
+
+    #[test]
+    fn test_diverged_pull_and_push() {
+        let (_server, url, _dir, root, repo) = published();
+        let (_other_dir, other_root, other) = working_clone(&url);
+        write(&other_root, "b.txt", "remote\n");
+        let remote_tip = commit_all(&other, "remote work");
+        push(
+            &other,
+            &PushRequest::default(),
+            &RemoteAuth::default(),
+            &mut no_progress(),
+        )
+        .unwrap();
+        write(&root, "c.txt", "local\n");
+        commit_all(&repo, "local work");
+
+        let auth = RemoteAuth::default();
+        let rejected = push(&repo, &PushRequest::default(), &auth, &mut no_progress());
+        assert_eq!(
+            rejected.unwrap_err().code,
+            crate::error::ErrorCode::Conflict
+        );
+        let diverged = pull(
+            &repo,
+            None,
+            PullMode::FastForward,
+            &auth,
+            &mut no_progress(),
+        );
+        assert_eq!(
+            diverged.unwrap_err().code,
+            crate::error::ErrorCode::Conflict
+        );
+
+        let pulled = pull(&repo, None, PullMode::Rebase, &auth, &mut no_progress()).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(
+            pulled.outcome,
+            PullOutcome::Rebased {
+                commit: head.id().to_string(),
+                rebased: 1,
+            }
+        );
+        assert_eq!(head.parent_id(0).unwrap(), remote_tip);
+        assert_eq!(head.summary(), Some("local work"));
+        assert!(root.join("b.txt").exists() && root.join("c.txt").exists());
+        push(&repo, &PushRequest::default(), &auth, &mut no_progress()).unwrap();
+    }
+
+    #[test]
+    fn test_rebase_conflict_rolls_back() {
+        let (_server, url, _dir, root, repo) = published();
+        let (_other_dir, other_root, other) = working_clone(&url);
+        write(&other_root, "a.txt", "remote\n");
+        commit_all(&other, "remote edit");
+        let auth = RemoteAuth::default();
+        push(&other, &PushRequest::default(), &auth, &mut no_progress()).unwrap();
+        write(&root, "a.txt", "local\n");
+        let local_tip = commit_all(&repo, "local edit");
+
+        let pulled = pull(&repo, None, PullMode::Rebase, &auth, &mut no_progress()).unwrap();
+        assert_eq!(
+            pulled.outcome,
+            PullOutcome::Conflicted {
+                conflicts: vec!["a.txt".to_string()]
+            }
+        );
+        assert_eq!(repo.state(), RepositoryState::Clean);
+        assert_eq!(repo.head().unwrap().target(), Some(local_tip));
+        assert_eq!(fs::read_to_string(root.join("a.txt")).unwrap(), "local\n");
+
+        // Forcing replaces the remote branch with the local one
+        push(
+            &repo,
+            &PushRequest {
+                force: true,
+                ..Default::default()
+            },
+            &auth,
+            &mut no_progress(),
+        )
+        .unwrap();
+        other
+            .find_remote(DEFAULT_REMOTE)
+            .unwrap()
+            .fetch(&[] as &[&str], None, None)
+            .unwrap();
+        let remote_master = other.find_reference("refs/remotes/origin/master").unwrap();
+        assert_eq!(remote_master.target(), Some(local_tip));
+    }
+
+    #[test]
+    fn test_missing_remote_and_detached_head() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "one\n");
+        let first = commit_all(&repo, "initial");
+        let auth = RemoteAuth::default();
+        let err = fetch(&repo, None, false, &auth, &mut no_progress()).unwrap_err();
+        assert_eq!(err.code, crate::error::ErrorCode::NotFound);
+
+        repo.set_head_detached(first).unwrap();
+        let err = pull(&repo, None, PullMode::Rebase, &auth, &mut no_progress()).unwrap_err();
+        assert_eq!(err.code, crate::error::ErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn test_clone_branch_and_depth() {
+        let (_server, url, _dir, root, repo) = published();
+        write(&root, "a.txt", "two\n");
+        let second = commit_all(&repo, "second");
+        create_branch(&repo, "feature", None, false).unwrap();
+        let auth = RemoteAuth::default();
+        for branch in ["master", "feature"] {
+            let request = PushRequest {
+                branch: Some(branch.to_string()),
+                ..Default::default()
+            };
+            push(&repo, &request, &auth, &mut no_progress()).unwrap();
+        }
+
+        let target = tempfile::tempdir().unwrap();
+        let destination = target.path().join("copy");
+        let mut events = Vec::new();
+        let options = CloneOptions {
+            branch: Some("feature".to_string()),
+            depth: None,
+        };
+        let cloned = clone(&url, &destination, &options, &auth, &mut |p| events.push(p)).unwrap();
+        assert_eq!(
+            cloned.head,
+            HeadState::Branch {
+                name: "feature".to_string(),
+                commit: second.to_string(),
+            }
+        );
+        assert!(!cloned.shallow);
+        assert_eq!(
+            fs::read_to_string(destination.join("a.txt")).unwrap(),
+            "two\n"
+        );
+        assert!(events.iter().all(|p| p.operation == RemoteOperation::Clone));
+        assert!(events.iter().any(|p| p.stage == ProgressStage::CheckingOut));
+
+        let err = clone(&url, &destination, &options, &auth, &mut no_progress()).unwrap_err();
+        assert_eq!(err.code, crate::error::ErrorCode::AlreadyExists);
+        let missing = CloneOptions {
+            branch: Some("nope".to_string()),
+            depth: None,
+        };
+        let other = target.path().join("other");
+        assert!(clone(&url, &other, &missing, &auth, &mut no_progress()).is_err());
+
+        let shallow = CloneOptions {
+            branch: None,
+            depth: Some(1),
+        };
+        // libgit2 always copies full history over the local transport
+        let cloned = clone(&url, &other, &shallow, &auth, &mut no_progress()).unwrap();
+        assert!(!cloned.shallow);
+        assert_eq!(cloned.head.branch_name(), Some("master"));
+    }
+
+    #[test]
+    fn test_credentials_are_offered_once_each() {
+        let auth = RemoteAuth {
+            token: Some("secret".to_string()),
+            ssh_key_path: Some("/keys/id_ed25519".to_string()),
+            ..Default::default()
+        };
+        let mut state = CredentialState::new(&auth);
+        let ssh = CredentialType::SSH_KEY;
+        assert_eq!(state.next_kind(ssh), Some(CredentialKind::SshAgent));
+        state.tried.push(CredentialKind::SshAgent);
+        assert_eq!(state.next_kind(ssh), Some(CredentialKind::SshKey));
+        state.tried.push(CredentialKind::SshKey);
+        assert_eq!(state.next_kind(ssh), None);
+
+        let https = CredentialType::USER_PASS_PLAINTEXT;
+        assert_eq!(state.next_kind(https), Some(CredentialKind::Token));
+        state.tried.push(CredentialKind::Token);
+        assert_eq!(state.next_kind(https), None);
+
+        let mut state = CredentialState::new(&auth);
+        let token = state.next("https://example.com/repo.git", None, https);
+        assert!(token.is_ok());
+        let exhausted = state
+            .next("https://example.com/repo.git", None, https)
+            .err()
+            .unwrap();
+        assert_eq!(exhausted.code(), git2::ErrorCode::Auth);
+    }
+}
@@ -0,0 +1,301 @@
+// Git Stash — R20-02
+// Save, list, inspect, apply and drop stashes, reporting conflicts instead of failing
+
+use git2::build::CheckoutBuilder;
+use git2::{CheckoutNotificationType, Repository, StashApplyOptions, StashFlags};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+use super::conflicted_paths;
+use super::diff::{diff, DiffFileStatus, DiffRequest, DiffResult, DiffTarget};
+use crate::error::{AppError, AppResult};
+use crate::workspace::to_slash_path;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct StashOptions {
+    pub message: Option<String>,
+    /// Stash untracked files too, leaving them out of the working tree
+    #[serde(default)]
+    pub include_untracked: bool,
+    /// Leave staged changes in the index as well as stashing them
+    #[serde(default)]
+    pub keep_index: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StashEntry {
+    /// Position in the stash list; 0 is the most recent
+    pub index: usize,
+    pub id: String,
+    pub message: String,
+    /// Branch that was checked out when the stash was saved
+    pub branch: Option<String>,
+    /// Unix milliseconds
+    pub time: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct StashApplyResult {
+    pub applied: bool,
+    /// Files left with conflict markers after applying
+    pub conflicts: Vec<String>,
+    /// Local changes that stopped the stash from being applied at all
+    pub blocked_by: Vec<String>,
+    /// True if the entry was removed from the stash list (pop without conflicts)
+    pub dropped: bool,
+}
+
+pub fn save(repo: &mut Repository, options: &StashOptions) -> AppResult<StashEntry> {
+    let signature = repo
+        .signature()
+        .map_err(|e| AppError::git("Failed to get signature", e))?;
+    let mut flags = StashFlags::DEFAULT;
+    if options.include_untracked {
+        flags |= StashFlags::INCLUDE_UNTRACKED;
+    }
+    if options.keep_index {
+        flags |= StashFlags::KEEP_INDEX;
+    }
+
+    let message = options.message.as_deref().filter(|m| !m.trim().is_empty());
+    repo.stash_save2(&signature, message, Some(flags))
+        .map_err(|e| {
+            if e.code() == git2::ErrorCode::NotFound {
+                AppError::invalid_input("No local changes to stash")
+            } else {
+                AppError::git("Failed to stash changes", e)
+            }
+        })?;
+
+    list(repo)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::internal("Stash was saved but is not listed"))
+}
+
+pub fn list(repo: &mut Repository) -> AppResult<Vec<StashEntry>> {
+    let mut raw = Vec::new();
+    repo.stash_foreach(|index, message, id| {
+        raw.push((index, message.to_string(), *id));
+        true
+    })
+    .map_err(|e| AppError::git("Failed to list stashes", e))?;
+
+    Ok(raw
+        .into_iter()
+        .map(|(index, message, id)| {
+            let time = repo
+                .find_commit(id)
+                .map(|c| c.time().seconds() * 1000)
+                .unwrap_or_default();
+            let (branch, message) = parse_message(&message);
+            StashEntry {
+                index,
+                id: id.to_string(),
+                message,
+                branch,
+                time,
+            }
+        })
+        .collect())
+}
+
+/// Split `On main: message` / `WIP on main: abc1234 summary` into branch and message
+fn parse_message(raw: &str) -> (Option<String>, String) {
+    let rest = raw
+        .strip_prefix("WIP on ")
+        .or_else(|| raw.strip_prefix("On "));
+    match rest.and_then(|r| r.split_once(": ")) {
+        Some((branch, message)) => (Some(branch.to_string()), message.to_string()),
+        None => (None, raw.to_string()),
+    }
+}
+
+/// The changes recorded in a stash, including any untracked files it holds
+pub fn show(repo: &mut Repository, index: usize, request: &DiffRequest) -> AppResult<DiffResult> {
+    let entry = list(repo)?
+        .into_iter()
+        .find(|e| e.index == index)
+        .ok_or_else(|| AppError::not_found(format!("No stash entry at index {}", index)))?;
+
+    let mut result = diff(
+        repo,
+        &DiffTarget::Commits {
+            from: None,
+            to: entry.id.clone(),
+        },
+        request,
+    )?;
+
+    // With --include-untracked the third parent holds the untracked files
+    let stash = repo
+        .find_commit(git2::Oid::from_str(&entry.id).expect("listed stash id is valid"))
+        .map_err(|e| AppError::git("Failed to read stash", e))?;
+    if let Ok(untracked) = stash.parent_id(2) {
+        let extra = diff(
+            repo,
+            &DiffTarget::Commits {
+                from: None,
+                to: untracked.to_string(),
+            },
+            request,
+        )?;
+        for mut file in extra.files {
+            file.status = DiffFileStatus::Untracked;
+            result.files.push(file);
+        }
+        result.files_changed += extra.files_changed;
+        result.insertions += extra.insertions;
+        result.deletions += extra.deletions;
+    }
+    Ok(result)
+}
+
+/// Apply a stash onto the working tree. Conflicting hunks are written with markers
+/// and reported; local changes that would be overwritten block the apply entirely.
+pub fn apply(
+    repo: &mut Repository,
+    index: usize,
+    reinstate_index: bool,
+) -> AppResult<StashApplyResult> {
+    let blocked = RefCell::new(Vec::new());
+    let outcome = {
+        let mut checkout = CheckoutBuilder::new();
+        checkout
+            .notify_on(CheckoutNotificationType::CONFLICT)
+            .notify(|_, path, _, _, _| {
+                if let Some(path) = path {
+                    blocked.borrow_mut().push(to_slash_path(path));
+                }
+                true
+            });
+        let mut options = StashApplyOptions::new();
+        options.checkout_options(checkout);
+        if reinstate_index {
+            options.reinstantiate_index();
+        }
+        repo.stash_apply(index, Some(&mut options))
+    };
+
+    let blocked_by = blocked.into_inner();
+    match outcome {
+        Ok(()) => {}
+        Err(e) if e.code() == git2::ErrorCode::Conflict && !blocked_by.is_empty() => {
+            return Ok(StashApplyResult {
+                blocked_by,
+                ..Default::default()
+            });
+        }
+        Err(e) => return Err(AppError::git("Failed to apply stash", e)),
+    }
+
+    let mut repo_index = repo
+        .index()
+        .map_err(|e| AppError::git("Failed to get index", e))?;
+    repo_index
+        .read(false)
+        .map_err(|e| AppError::git("Failed to read index", e))?;
+    Ok(StashApplyResult {
+        applied: true,
+        conflicts: conflicted_paths(&repo_index)?,
+        ..Default::default()
+    })
+}
+
+/// Apply a stash and drop it, unless applying it left conflicts to resolve
+pub fn pop(
+    repo: &mut Repository,
+    index: usize,
+    reinstate_index: bool,
+) -> AppResult<StashApplyResult> {
+    let mut result = apply(repo, index, reinstate_index)?;
+    if result.applied && result.conflicts.is_empty() {
+        drop(repo, index)?;
+        result.dropped = true;
+    }
+    Ok(result)
+}
+
+pub fn drop(repo: &mut Repository, index: usize) -> AppResult<()> {
+    repo.stash_drop(index)
+        .map_err(|e| AppError::git("Failed to drop stash", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{commit_all, init_repo, write};
+    use std::fs;
+
+    #[test]
+    fn test_save_show_pop() {
+        let (_dir, root, mut repo) = init_repo();
+        write(&root, "a.txt", "one\n");
+        commit_all(&repo, "initial");
+
+        assert_eq!(
+            save(&mut repo, &StashOptions::default())
+                .unwrap_err()
+                .message,
+            "No local changes to stash"
+        );
+
+        write(&root, "a.txt", "two\n");
+        write(&root, "new.txt", "untracked\n");
+        let entry = save(
+            &mut repo,
+            &StashOptions {
+                message: Some("park work".to_string()),
+                include_untracked: true,
+                keep_index: false,
+            },
+        )
+        .unwrap();
+        assert_eq!(entry.message, "park work");
+        assert_eq!(entry.branch.as_deref(), Some("master"));
+        assert_eq!(fs::read_to_string(root.join("a.txt")).unwrap(), "one\n");
+        assert!(!root.join("new.txt").exists());
+
+        let shown = show(&mut repo, 0, &DiffRequest::default()).unwrap();
+        let statuses: Vec<_> = shown.files.iter().map(|f| f.status).collect();
+        assert_eq!(
+            statuses,
+            vec![DiffFileStatus::Modified, DiffFileStatus::Untracked]
+        );
+
+        let result = pop(&mut repo, 0, false).unwrap();
+        assert!(result.applied && result.dropped);
+        assert_eq!(fs::read_to_string(root.join("a.txt")).unwrap(), "two\n");
+        assert!(root.join("new.txt").exists());
+        assert!(list(&mut repo).unwrap().is_empty());
+        assert!(drop(&mut repo, 0).is_err());
+    }
+
+    #[test]
+    fn test_apply_reports_conflicts() {
+        let (_dir, root, mut repo) = init_repo();
+        write(&root, "a.txt", "one\n");
+        commit_all(&repo, "initial");
+        write(&root, "a.txt", "stashed\n");
+        save(&mut repo, &StashOptions::default()).unwrap();
+        write(&root, "a.txt", "committed\n");
+        commit_all(&repo, "diverge");
+
+        // Uncommitted edits to the same file block the apply
+        write(&root, "a.txt", "local\n");
+        let blocked = apply(&mut repo, 0, false).unwrap();
+        assert!(!blocked.applied);
+        assert_eq!(blocked.blocked_by, vec!["a.txt"]);
+        assert_eq!(fs::read_to_string(root.join("a.txt")).unwrap(), "local\n");
+
+        write(&root, "a.txt", "committed\n");
+        let result = pop(&mut repo, 0, false).unwrap();
+        assert!(result.applied);
+        assert_eq!(result.conflicts, vec!["a.txt"]);
+        assert!(!result.dropped);
+        assert_eq!(list(&mut repo).unwrap().len(), 1);
+        assert!(fs::read_to_string(root.join("a.txt"))
+            .unwrap()
+            .contains("<<<<<<<"));
+    }
+}
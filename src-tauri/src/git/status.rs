@@ -0,0 +1,339 @@
+// Git Status — R20-02
+// Per-file index and worktree states, renames, conflicts, upstream divergence and submodules
+
+use git2::{Repository, Status, StatusOptions, SubmoduleIgnore, SubmoduleStatus as SmStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::branch::{head_state, upstream_of, HeadState};
+use super::diff::DiffFileStatus;
+use crate::error::{AppError, AppResult};
+use crate::workspace::to_slash_path;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GitStatus {
+    pub head: HeadState,
+    /// Checked-out branch, or `None` when HEAD is detached
+    pub branch: Option<String>,
+    pub upstream: Option<UpstreamStatus>,
+    pub files: Vec<FileStatus>,
+    /// Tracked files with unstaged changes, deletions included
+    pub modified: Vec<String>,
+    /// Files with staged changes, deletions included
+    pub staged: Vec<String>,
+    pub untracked: Vec<String>,
+    pub conflicted: Vec<String>,
+    pub submodules: Vec<SubmoduleStatus>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UpstreamStatus {
+    pub name: String,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileStatus {
+    pub path: String,
+    /// Source path when the file was renamed, in the index or the worktree
+    pub old_path: Option<String>,
+    /// Change staged relative to HEAD
+    pub index: Option<DiffFileStatus>,
+    /// Unstaged change relative to the index
+    pub worktree: Option<DiffFileStatus>,
+    pub conflicted: bool,
+    pub is_submodule: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SubmoduleStatus {
+    pub name: String,
+    pub path: String,
+    pub url: Option<String>,
+    /// Commit recorded in HEAD and the one checked out in the submodule
+    pub head_commit: Option<String>,
+    pub workdir_commit: Option<String>,
+    pub initialized: bool,
+    /// The checked-out commit differs from the one recorded in HEAD
+    pub commit_changed: bool,
+    /// Modified or untracked files inside the submodule
+    pub dirty: bool,
+}
+
+pub fn status(repo: &Repository) -> AppResult<GitStatus> {
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false)
+        .renames_head_to_index(true)
+        .renames_index_to_workdir(true);
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .map_err(|e| AppError::git("Failed to get statuses", e))?;
+
+    let submodules = submodules(repo)?;
+    let submodule_paths: HashSet<&str> = submodules.iter().map(|s| s.path.as_str()).collect();
+
+    let mut files = Vec::new();
+    for entry in statuses.iter() {
+        let bits = entry.status();
+        let head_to_index = entry.head_to_index();
+        let index_to_workdir = entry.index_to_workdir();
+
+        // Renamed entries are keyed by their new path
+        let new_path = index_to_workdir
+            .as_ref()
+            .and_then(|d| d.new_file().path())
+            .or_else(|| head_to_index.as_ref().and_then(|d| d.new_file().path()))
+            .map(to_slash_path)
+            .or_else(|| entry.path().map(str::to_string))
+            .unwrap_or_default();
+        let old_path = if bits.is_index_renamed() {
+            head_to_index
+                .as_ref()
+                .and_then(|d| d.old_file().path())
+                .map(to_slash_path)
+        } else if bits.is_wt_renamed() {
+            index_to_workdir
+                .as_ref()
+                .and_then(|d| d.old_file().path())
+                .map(to_slash_path)
+        } else {
+            None
+        };
+
+        // Conflicted entries carry stage information rather than a change
+        let conflicted = bits.is_conflicted();
+        let (index, worktree) = if conflicted {
+            (None, None)
+        } else {
+            (index_state(bits), worktree_state(bits))
+        };
+        files.push(FileStatus {
+            is_submodule: submodule_paths.contains(new_path.as_str()),
+            path: new_path,
+            old_path,
+            index,
+            worktree,
+            conflicted,
+        });
+    }
+
+    let paths = |keep: fn(&FileStatus) -> bool| -> Vec<String> {
+        files
+            .iter()
+            .filter(|f| keep(f))
+            .map(|f| f.path.clone())
+            .collect()
+    };
+    let modified = paths(|f| matches!(f.worktree, Some(s) if s != DiffFileStatus::Untracked));
+    let staged = paths(|f| f.index.is_some());
+    let untracked = paths(|f| f.worktree == Some(DiffFileStatus::Untracked));
+    let conflicted = paths(|f| f.conflicted);
+
+    let head = head_state(repo)?;
+    let upstream = match head.branch_name() {
+        Some(name) => repo
+            .find_branch(name, git2::BranchType::Local)
+            .ok()
+            .and_then(|branch| upstream_of(repo, &branch))
+            .and_then(|(name, counts)| {
+                counts.map(|(ahead, behind)| UpstreamStatus {
+                    name,
+                    ahead,
+                    behind,
+                })
+            }),
+        None => None,
+    };
+
+    Ok(GitStatus {
+        branch: head.branch_name().map(|s| s.to_string()),
+        head,
+        upstream,
+        files,
+        modified,
+        staged,
+        untracked,
+        conflicted,
+        submodules,
+    })
+}
+
+fn index_state(bits: Status) -> Option<DiffFileStatus> {
+    if bits.is_index_new() {
+        Some(DiffFileStatus::Added)
+    } else if bits.is_index_modified() {
+        Some(DiffFileStatus::Modified)
+    } else if bits.is_index_deleted() {
+        Some(DiffFileStatus::Deleted)
+    } else if bits.is_index_renamed() {
+        Some(DiffFileStatus::Renamed)
+    } else if bits.is_index_typechange() {
+        Some(DiffFileStatus::TypeChange)
+    } else {
+        None
+    }
+}
+
+fn worktree_state(bits: Status) -> Option<DiffFileStatus> {
+    if bits.is_wt_new() {
+        Some(DiffFileStatus::Untracked)
+    } else if bits.is_wt_modified() {
+        Some(DiffFileStatus::Modified)
+    } else if bits.is_wt_deleted() {
+        Some(DiffFileStatus::Deleted)
+    } else if bits.is_wt_renamed() {
+        Some(DiffFileStatus::Renamed)
+    } else if bits.is_wt_typechange() {
+        Some(DiffFileStatus::TypeChange)
+    } else {
+        None
+    }
+}
+
+fn submodules(repo: &Repository) -> AppResult<Vec<SubmoduleStatus>> {
+    let submodules = repo
+        .submodules()
+        .map_err(|e| AppError::git("Failed to list submodules", e))?;
+    let mut result = Vec::new();
+    for submodule in submodules {
+        let name = submodule.name().unwrap_or_default().to_string();
+        let bits = repo
+            .submodule_status(&name, SubmoduleIgnore::None)
+            .map_err(|e| AppError::git("Failed to get submodule status", e))?;
+        result.push(SubmoduleStatus {
+            path: to_slash_path(submodule.path()),
+            url: submodule.url().map(str::to_string),
+            head_commit: submodule.head_id().map(|id| id.to_string()),
+            workdir_commit: submodule.workdir_id().map(|id| id.to_string()),
+            initialized: !bits.contains(SmStatus::WD_UNINITIALIZED),
+            commit_changed: bits.contains(SmStatus::WD_MODIFIED),
+            dirty: bits.intersects(
+                SmStatus::WD_INDEX_MODIFIED | SmStatus::WD_WD_MODIFIED | SmStatus::WD_UNTRACKED,
+            ),
+            name,
+        });
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::branch::create_branch;
+    use crate::git::merge::{merge, MergeOptions};
+    use crate::git::test_support::{commit_all, init_repo, write};
+    use std::fs;
+
+    fn file<'a>(status: &'a GitStatus, path: &str) -> &'a FileStatus {
+        status.files.iter().find(|f| f.path == path).unwrap()
+    }
+
+    #[test]
+    fn test_status_renames_deletions_and_states() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "old.txt", "same content\nacross the rename\n");
+        write(&root, "gone.txt", "bye\n");
+        write(&root, "both.txt", "v1\n");
+        commit_all(&repo, "initial");
+
+        fs::rename(root.join("old.txt"), root.join("new.txt")).unwrap();
+        let mut index = repo.index().unwrap();
+        index.remove_path(std::path::Path::new("old.txt")).unwrap();
+        index.add_path(std::path::Path::new("new.txt")).unwrap();
+        write(&root, "both.txt", "v2\n");
+        index.add_path(std::path::Path::new("both.txt")).unwrap();
+        index.write().unwrap();
+        write(&root, "both.txt", "v3\n");
+        fs::remove_file(root.join("gone.txt")).unwrap();
+        write(&root, "fresh.txt", "new\n");
+
+        let result = status(&repo).unwrap();
+        let renamed = file(&result, "new.txt");
+        assert_eq!(renamed.index, Some(DiffFileStatus::Renamed));
+        assert_eq!(renamed.old_path.as_deref(), Some("old.txt"));
+        assert_eq!(
+            file(&result, "gone.txt").worktree,
+            Some(DiffFileStatus::Deleted)
+        );
+        let both = file(&result, "both.txt");
+        assert_eq!(
+            (both.index, both.worktree),
+            (
+                Some(DiffFileStatus::Modified),
+                Some(DiffFileStatus::Modified)
+            )
+        );
+        assert_eq!(result.untracked, vec!["fresh.txt"]);
+        assert!(result.modified.contains(&"gone.txt".to_string()));
+        assert!(result.upstream.is_none());
+    }
+
+    #[test]
+    fn test_status_conflicts_and_upstream() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "base\n");
+        commit_all(&repo, "initial");
+        create_branch(&repo, "feature", None, false).unwrap();
+        let mut master = repo.find_branch("master", git2::BranchType::Local).unwrap();
+        master.set_upstream(Some("feature")).unwrap();
+
+        write(&root, "a.txt", "ours\n");
+        commit_all(&repo, "ours");
+        let result = status(&repo).unwrap();
+        assert_eq!(
+            result.upstream,
+            Some(UpstreamStatus {
+                name: "feature".to_string(),
+                ahead: 1,
+                behind: 0
+            })
+        );
+
+        crate::git::branch::checkout_branch(&repo, "feature", false).unwrap();
+        write(&root, "a.txt", "theirs\n");
+        commit_all(&repo, "theirs");
+        crate::git::branch::checkout_branch(&repo, "master", false).unwrap();
+        merge(&repo, "feature", &MergeOptions::default()).unwrap();
+
+        let result = status(&repo).unwrap();
+        assert_eq!(result.conflicted, vec!["a.txt"]);
+        assert!(file(&result, "a.txt").index.is_none());
+        assert_eq!(result.upstream.map(|u| (u.ahead, u.behind)), Some((1, 1)));
+    }
+
+    #[test]
+    fn test_status_submodules() {
+        let (_sub_dir, sub_root, sub_repo) = init_repo();
+        write(&sub_root, "lib.txt", "v1\n");
+        let pinned = commit_all(&sub_repo, "sub initial");
+
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "a\n");
+        commit_all(&repo, "initial");
+        let url = format!("file://{}", sub_root.display());
+        let mut submodule = repo
+            .submodule(&url, std::path::Path::new("deps/sub"), true)
+            .unwrap();
+        submodule.clone(None).unwrap();
+        submodule.add_finalize().unwrap();
+        commit_all(&repo, "add submodule");
+
+        let result = status(&repo).unwrap();
+        assert_eq!(result.submodules.len(), 1);
+        let sub = &result.submodules[0];
+        assert_eq!(sub.path, "deps/sub");
+        assert_eq!(sub.head_commit, Some(pinned.to_string()));
+        assert!(sub.initialized && !sub.commit_changed && !sub.dirty);
+
+        let checkout = Repository::open(root.join("deps/sub")).unwrap();
+        write(&root, "deps/sub/lib.txt", "v2\n");
+        commit_all(&checkout, "sub change");
+        let result = status(&repo).unwrap();
+        assert!(result.submodules[0].commit_changed);
+        assert!(file(&result, "deps/sub").is_submodule);
+    }
+}
@@ -0,0 +1,176 @@
+// Git Tags — R20-02
+// List, create (lightweight or annotated) and delete tags
+
+use git2::{ObjectType, Reference, Repository};
+use serde::{Deserialize, Serialize};
+
+use super::resolve_commit;
+use crate::error::{AppError, AppResult};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TagInfo {
+    pub name: String,
+    /// Commit the tag points at, if it points at a commit at all
+    pub commit: Option<String>,
+    pub annotated: bool,
+    /// Annotated tags only
+    pub message: Option<String>,
+    pub tagger_name: Option<String>,
+    pub tagger_email: Option<String>,
+    /// Unix milliseconds: when an annotated tag was made, or the tagged commit's time
+    pub time: Option<i64>,
+}
+
+/// Tags matching the glob `pattern` (default all), newest first
+pub fn list(repo: &Repository, pattern: Option<&str>) -> AppResult<Vec<TagInfo>> {
+    let names = repo
+        .tag_names(pattern)
+        .map_err(|e| AppError::git("Failed to list tags", e))?;
+    let mut tags = Vec::new();
+    for name in names.iter().flatten() {
+        tags.push(find(repo, name)?);
+    }
+    tags.sort_by(|a, b| b.time.cmp(&a.time).then_with(|| a.name.cmp(&b.name)));
+    Ok(tags)
+}
+
+/// Tag `target` (default HEAD). A `message` makes an annotated tag; without one
+/// the tag is lightweight. Existing tags are only replaced with `force`.
+pub fn create(
+    repo: &Repository,
+    name: &str,
+    target: Option<&str>,
+    message: Option<&str>,
+    force: bool,
+) -> AppResult<TagInfo> {
+    validate_name(name)?;
+    if !force && repo.find_reference(&tag_ref(name)).is_ok() {
+        return Err(AppError::already_exists(format!(
+            "Tag '{}' already exists",
+            name
+        )));
+    }
+    let commit = resolve_commit(repo, target.unwrap_or("HEAD"))?;
+
+    let created = match message.map(str::trim).filter(|m| !m.is_empty()) {
+        Some(message) => {
+            let tagger = repo
+                .signature()
+                .map_err(|e| AppError::git("Failed to get signature", e))?;
+            repo.tag(name, commit.as_object(), &tagger, message, force)
+        }
+        None => repo.tag_lightweight(name, commit.as_object(), force),
+    };
+    created.map_err(|e| AppError::git(&format!("Failed to create tag '{}'", name), e))?;
+
+    find(repo, name)
+}
+
+pub fn delete(repo: &Repository, name: &str) -> AppResult<()> {
+    repo.tag_delete(name)
+        .map_err(|e| AppError::git(&format!("Failed to delete tag '{}'", name), e))
+}
+
+fn find(repo: &Repository, name: &str) -> AppResult<TagInfo> {
+    let reference = repo
+        .find_reference(&tag_ref(name))
+        .map_err(|e| AppError::git(&format!("Failed to find tag '{}'", name), e))?;
+    Ok(info(name, &reference))
+}
+
+fn info(name: &str, reference: &Reference) -> TagInfo {
+    let commit = reference.peel_to_commit().ok();
+    let mut info = TagInfo {
+        name: name.to_string(),
+        commit: commit.as_ref().map(|c| c.id().to_string()),
+        annotated: false,
+        message: None,
+        tagger_name: None,
+        tagger_email: None,
+        time: commit.as_ref().map(|c| c.time().seconds() * 1000),
+    };
+
+    let tag = reference
+        .peel(ObjectType::Tag)
+        .ok()
+        .and_then(|object| object.into_tag().ok());
+    if let Some(tag) = tag {
+        info.annotated = true;
+        info.message = tag.message().map(|m| m.trim_end().to_string());
+        if let Some(tagger) = tag.tagger() {
+            info.tagger_name = tagger.name().map(str::to_string);
+            info.tagger_email = tagger.email().map(str::to_string);
+            info.time = Some(tagger.when().seconds() * 1000);
+        }
+    }
+    info
+}
+
+fn tag_ref(name: &str) -> String {
+    format!("refs/tags/{}", name)
+}
+
+fn validate_name(name: &str) -> AppResult<()> {
+    if !name.is_empty() && Reference::is_valid_name(&tag_ref(name)) {
+        Ok(())
+    } else {
+        Err(AppError::invalid_input(format!(
+            "Invalid tag name: '{}'",
+            name
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::git::test_support::{commit_all, init_repo, write};
+
+    #[test]
+    fn test_create_list_and_delete() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "one\n");
+        let first = commit_all(&repo, "initial");
+        write(&root, "a.txt", "two\n");
+        let second = commit_all(&repo, "second");
+
+        let light = create(&repo, "v0.1.0", Some(&first.to_string()), None, false).unwrap();
+        assert!(!light.annotated);
+        assert_eq!(light.commit, Some(first.to_string()));
+        assert!(light.message.is_none());
+
+        let annotated = create(&repo, "v0.2.0", None, Some("Second release\n"), false).unwrap();
+        assert!(annotated.annotated);
+        assert_eq!(annotated.commit, Some(second.to_string()));
+        assert_eq!(annotated.message.as_deref(), Some("Second release"));
+        assert_eq!(annotated.tagger_name.as_deref(), Some("Nova Test"));
+
+        let names: Vec<_> = list(&repo, None)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"v0.1.0".to_string()));
+        assert_eq!(list(&repo, Some("v0.1*")).unwrap().len(), 1);
+
+        let err = create(&repo, "v0.1.0", None, None, false).unwrap_err();
+        assert_eq!(err.code, ErrorCode::AlreadyExists);
+        let moved = create(&repo, "v0.1.0", None, None, true).unwrap();
+        assert_eq!(moved.commit, Some(second.to_string()));
+        assert_eq!(
+            create(&repo, "bad..name", None, None, false)
+                .unwrap_err()
+                .code,
+            ErrorCode::InvalidInput
+        );
+
+        delete(&repo, "v0.1.0").unwrap();
+        assert_eq!(
+            delete(&repo, "v0.1.0").unwrap_err().code,
+            ErrorCode::NotFound
+        );
+        assert_eq!(list(&repo, None).unwrap().len(), 1);
+    }
+}
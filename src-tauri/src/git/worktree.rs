@@ -0,0 +1,316 @@
+// Git Worktrees — R20-02
+// Isolated per-agent checkouts under .nova/worktrees, merged back when the run finishes
+
+use git2::{
+    BranchType, Repository, StatusOptions, WorktreeAddOptions, WorktreeLockStatus,
+    WorktreePruneOptions,
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::branch::{checkout_branch, head_state, HeadState};
+use super::merge::{self, MergeOptions, MergeOutcome};
+use super::{exclude_nova_dir, resolve_commit};
+use crate::error::{AppError, AppResult};
+use crate::workspace::WORKTREES_DIR;
+
+/// Prefix for branches created for agent worktrees
+pub const WORKTREE_BRANCH_PREFIX: &str = "nova/";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WorktreeInfo {
+    pub name: String,
+    pub path: String,
+    pub head: Option<HeadState>,
+    pub locked: bool,
+    /// The checkout directory is gone; `prune` will clean up the bookkeeping
+    pub stale: bool,
+}
+
+/// Create a worktree named `name` under `.nova/worktrees` in `root`, on a new
+/// branch (default `nova/<name>`) starting from `start_point` (default HEAD)
+pub fn create(
+    repo: &Repository,
+    root: &Path,
+    name: &str,
+    branch: Option<&str>,
+    start_point: Option<&str>,
+) -> AppResult<WorktreeInfo> {
+    validate_name(name)?;
+    if repo.find_worktree(name).is_ok() {
+        return Err(AppError::already_exists(format!(
+            "Worktree '{}' already exists",
+            name
+        )));
+    }
+    let path = worktree_path(root, name);
+    if path.exists() {
+        return Err(AppError::already_exists(format!(
+            "Worktree directory already exists: {}",
+            path.display()
+        ))
+        .with_path(&path));
+    }
+
+    let branch_name = match branch {
+        Some(branch) => branch.to_string(),
+        None => format!("{}{}", WORKTREE_BRANCH_PREFIX, name),
+    };
+    let start = resolve_commit(repo, start_point.unwrap_or("HEAD"))?;
+    let branch = repo
+        .branch(&branch_name, &start, false)
+        .map_err(|e| AppError::git(&format!("Failed to create branch '{}'", branch_name), e))?;
+
+    fs::create_dir_all(root.join(WORKTREES_DIR))
+        .map_err(|e| AppError::io("Failed to create worktrees directory", e))?;
+    exclude_nova_dir(repo)?;
+
+    let mut options = WorktreeAddOptions::new();
+    options.reference(Some(branch.get()));
+    let worktree = repo.worktree(name, &path, Some(&options)).map_err(|e| {
+        AppError::git(&format!("Failed to create worktree '{}'", name), e).with_path(&path)
+    });
+    let worktree = match worktree {
+        Ok(worktree) => worktree,
+        Err(e) => {
+            // Don't leave the branch behind for a worktree that was never made
+            let mut branch = branch;
+            let _ = branch.delete();
+            return Err(e);
+        }
+    };
+
+    info(&worktree)
+}
+
+pub fn list(repo: &Repository) -> AppResult<Vec<WorktreeInfo>> {
+    let names = repo
+        .worktrees()
+        .map_err(|e| AppError::git("Failed to list worktrees", e))?;
+    let mut worktrees = Vec::new();
+    for name in names.iter().flatten() {
+        let worktree = repo
+            .find_worktree(name)
+            .map_err(|e| AppError::git("Failed to open worktree", e))?;
+        worktrees.push(info(&worktree)?);
+    }
+    Ok(worktrees)
+}
+
+fn info(worktree: &git2::Worktree) -> AppResult<WorktreeInfo> {
+    let stale = worktree.validate().is_err();
+    let head = if stale {
+        None
+    } else {
+        Repository::open_from_worktree(worktree)
+            .ok()
+            .and_then(|repo| head_state(&repo).ok())
+    };
+    Ok(WorktreeInfo {
+        name: worktree.name().unwrap_or_default().to_string(),
+        path: worktree.path().to_string_lossy().into_owned(),
+        head,
+        locked: matches!(worktree.is_locked(), Ok(WorktreeLockStatus::Locked(_))),
+        stale,
+    })
+}
+
+/// Delete a worktree's checkout and bookkeeping. Its branch is kept so finished
+/// work can still be merged. Uncommitted changes, including untracked files,
+/// are refused unless `force`.
+pub fn remove(repo: &Repository, name: &str, force: bool) -> AppResult<()> {
+    let worktree = repo
+        .find_worktree(name)
+        .map_err(|e| AppError::git(&format!("Failed to find worktree '{}'", name), e))?;
+
+    if !force && worktree.validate().is_ok() {
+        let checkout = Repository::open_from_worktree(&worktree)
+            .map_err(|e| AppError::git("Failed to open worktree", e))?;
+        if has_unsaved_work(&checkout)? {
+            return Err(AppError::conflict(format!(
+                "Worktree '{}' has uncommitted or untracked files; commit them or force removal",
+                name
+            )));
+        }
+    }
+
+    let mut options = WorktreePruneOptions::new();
+    options.valid(true).working_tree(true).locked(force);
+    worktree
+        .prune(Some(&mut options))
+        .map_err(|e| AppError::git(&format!("Failed to remove worktree '{}'", name), e))
+}
+
+/// Forget worktrees whose checkout directory no longer exists
+pub fn prune(repo: &Repository) -> AppResult<Vec<String>> {
+    let mut pruned = Vec::new();
+    for info in list(repo)?.into_iter().filter(|w| w.stale && !w.locked) {
+        let worktree = repo
+            .find_worktree(&info.name)
+            .map_err(|e| AppError::git("Failed to open worktree", e))?;
+        worktree
+            .prune(None)
+            .map_err(|e| AppError::git(&format!("Failed to prune '{}'", info.name), e))?;
+        pruned.push(info.name);
+    }
+    Ok(pruned)
+}
+
+/// Merge a worktree's branch into `into` (default: the branch checked out in
+/// the main working tree), reporting conflicts there
+pub fn merge_back(
+    repo: &Repository,
+    name: &str,
+    into: Option<&str>,
+    options: &MergeOptions,
+) -> AppResult<MergeOutcome> {
+    let worktree = repo
+        .find_worktree(name)
+        .map_err(|e| AppError::git(&format!("Failed to find worktree '{}'", name), e))?;
+    let checkout = Repository::open_from_worktree(&worktree)
+        .map_err(|e| AppError::git("Failed to open worktree", e))?;
+    if has_unsaved_work(&checkout)? {
+        return Err(AppError::conflict(format!(
+            "Worktree '{}' has uncommitted changes or untracked files; commit them before merging",
+            name
+        )));
+    }
+    let branch = match head_state(&checkout)? {
+        HeadState::Branch { name, .. } => name,
+        _ => {
+            return Err(AppError::invalid_input(format!(
+                "Worktree '{}' is not on a branch",
+                name
+            )))
+        }
+    };
+
+    if let Some(target) = into {
+        if head_state(repo)?.branch_name() != Some(target) {
+            repo.find_branch(target, BranchType::Local)
+                .map_err(|e| AppError::git(&format!("Failed to find branch '{}'", target), e))?;
+            checkout_branch(repo, target, false)?;
+        }
+    }
+    merge::merge(repo, &branch, options)
+}
+
+/// Like `has_uncommitted_changes`, but untracked files count too: an agent's
+/// new files exist nowhere else until they are committed
+fn has_unsaved_work(checkout: &Repository) -> AppResult<bool> {
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .include_ignored(false)
+        .exclude_submodules(true);
+    let statuses = checkout
+        .statuses(Some(&mut options))
+        .map_err(|e| AppError::git("Failed to get statuses", e))?;
+    Ok(!statuses.is_empty())
+}
+
+fn worktree_path(root: &Path, name: &str) -> PathBuf {
+    root.join(WORKTREES_DIR).join(name)
+}
+
+fn validate_name(name: &str) -> AppResult<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::invalid_input(format!(
+            "Invalid worktree name '{}': use letters, digits, '-', '_' and '.'",
+            name
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::git::test_support::{commit_all, init_repo, write};
+
+    #[test]
+    fn test_create_list_and_remove() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "a\n");
+        commit_all(&repo, "initial");
+
+        let info = create(&repo, &root, "agent-1", None, None).unwrap();
+        assert_eq!(
+            info.head,
+            Some(HeadState::Branch {
+                name: "nova/agent-1".to_string(),
+                commit: repo.head().unwrap().target().unwrap().to_string(),
+            })
+        );
+        assert!(root.join(".nova/worktrees/agent-1/a.txt").exists());
+        assert!(create(&repo, &root, "agent-1", None, None).is_err());
+        assert!(create(&repo, &root, "../escape", None, None).is_err());
+        // The main checkout sees the worktree as ignored, not untracked content
+        let statuses = repo.statuses(None).unwrap();
+        assert!(statuses.iter().all(|e| e.status() == git2::Status::IGNORED));
+
+        write(&root, ".nova/worktrees/agent-1/new.txt", "untracked\n");
+        assert_eq!(
+            remove(&repo, "agent-1", false).unwrap_err().code,
+            ErrorCode::Conflict
+        );
+        write(&root, ".nova/worktrees/agent-1/a.txt", "dirty\n");
+        assert!(remove(&repo, "agent-1", false).is_err());
+        remove(&repo, "agent-1", true).unwrap();
+        assert!(!root.join(".nova/worktrees/agent-1").exists());
+        assert!(list(&repo).unwrap().is_empty());
+        assert!(repo.find_branch("nova/agent-1", BranchType::Local).is_ok());
+    }
+
+    #[test]
+    fn test_prune_stale() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "a\n");
+        commit_all(&repo, "initial");
+        create(&repo, &root, "gone", None, None).unwrap();
+        create(&repo, &root, "kept", None, None).unwrap();
+        fs::remove_dir_all(root.join(".nova/worktrees/gone")).unwrap();
+
+        let stale: Vec<_> = list(&repo)
+            .unwrap()
+            .into_iter()
+            .filter(|w| w.stale)
+            .map(|w| w.name)
+            .collect();
+        assert_eq!(stale, vec!["gone"]);
+        assert_eq!(prune(&repo).unwrap(), vec!["gone"]);
+        assert_eq!(list(&repo).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_merge_back() {
+        let (_dir, root, repo) = init_repo();
+        write(&root, "a.txt", "a\n");
+        commit_all(&repo, "initial");
+        create(&repo, &root, "agent-1", None, None).unwrap();
+
+        let checkout = Repository::open(root.join(".nova/worktrees/agent-1")).unwrap();
+        write(&root, ".nova/worktrees/agent-1/b.txt", "b\n");
+        let err = merge_back(&repo, "agent-1", None, &MergeOptions::default()).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Conflict);
+        let tip = commit_all(&checkout, "agent work");
+
+        let outcome =
+            merge_back(&repo, "agent-1", Some("master"), &MergeOptions::default()).unwrap();
+        assert_eq!(
+            outcome,
+            MergeOutcome::FastForward {
+                commit: tip.to_string()
+            }
+        );
+        assert!(root.join("b.txt").exists());
+    }
+}
@@ -6,6 +6,17 @@
 mod commands;
 mod ollama_manager;
 mod electric_sync;
+mod error;
+mod file_history;
+mod file_ops;
+mod file_reader;
+mod file_tree;
+mod file_watcher;
+mod git;
+mod patch;
+mod project_search;
+mod trash;
+mod workspace;
 
 use tauri::{Manager, SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem};
 use std::sync::Arc;
@@ -14,6 +25,11 @@ use tokio::sync::Mutex;
 pub struct AppState {
     pub ollama_manager: Arc<Mutex<ollama_manager::OllamaManager>>,
     pub sync_engine: Arc<Mutex<electric_sync::ElectricSync>>,
+    pub workspace: Arc<Mutex<workspace::Workspace>>,
+    pub file_watcher: Arc<Mutex<file_watcher::FileWatcher>>,
+    pub search_manager: Arc<Mutex<project_search::SearchManager>>,
+    pub file_history: Arc<Mutex<file_history::FileHistory>>,
+    pub repos: Arc<Mutex<git::RepoCache>>,
 }
 
 fn main() {
@@ -32,6 +48,11 @@ fn main() {
             let state = AppState {
                 ollama_manager: Arc::new(Mutex::new(ollama_manager::OllamaManager::new())),
                 sync_engine: Arc::new(Mutex::new(electric_sync::ElectricSync::new())),
+                workspace: Arc::new(Mutex::new(workspace::Workspace::new())),
+                file_watcher: Arc::new(Mutex::new(file_watcher::FileWatcher::new())),
+                search_manager: Arc::new(Mutex::new(project_search::SearchManager::new())),
+                file_history: Arc::new(Mutex::new(file_history::FileHistory::new())),
+                repos: Arc::new(Mutex::new(git::RepoCache::new())),
             };
             app.manage(state);
 
@@ -82,14 +103,73 @@ fn main() {
             }
         })
         .invoke_handler(tauri::generate_handler![
+            commands::set_workspace_root,
+            commands::get_workspace_root,
             commands::read_project_file,
+            commands::read_file,
             commands::write_file,
+            commands::file_version,
+            commands::file_history_list,
+            commands::file_history_diff,
+            commands::file_history_restore,
+            commands::apply_patch,
+            commands::rename_path,
+            commands::copy_path,
+            commands::delete_path,
+            commands::list_trash,
+            commands::restore_from_trash,
+            commands::empty_trash,
+            commands::list_directory,
+            commands::search_project,
+            commands::cancel_search,
             commands::git_commit,
             commands::git_status,
+            commands::git_diff,
+            commands::git_stage_lines,
+            commands::git_unstage_lines,
+            commands::git_discard_lines,
+            commands::git_log,
+            commands::git_blame,
+            commands::git_stash_save,
+            commands::git_stash_list,
+            commands::git_stash_show,
+            commands::git_stash_apply,
+            commands::git_stash_pop,
+            commands::git_stash_drop,
+            commands::git_merge,
+            commands::git_merge_conflicts,
+            commands::git_merge_resolve,
+            commands::git_merge_conclude,
+            commands::git_merge_abort,
+            commands::git_worktree_create,
+            commands::git_worktree_list,
+            commands::git_worktree_remove,
+            commands::git_worktree_prune,
+            commands::git_worktree_merge,
+            commands::git_checkpoint_create,
+            commands::git_checkpoint_list,
+            commands::git_checkpoint_diff,
+            commands::git_checkpoint_restore,
+            commands::git_checkpoint_delete,
+            commands::git_init,
+            commands::git_clone,
+            commands::git_fetch,
+            commands::git_pull,
+            commands::git_push,
+            commands::git_tags,
+            commands::git_create_tag,
+            commands::git_delete_tag,
+            commands::git_changelog,
+            commands::git_branches,
+            commands::git_create_branch,
+            commands::git_rename_branch,
+            commands::git_delete_branch,
+            commands::git_checkout_branch,
             commands::spawn_ollama,
             commands::stop_ollama,
             commands::ollama_status,
             commands::watch_project,
+            commands::unwatch_project,
             commands::send_notification,
         ])
         .run(tauri::generate_context!())
@@ -105,6 +185,11 @@ mod tests {
         let state = AppState {
             ollama_manager: Arc::new(Mutex::new(ollama_manager::OllamaManager::new())),
             sync_engine: Arc::new(Mutex::new(electric_sync::ElectricSync::new())),
+            workspace: Arc::new(Mutex::new(workspace::Workspace::new())),
+            file_watcher: Arc::new(Mutex::new(file_watcher::FileWatcher::new())),
+            search_manager: Arc::new(Mutex::new(project_search::SearchManager::new())),
+            file_history: Arc::new(Mutex::new(file_history::FileHistory::new())),
+            repos: Arc::new(Mutex::new(git::RepoCache::new())),
         };
         // State created successfully
         assert!(Arc::strong_count(&state.ollama_manager) > 0);
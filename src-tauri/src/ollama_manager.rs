@@ -7,6 +7,8 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::time::{sleep, Duration};
 
+use crate::error::{AppError, AppResult, ErrorCode};
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OllamaStatus {
     pub running: bool,
@@ -29,18 +31,18 @@ impl OllamaManager {
     }
 
     /// Start Ollama service
-    pub async fn start(&self) -> Result<(), String> {
+    pub async fn start(&self) -> AppResult<()> {
         let mut process = self.process.lock().await;
         
         if process.is_some() {
-            return Err("Ollama is already running".to_string());
+            return Err(AppError::conflict("Ollama is already running"));
         }
 
         // Try to start Ollama
         let child = Command::new("ollama")
             .arg("serve")
             .spawn()
-            .map_err(|e| format!("Failed to start Ollama: {}", e))?;
+            .map_err(|e| AppError::io("Failed to start Ollama", e))?;
 
         *process = Some(child);
 
@@ -52,17 +54,17 @@ impl OllamaManager {
             Ok(true) => Ok(()),
             Ok(false) => {
                 *process = None;
-                Err("Ollama failed to start".to_string())
+                Err(AppError::new(ErrorCode::ServiceUnavailable, "Ollama failed to start"))
             }
             Err(e) => {
                 *process = None;
-                Err(format!("Failed to verify Ollama: {}", e))
+                Err(e)
             }
         }
     }
 
     /// Stop Ollama service
-    pub async fn stop(&self) -> Result<(), String> {
+    pub async fn stop(&self) -> AppResult<()> {
         let mut process = self.process.lock().await;
         
         if let Some(mut child) = process.take() {
@@ -89,7 +91,7 @@ impl OllamaManager {
     }
 
     /// Check Ollama status
-    pub async fn check_status(&self) -> Result<OllamaStatus, String> {
+    pub async fn check_status(&self) -> AppResult<OllamaStatus> {
         let port = self.detect_port().await.unwrap_or(self.default_port);
         
         match self.check_port(port).await {
@@ -110,7 +112,7 @@ impl OllamaManager {
                 version: None,
                 models: vec![],
             }),
-            Err(e) => Err(format!("Failed to check status: {}", e)),
+            Err(e) => Err(e),
         }
     }
 
@@ -128,7 +130,7 @@ impl OllamaManager {
     }
 
     /// Check if a specific port is responding
-    async fn check_port(&self, port: u16) -> Result<bool, String> {
+    async fn check_port(&self, port: u16) -> AppResult<bool> {
         let client = reqwest::Client::new();
         let url = format!("http://localhost:{}/api/tags", port);
         
@@ -139,7 +141,7 @@ impl OllamaManager {
     }
 
     /// Fetch list of available models
-    async fn fetch_models(&self, port: u16) -> Result<Vec<String>, String> {
+    async fn fetch_models(&self, port: u16) -> AppResult<Vec<String>> {
         let client = reqwest::Client::new();
         let url = format!("http://localhost:{}/api/tags", port);
         
@@ -158,7 +160,7 @@ impl OllamaManager {
             .timeout(Duration::from_secs(5))
             .send()
             .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+            .map_err(|e| AppError::http("Request failed", e))?;
         
         if !response.status().is_success() {
             return Ok(vec![]);
@@ -167,13 +169,13 @@ impl OllamaManager {
         let data: ModelsResponse = response
             .json()
             .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?;
+            .map_err(|e| AppError::http("Failed to parse response", e))?;
         
         Ok(data.models.into_iter().map(|m| m.name).collect())
     }
 
     /// Wait for Ollama to be ready
-    pub async fn wait_for_ready(&self, timeout_secs: u64) -> Result<bool, String> {
+    pub async fn wait_for_ready(&self, timeout_secs: u64) -> AppResult<bool> {
         let start = std::time::Instant::now();
         let timeout = Duration::from_secs(timeout_secs);
         
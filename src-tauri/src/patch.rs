@@ -0,0 +1,794 @@
+// Patch Application — R20-02
+// Multi-file unified diffs and search/replace edits, validated then applied all-or-nothing
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{AppError, AppResult};
+use crate::file_ops::{self, WriteExpectation};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchReplaceEdit {
+    pub path: String,
+    pub search: String,
+    pub replace: String,
+    #[serde(default)]
+    pub replace_all: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileOperation {
+    Create,
+    Modify,
+    Delete,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HunkResult {
+    pub index: usize,
+    pub applied: bool,
+    /// How many lines away from its stated position the hunk matched
+    pub offset: i64,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FilePatchResult {
+    pub path: String,
+    pub operation: FileOperation,
+    pub hunks: Vec<HunkResult>,
+    pub error: Option<AppError>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PatchResult {
+    /// True only if every hunk validated and every file was written
+    pub applied: bool,
+    pub files: Vec<FilePatchResult>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+#[derive(Debug, Clone)]
+struct Hunk {
+    old_start: usize,
+    lines: Vec<HunkLine>,
+    /// `\ No newline at end of file` seen for the old / new side
+    old_no_eol: bool,
+    new_no_eol: bool,
+}
+
+#[derive(Debug, Clone)]
+struct FilePatch {
+    path: String,
+    operation: FileOperation,
+    hunks: Vec<Hunk>,
+}
+
+/// A fully computed change for one file, ready to be written
+struct PlannedWrite {
+    target: PathBuf,
+    content: Option<String>,
+}
+
+/// Apply a multi-file unified diff under `root`
+pub fn apply_unified_diff(root: &Path, diff: &str, dry_run: bool) -> AppResult<PatchResult> {
+    let patches = parse_unified_diff(diff)?;
+    if patches.is_empty() {
+        return Err(AppError::invalid_input("Patch contains no file changes"));
+    }
+
+    let mut results = Vec::new();
+    let mut planned = Vec::new();
+    for patch in &patches {
+        let (result, write) = plan_file_patch(root, patch);
+        results.push(result);
+        planned.push(write);
+    }
+
+    commit_plan(results, planned, dry_run)
+}
+
+/// Apply a list of search/replace edits under `root`. Edits to the same file
+/// are applied in order against the result of the previous edit.
+pub fn apply_search_replace(
+    root: &Path,
+    edits: &[SearchReplaceEdit],
+    dry_run: bool,
+) -> AppResult<PatchResult> {
+    if edits.is_empty() {
+        return Err(AppError::invalid_input("No edits supplied"));
+    }
+
+    // Group by path, preserving first-seen order
+    let mut order: Vec<&str> = Vec::new();
+    for edit in edits {
+        if !order.contains(&edit.path.as_str()) {
+            order.push(&edit.path);
+        }
+    }
+
+    let mut results = Vec::new();
+    let mut planned = Vec::new();
+    for path in order {
+        let file_edits: Vec<&SearchReplaceEdit> = edits.iter().filter(|e| e.path == path).collect();
+        let (result, write) = plan_search_replace(root, path, &file_edits);
+        results.push(result);
+        planned.push(write);
+    }
+
+    commit_plan(results, planned, dry_run)
+}
+
+fn commit_plan(
+    mut results: Vec<FilePatchResult>,
+    planned: Vec<Option<PlannedWrite>>,
+    dry_run: bool,
+) -> AppResult<PatchResult> {
+    let valid = results.iter().all(|r| r.error.is_none());
+    if !valid || dry_run {
+        return Ok(PatchResult {
+            applied: false,
+            files: results,
+        });
+    }
+
+    // Remember which result each write belongs to so a failure is reported on the right file
+    let (owners, writes): (Vec<usize>, Vec<PlannedWrite>) = planned
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, w)| w.map(|w| (i, w)))
+        .unzip();
+    if let Err((index, e)) = write_all(&writes) {
+        results[owners[index]].error = Some(e);
+        return Ok(PatchResult {
+            applied: false,
+            files: results,
+        });
+    }
+
+    Ok(PatchResult {
+        applied: true,
+        files: results,
+    })
+}
+
+/// Write every planned change, restoring all earlier files (and removing any
+/// directories made for new ones) if one fails
+fn write_all(writes: &[PlannedWrite]) -> Result<(), (usize, AppError)> {
+    let mut originals: Vec<(&Path, Option<Vec<u8>>)> = Vec::new();
+    let mut created_dirs: Vec<PathBuf> = Vec::new();
+
+    for (index, write) in writes.iter().enumerate() {
+        let original = fs::read(&write.target).ok();
+        if write.content.is_some() {
+            created_dirs.extend(missing_dirs(&write.target));
+        }
+        let result = match &write.content {
+            Some(content) => file_ops::atomic_write(
+                &write.target,
+                content.as_bytes(),
+                &WriteExpectation::default(),
+            )
+            .map(|_| ()),
+            None => fs::remove_file(&write.target)
+                .map_err(|e| AppError::io("Failed to delete file", e).with_path(&write.target)),
+        };
+
+        if let Err(mut e) = result {
+            for (path, original) in originals.into_iter().rev() {
+                match original {
+                    Some(bytes) => {
+                        let _ = file_ops::atomic_write(path, &bytes, &WriteExpectation::default());
+                    }
+                    None => {
+                        let _ = fs::remove_file(path);
+                    }
+                }
+            }
+            for dir in created_dirs.iter().rev() {
+                let _ = fs::remove_dir(dir);
+            }
+            e.message.push_str(" (all changes rolled back)");
+            return Err((index, e));
+        }
+        originals.push((&write.target, original));
+    }
+
+    Ok(())
+}
+
+/// Directories above `path` that do not exist yet, outermost first
+fn missing_dirs(path: &Path) -> Vec<PathBuf> {
+    let mut missing: Vec<PathBuf> = path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| !dir.exists())
+        .map(Path::to_path_buf)
+        .collect();
+    missing.reverse();
+    missing
+}
+
+fn plan_file_patch(root: &Path, patch: &FilePatch) -> (FilePatchResult, Option<PlannedWrite>) {
+    let mut result = FilePatchResult {
+        path: patch.path.clone(),
+        operation: patch.operation,
+        hunks: Vec::new(),
+        error: None,
+    };
+
+    let target = match crate::workspace::resolve_within(root, Path::new(&patch.path)) {
+        Ok(t) => t,
+        Err(e) => {
+            result.error = Some(e.into());
+            return (result, None);
+        }
+    };
+
+    let original = match (patch.operation, fs::read_to_string(&target)) {
+        (FileOperation::Create, Ok(_)) => {
+            result.error = Some(
+                AppError::already_exists(format!("File already exists: {}", patch.path))
+                    .with_path(&patch.path),
+            );
+            return (result, None);
+        }
+        (FileOperation::Create, Err(_)) => String::new(),
+        (_, Ok(content)) => content,
+        (_, Err(e)) => {
+            result.error = Some(AppError::io("Failed to read file", e).with_path(&patch.path));
+            return (result, None);
+        }
+    };
+
+    let mut text = TextLines::parse(&original);
+    let mut search_from = 0;
+    let mut shift: i64 = 0;
+    let mut failed = false;
+
+    for (index, hunk) in patch.hunks.iter().enumerate() {
+        let old: Vec<&str> = hunk
+            .lines
+            .iter()
+            .filter_map(|l| match l {
+                HunkLine::Context(s) | HunkLine::Remove(s) => Some(s.as_str()),
+                HunkLine::Add(_) => None,
+            })
+            .collect();
+        let new: Vec<String> = hunk
+            .lines
+            .iter()
+            .filter_map(|l| match l {
+                HunkLine::Context(s) | HunkLine::Add(s) => Some(s.clone()),
+                HunkLine::Remove(_) => None,
+            })
+            .collect();
+
+        // Pure additions have old_start pointing at the line *before* the insert
+        let stated = if old.is_empty() {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let expected = (stated as i64 + shift).max(0) as usize;
+
+        match find_hunk(&text.lines, &old, expected, search_from) {
+            Some(position) => {
+                let touches_end = position + old.len() == text.lines.len();
+                text.lines
+                    .splice(position..position + old.len(), new.iter().cloned());
+                if touches_end {
+                    if hunk.new_no_eol {
+                        text.trailing_newline = false;
+                    } else if hunk.old_no_eol {
+                        text.trailing_newline = true;
+                    }
+                }
+                result.hunks.push(HunkResult {
+                    index,
+                    applied: true,
+                    offset: position as i64 - expected as i64,
+                    message: None,
+                });
+                shift += new.len() as i64 - old.len() as i64;
+                search_from = position + new.len();
+            }
+            None => {
+                failed = true;
+                result.hunks.push(HunkResult {
+                    index,
+                    applied: false,
+                    offset: 0,
+                    message: Some(format!(
+                        "Hunk does not match current content near line {}",
+                        hunk.old_start
+                    )),
+                });
+            }
+        }
+    }
+
+    if failed {
+        result.error = Some(AppError::conflict("One or more hunks failed to apply"));
+        return (result, None);
+    }
+
+    let content = match patch.operation {
+        FileOperation::Delete => {
+            if !text.lines.is_empty() {
+                result.error = Some(AppError::conflict(
+                    "Deletion patch does not remove all content",
+                ));
+                return (result, None);
+            }
+            None
+        }
+        _ => Some(text.render()),
+    };
+
+    (result, Some(PlannedWrite { target, content }))
+}
+
+fn plan_search_replace(
+    root: &Path,
+    path: &str,
+    edits: &[&SearchReplaceEdit],
+) -> (FilePatchResult, Option<PlannedWrite>) {
+    let mut result = FilePatchResult {
+        path: path.to_string(),
+        operation: FileOperation::Modify,
+        hunks: Vec::new(),
+        error: None,
+    };
+
+    let target = match crate::workspace::resolve_within(root, Path::new(path)) {
+        Ok(t) => t,
+        Err(e) => {
+            result.error = Some(e.into());
+            return (result, None);
+        }
+    };
+
+    let mut content = match fs::read_to_string(&target) {
+        Ok(c) => c,
+        // An empty search against a missing file creates it
+        Err(_) if edits.first().map(|e| e.search.is_empty()).unwrap_or(false) => {
+            result.operation = FileOperation::Create;
+            String::new()
+        }
+        Err(e) => {
+            result.error = Some(AppError::io("Failed to read file", e).with_path(path));
+            return (result, None);
+        }
+    };
+
+    for (index, edit) in edits.iter().enumerate() {
+        let message = if edit.search.is_empty() {
+            if content.is_empty() {
+                content = edit.replace.clone();
+                None
+            } else {
+                Some("Empty search text is only allowed when creating a file".to_string())
+            }
+        } else {
+            match content.matches(edit.search.as_str()).count() {
+                0 => Some("Search text not found".to_string()),
+                n if n > 1 && !edit.replace_all => Some(format!(
+                    "Search text is ambiguous ({} matches); add context or set replace_all",
+                    n
+                )),
+                _ => {
+                    content = content.replace(edit.search.as_str(), &edit.replace);
+                    None
+                }
+            }
+        };
+
+        result.hunks.push(HunkResult {
+            index,
+            applied: message.is_none(),
+            offset: 0,
+            message,
+        });
+    }
+
+    if result.hunks.iter().any(|h| !h.applied) {
+        result.error = Some(AppError::conflict("One or more edits failed to apply"));
+        return (result, None);
+    }
+
+    (
+        result,
+        Some(PlannedWrite {
+            target,
+            content: Some(content),
+        }),
+    )
+}
+
+/// Locate `old` in `lines`, preferring `expected` and then the nearest offset,
+/// never before `min` so hunks cannot overlap. Line endings are ignored.
+fn find_hunk(lines: &[String], old: &[&str], expected: usize, min: usize) -> Option<usize> {
+    if old.len() > lines.len() {
+        return None;
+    }
+    let last = lines.len() - old.len();
+    let matches_at = |pos: usize| {
+        lines[pos..pos + old.len()]
+            .iter()
+            .zip(old)
+            .all(|(a, b)| a.trim_end_matches('\r') == b.trim_end_matches('\r'))
+    };
+
+    let expected = expected.clamp(min.min(last), last);
+    for distance in 0..=lines.len() {
+        let below = expected + distance;
+        if below <= last && below >= min && matches_at(below) {
+            return Some(below);
+        }
+        if let Some(above) = expected.checked_sub(distance) {
+            if distance > 0 && above >= min && matches_at(above) {
+                return Some(above);
+            }
+        }
+        if below > last && expected.checked_sub(distance).map_or(true, |a| a < min) {
+            break;
+        }
+    }
+    None
+}
+
+/// File content as lines, remembering the line ending and final newline
+struct TextLines {
+    lines: Vec<String>,
+    eol: &'static str,
+    trailing_newline: bool,
+}
+
+impl TextLines {
+    fn parse(content: &str) -> Self {
+        let eol = if content.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let trailing_newline = content.ends_with('\n');
+        let body = content.strip_suffix('\n').unwrap_or(content);
+        let lines = if content.is_empty() {
+            Vec::new()
+        } else {
+            body.split('\n')
+                .map(|l| l.trim_end_matches('\r').to_string())
+                .collect()
+        };
+        Self {
+            lines,
+            eol,
+            trailing_newline: trailing_newline || content.is_empty(),
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = self.lines.join(self.eol);
+        if self.trailing_newline && !self.lines.is_empty() {
+            out.push_str(self.eol);
+        }
+        out
+    }
+}
+
+fn parse_unified_diff(diff: &str) -> AppResult<Vec<FilePatch>> {
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let lines: Vec<&str> = diff.lines().collect();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        if !(line.starts_with("--- ") && i + 1 < lines.len() && lines[i + 1].starts_with("+++ ")) {
+            i += 1;
+            continue;
+        }
+
+        let old_path = parse_header_path(&line[4..]);
+        let new_path = parse_header_path(&lines[i + 1][4..]);
+        let (path, operation) = match (old_path, new_path) {
+            (None, Some(new)) => (new, FileOperation::Create),
+            (Some(old), None) => (old, FileOperation::Delete),
+            (Some(_), Some(new)) => (new, FileOperation::Modify),
+            (None, None) => return Err(AppError::invalid_input("Patch header has no file path")),
+        };
+        i += 2;
+
+        let mut hunks = Vec::new();
+        while i < lines.len() && lines[i].starts_with("@@") {
+            let (old_start, old_count, new_count) = parse_hunk_header(lines[i])?;
+            i += 1;
+
+            let mut hunk = Hunk {
+                old_start,
+                lines: Vec::new(),
+                old_no_eol: false,
+                new_no_eol: false,
+            };
+            let (mut old_seen, mut new_seen) = (0, 0);
+            while i < lines.len() && (old_seen < old_count || new_seen < new_count) {
+                let l = lines[i];
+                match l.chars().next() {
+                    Some(' ') | None => {
+                        hunk.lines
+                            .push(HunkLine::Context(l.get(1..).unwrap_or("").to_string()));
+                        old_seen += 1;
+                        new_seen += 1;
+                    }
+                    Some('-') => {
+                        hunk.lines.push(HunkLine::Remove(l[1..].to_string()));
+                        old_seen += 1;
+                    }
+                    Some('+') => {
+                        hunk.lines.push(HunkLine::Add(l[1..].to_string()));
+                        new_seen += 1;
+                    }
+                    Some('\\') => mark_no_eol(&mut hunk),
+                    _ => {
+                        return Err(AppError::invalid_input(format!(
+                            "Malformed hunk line: {}",
+                            l
+                        )))
+                    }
+                }
+                i += 1;
+            }
+            if old_seen != old_count || new_seen != new_count {
+                return Err(AppError::invalid_input(format!(
+                    "Truncated hunk for {}",
+                    path
+                )));
+            }
+            if i < lines.len() && lines[i].starts_with('\\') {
+                mark_no_eol(&mut hunk);
+                i += 1;
+            }
+            hunks.push(hunk);
+        }
+
+        // Each section is planned against the file on disk, so a second
+        // section for the same path would silently replace the first
+        if patches.iter().any(|p| p.path == path) {
+            return Err(AppError::invalid_input(format!(
+                "Patch changes {} more than once",
+                path
+            )));
+        }
+        patches.push(FilePatch {
+            path,
+            operation,
+            hunks,
+        });
+    }
+
+    Ok(patches)
+}
+
+fn mark_no_eol(hunk: &mut Hunk) {
+    match hunk.lines.last() {
+        Some(HunkLine::Remove(_)) => hunk.old_no_eol = true,
+        Some(HunkLine::Add(_)) => hunk.new_no_eol = true,
+        Some(HunkLine::Context(_)) => {
+            hunk.old_no_eol = true;
+            hunk.new_no_eol = true;
+        }
+        None => {}
+    }
+}
+
+/// `a/src/main.rs\t2024-01-01` -> `Some("src/main.rs")`, `/dev/null` -> `None`
+fn parse_header_path(raw: &str) -> Option<String> {
+    let path = raw.split('\t').next().unwrap_or(raw).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// `@@ -12,5 +12,7 @@ fn main()` -> `(12, 5, 7)`
+fn parse_hunk_header(line: &str) -> AppResult<(usize, usize, usize)> {
+    let malformed = || AppError::invalid_input(format!("Malformed hunk header: {}", line));
+    let inner = line
+        .strip_prefix("@@ ")
+        .and_then(|rest| rest.split(" @@").next())
+        .ok_or_else(malformed)?;
+    let mut parts = inner.split_whitespace();
+    let old = parts
+        .next()
+        .and_then(|p| p.strip_prefix('-'))
+        .ok_or_else(malformed)?;
+    let new = parts
+        .next()
+        .and_then(|p| p.strip_prefix('+'))
+        .ok_or_else(malformed)?;
+
+    let range = |spec: &str| -> AppResult<(usize, usize)> {
+        let mut it = spec.splitn(2, ',');
+        let start = it.next().unwrap_or("").parse().map_err(|_| malformed())?;
+        let count = match it.next() {
+            Some(c) => c.parse().map_err(|_| malformed())?,
+            None => 1,
+        };
+        Ok((start, count))
+    };
+    let (old_start, old_count) = range(old)?;
+    let (_, new_count) = range(new)?;
+    Ok((old_start, old_count, new_count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    fn project() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::write(root.join("a.txt"), "one\ntwo\nthree\nfour\n").unwrap();
+        fs::write(root.join("b.txt"), "alpha\nbeta\n").unwrap();
+        (dir, root)
+    }
+
+    #[test]
+    fn test_apply_multi_file_diff() {
+        let (_dir, root) = project();
+        let diff = "\
+--- a/a.txt
++++ b/a.txt
+@@ -2,2 +2,2 @@
+ two
+-three
++THREE
+--- a/b.txt
++++ /dev/null
+@@ -1,2 +0,0 @@
+-alpha
+-beta
+--- /dev/null
++++ b/new/c.txt
+@@ -0,0 +1,1 @@
++created
+\\ No newline at end of file
+";
+        let result = apply_unified_diff(&root, diff, false).unwrap();
+        assert!(result.applied, "{:?}", result);
+        assert_eq!(
+            fs::read_to_string(root.join("a.txt")).unwrap(),
+            "one\ntwo\nTHREE\nfour\n"
+        );
+        assert!(!root.join("b.txt").exists());
+        assert_eq!(
+            fs::read_to_string(root.join("new/c.txt")).unwrap(),
+            "created"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_file_sections_are_rejected() {
+        let (_dir, root) = project();
+        let diff = "\
+--- a/a.txt
++++ b/a.txt
+@@ -1,1 +1,1 @@
+-one
++ONE
+--- a/a.txt
++++ b/a.txt
+@@ -4,1 +4,1 @@
+-four
++FOUR
+";
+        let err = apply_unified_diff(&root, diff, false).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+        assert_eq!(
+            fs::read_to_string(root.join("a.txt")).unwrap(),
+            "one\ntwo\nthree\nfour\n"
+        );
+    }
+
+    #[test]
+    fn test_hunk_offset_and_failure_leaves_files_untouched() {
+        let (_dir, root) = project();
+        // Stated line is wrong by one but the context still matches
+        let shifted = "--- a/a.txt\n+++ b/a.txt\n@@ -1,1 +1,1 @@\n-two\n+TWO\n";
+        let result = apply_unified_diff(&root, shifted, true).unwrap();
+        assert_eq!(result.files[0].hunks[0].offset, 1);
+
+        let diff = "\
+--- a/b.txt
++++ b/b.txt
+@@ -1,1 +1,1 @@
+-alpha
++ALPHA
+--- a/a.txt
++++ b/a.txt
+@@ -1,1 +1,1 @@
+-missing
++nope
+";
+        let result = apply_unified_diff(&root, diff, false).unwrap();
+        assert!(!result.applied);
+        assert!(result.files[0].error.is_none());
+        assert!(!result.files[1].hunks[0].applied);
+        assert_eq!(
+            fs::read_to_string(root.join("b.txt")).unwrap(),
+            "alpha\nbeta\n"
+        );
+    }
+
+    #[test]
+    fn test_write_failure_rolls_back() {
+        let (_dir, root) = project();
+        // b.txt is a file, so writing beneath it fails after a.txt was replaced
+        let writes = vec![
+            PlannedWrite {
+                target: root.join("a.txt"),
+                content: Some("changed\n".to_string()),
+            },
+            PlannedWrite {
+                target: root.join("new/deep/new.txt"),
+                content: Some("created\n".to_string()),
+            },
+            PlannedWrite {
+                target: root.join("b.txt/inner.txt"),
+                content: Some("x".to_string()),
+            },
+        ];
+
+        let (index, error) = write_all(&writes).unwrap_err();
+        assert_eq!(index, 2);
+        assert!(error.message.contains("rolled back"));
+        assert_eq!(
+            fs::read_to_string(root.join("a.txt")).unwrap(),
+            "one\ntwo\nthree\nfour\n"
+        );
+        assert!(!root.join("new").exists());
+    }
+
+    #[test]
+    fn test_search_replace_requires_unique_match() {
+        let (_dir, root) = project();
+        fs::write(root.join("dup.txt"), "x = 1\nx = 1\n").unwrap();
+        let edit = SearchReplaceEdit {
+            path: "dup.txt".to_string(),
+            search: "x = 1".to_string(),
+            replace: "x = 2".to_string(),
+            replace_all: false,
+        };
+
+        let result = apply_search_replace(&root, std::slice::from_ref(&edit), false).unwrap();
+        assert!(!result.applied);
+        assert!(result.files[0].hunks[0]
+            .message
+            .as_deref()
+            .unwrap()
+            .contains("ambiguous"));
+
+        let all = SearchReplaceEdit {
+            replace_all: true,
+            ..edit
+        };
+        assert!(apply_search_replace(&root, &[all], false).unwrap().applied);
+        assert_eq!(
+            fs::read_to_string(root.join("dup.txt")).unwrap(),
+            "x = 2\nx = 2\n"
+        );
+    }
+}
@@ -0,0 +1,350 @@
+// Project Search — R20-02
+// Literal/regex search across the workspace with glob filters and cancellation
+
+use ignore::overrides::OverrideBuilder;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::error::{AppError, AppResult};
+use crate::workspace::{self, to_slash_path};
+
+pub const DEFAULT_MAX_RESULTS: usize = 2000;
+const BATCH_SIZE: usize = 50;
+const MAX_FILE_SIZE: u64 = 20 * 1024 * 1024;
+const MAX_PREVIEW_CHARS: usize = 240;
+const BINARY_SNIFF_BYTES: usize = 8192;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SearchQuery {
+    pub query: String,
+    #[serde(default)]
+    pub is_regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Globs a file must match, e.g. `src/**/*.rs`
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Globs that exclude a file, e.g. `**/*.test.ts`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    pub max_results: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchMatch {
+    /// Path relative to the workspace root, `/`-separated
+    pub path: String,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, in characters
+    pub column: usize,
+    pub match_length: usize,
+    pub preview: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchMatchBatch {
+    pub search_id: String,
+    pub matches: Vec<SearchMatch>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SearchSummary {
+    pub search_id: String,
+    pub total_matches: usize,
+    pub files_searched: usize,
+    pub truncated: bool,
+    pub cancelled: bool,
+    pub error: Option<AppError>,
+}
+
+/// Tracks cancellation flags for searches that are still running
+pub struct SearchManager {
+    active: HashMap<String, Arc<AtomicBool>>,
+}
+
+impl SearchManager {
+    pub fn new() -> Self {
+        Self {
+            active: HashMap::new(),
+        }
+    }
+
+    /// Register a new search and return its id and cancellation flag
+    pub fn start(&mut self) -> (String, Arc<AtomicBool>) {
+        let id = uuid::Uuid::new_v4().to_string();
+        let flag = Arc::new(AtomicBool::new(false));
+        self.active.insert(id.clone(), flag.clone());
+        (id, flag)
+    }
+
+    /// Request cancellation of a running search
+    pub fn cancel(&mut self, search_id: &str) -> AppResult<()> {
+        match self.active.get(search_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                Ok(())
+            }
+            None => Err(AppError::not_found(format!(
+                "Unknown or finished search: {}",
+                search_id
+            ))),
+        }
+    }
+
+    /// Forget a search once it has completed
+    pub fn finish(&mut self, search_id: &str) {
+        self.active.remove(search_id);
+    }
+}
+
+impl Default for SearchManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Search every non-ignored text file under `root`, handing matches to
+/// `on_batch` as they are found. Stops early on `cancel` or `max_results`.
+pub fn search(
+    root: &Path,
+    query: &SearchQuery,
+    cancel: &AtomicBool,
+    mut on_batch: impl FnMut(Vec<SearchMatch>),
+) -> AppResult<SearchSummary> {
+    if query.query.is_empty() {
+        return Err(AppError::invalid_input("Search query is empty"));
+    }
+
+    let pattern = build_pattern(query)?;
+    let max_results = query.max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in &query.include {
+        overrides.add(glob).map_err(|e| {
+            AppError::invalid_input(format!("Invalid include glob '{}': {}", glob, e))
+        })?;
+    }
+    for glob in &query.exclude {
+        overrides.add(&format!("!{}", glob)).map_err(|e| {
+            AppError::invalid_input(format!("Invalid exclude glob '{}': {}", glob, e))
+        })?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| AppError::invalid_input(format!("Invalid search globs: {}", e)))?;
+
+    let mut walker = workspace::project_walker(root);
+    walker.overrides(overrides).max_filesize(Some(MAX_FILE_SIZE));
+
+    let mut summary = SearchSummary::default();
+    let mut batch = Vec::new();
+
+    'files: for entry in walker.build() {
+        if cancel.load(Ordering::Relaxed) {
+            summary.cancelled = true;
+            break;
+        }
+        let entry = match entry {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+        if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
+            continue;
+        }
+
+        let rel = to_slash_path(entry.path().strip_prefix(root).unwrap_or(entry.path()));
+        let mut reader = match open_text_file(entry.path()) {
+            Some(r) => r,
+            None => continue,
+        };
+        summary.files_searched += 1;
+
+        let mut buf = Vec::new();
+        let mut line_no = 0;
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            line_no += 1;
+
+            let line = String::from_utf8_lossy(&buf);
+            let line = line.trim_end_matches(['\n', '\r']);
+            for m in pattern.find_iter(line) {
+                if summary.total_matches >= max_results {
+                    summary.truncated = true;
+                    break 'files;
+                }
+                summary.total_matches += 1;
+                batch.push(SearchMatch {
+                    path: rel.clone(),
+                    line: line_no,
+                    column: line[..m.start()].chars().count() + 1,
+                    match_length: m.as_str().chars().count(),
+                    preview: preview(line, m.start()),
+                });
+            }
+
+            if batch.len() >= BATCH_SIZE {
+                on_batch(std::mem::take(&mut batch));
+                if cancel.load(Ordering::Relaxed) {
+                    summary.cancelled = true;
+                    break 'files;
+                }
+            }
+        }
+    }
+
+    if !batch.is_empty() {
+        on_batch(batch);
+    }
+
+    Ok(summary)
+}
+
+fn build_pattern(query: &SearchQuery) -> AppResult<Regex> {
+    let source = if query.is_regex {
+        query.query.clone()
+    } else {
+        regex::escape(&query.query)
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(!query.case_sensitive)
+        .build()
+        .map_err(|e| AppError::invalid_input(format!("Invalid search pattern: {}", e)))
+}
+
+/// Open a file for line reading, or `None` if it is unreadable or binary
+fn open_text_file(path: &Path) -> Option<BufReader<File>> {
+    let mut file = File::open(path).ok()?;
+    let mut head = vec![0; BINARY_SNIFF_BYTES];
+    let n = file.read(&mut head).ok()?;
+    if head[..n].contains(&0) {
+        return None;
+    }
+    drop(file);
+    File::open(path).ok().map(BufReader::new)
+}
+
+/// Trim long lines to a window around the match
+fn preview(line: &str, match_start: usize) -> String {
+    if line.chars().count() <= MAX_PREVIEW_CHARS {
+        return line.to_string();
+    }
+    let before = line[..match_start].chars().count();
+    let skip = before.saturating_sub(MAX_PREVIEW_CHARS / 4);
+    line.chars().skip(skip).take(MAX_PREVIEW_CHARS).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn project() -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "fn alpha() {}\nfn Beta() { alpha(); }\n").unwrap();
+        fs::write(root.join("src/lib.test.rs"), "alpha\n").unwrap();
+        fs::write(root.join("notes.md"), "ALPHA notes\n").unwrap();
+        fs::write(root.join("blob.bin"), b"alpha\0\x01").unwrap();
+        (dir, root)
+    }
+
+    fn run(root: &Path, query: SearchQuery) -> (Vec<SearchMatch>, SearchSummary) {
+        let mut matches = Vec::new();
+        let summary = search(root, &query, &AtomicBool::new(false), |b| matches.extend(b)).unwrap();
+        matches.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+        (matches, summary)
+    }
+
+    #[test]
+    fn test_search_literal_case_and_globs() {
+        let (_dir, root) = project();
+        let (matches, _) = run(
+            &root,
+            SearchQuery {
+                query: "alpha".to_string(),
+                case_sensitive: true,
+                include: vec!["src/**".to_string()],
+                exclude: vec!["*.test.rs".to_string()],
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[1].path, "src/lib.rs");
+        assert_eq!((matches[1].line, matches[1].column), (2, 13));
+
+        let (insensitive, _) = run(
+            &root,
+            SearchQuery {
+                query: "alpha".to_string(),
+                ..Default::default()
+            },
+        );
+        // Binary file is skipped, notes.md matches case-insensitively
+        assert_eq!(insensitive.len(), 4);
+    }
+
+    #[test]
+    fn test_search_regex_and_limits() {
+        let (_dir, root) = project();
+        let (matches, summary) = run(
+            &root,
+            SearchQuery {
+                query: r"fn \w+\(".to_string(),
+                is_regex: true,
+                case_sensitive: true,
+                max_results: Some(1),
+                ..Default::default()
+            },
+        );
+        assert_eq!(matches.len(), 1);
+        assert!(summary.truncated);
+
+        assert!(search(
+            &root,
+            &SearchQuery {
+                query: "(".to_string(),
+                is_regex: true,
+                ..Default::default()
+            },
+            &AtomicBool::new(false),
+            |_| {}
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_search_cancelled() {
+        let (_dir, root) = project();
+        let mut manager = SearchManager::new();
+        let (id, flag) = manager.start();
+        manager.cancel(&id).unwrap();
+
+        let summary = search(
+            &root,
+            &SearchQuery {
+                query: "alpha".to_string(),
+                ..Default::default()
+            },
+            &flag,
+            |_| panic!("no matches expected after cancellation"),
+        )
+        .unwrap();
+        assert!(summary.cancelled);
+
+        manager.finish(&id);
+        assert!(manager.cancel(&id).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+// Trash — R20-02
+// App-managed recycle bin under .nova/trash so deletes can be listed and undone
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{AppError, AppResult};
+use crate::file_ops::{self, WriteExpectation};
+use crate::git;
+
+const TRASH_DIR: &str = ".nova/trash";
+const META_FILE: &str = "meta.json";
+const PAYLOAD: &str = "payload";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrashEntry {
+    pub id: String,
+    /// Workspace-relative path the item was deleted from
+    pub original_path: String,
+    pub deleted_at: i64,
+    pub is_dir: bool,
+}
+
+/// Move `path` (at `rel_path` inside `root`) into the trash
+pub fn move_to_trash(root: &Path, path: &Path, rel_path: &str) -> AppResult<TrashEntry> {
+    let metadata = fs::symlink_metadata(path)
+        .map_err(|e| AppError::io("Path does not exist", e).with_path(rel_path))?;
+
+    let entry = TrashEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        original_path: rel_path.to_string(),
+        deleted_at: chrono::Utc::now().timestamp_millis(),
+        is_dir: metadata.is_dir(),
+    };
+
+    if !root.join(TRASH_DIR).exists() {
+        git::exclude_nova_dir_at(root);
+    }
+    let slot = slot_dir(root, &entry.id);
+    fs::create_dir_all(&slot).map_err(|e| AppError::io("Failed to create trash slot", e))?;
+    write_meta(&slot, &entry)?;
+
+    // The trash lives inside the workspace, so this is a cheap same-filesystem rename
+    if let Err(e) = fs::rename(path, slot.join(PAYLOAD)) {
+        let _ = fs::remove_dir_all(&slot);
+        return Err(AppError::io("Failed to move to trash", e).with_path(rel_path));
+    }
+
+    Ok(entry)
+}
+
+/// Everything currently in the trash, most recently deleted first
+pub fn list(root: &Path) -> AppResult<Vec<TrashEntry>> {
+    let dir = root.join(TRASH_DIR);
+    let mut entries = Vec::new();
+
+    for slot in fs::read_dir(&dir).into_iter().flatten().flatten() {
+        let meta = slot.path().join(META_FILE);
+        if let Ok(content) = fs::read_to_string(&meta) {
+            if let Ok(entry) = serde_json::from_str::<TrashEntry>(&content) {
+                entries.push(entry);
+            }
+        }
+    }
+
+    entries.sort_by_key(|e| std::cmp::Reverse(e.deleted_at));
+    Ok(entries)
+}
+
+/// Put a trashed item back where it came from, or at `target` if given.
+/// Returns the entry and the absolute path it was restored to.
+pub fn restore(root: &Path, id: &str, target: Option<&Path>) -> AppResult<(TrashEntry, PathBuf)> {
+    let slot = slot_dir(root, id);
+    let entry = read_meta(&slot)?;
+    let target = match target {
+        Some(t) => t.to_path_buf(),
+        None => crate::workspace::resolve_within(root, Path::new(&entry.original_path))?,
+    };
+
+    file_ops::move_path(&slot.join(PAYLOAD), &target)?;
+    let _ = fs::remove_dir_all(&slot);
+    Ok((entry, target))
+}
+
+/// Permanently delete every trashed item; returns how many were removed
+pub fn empty(root: &Path) -> AppResult<usize> {
+    let entries = list(root)?;
+    for entry in &entries {
+        fs::remove_dir_all(slot_dir(root, &entry.id))
+            .map_err(|e| AppError::io("Failed to empty trash", e))?;
+    }
+    Ok(entries.len())
+}
+
+fn slot_dir(root: &Path, id: &str) -> PathBuf {
+    // Ids come from the webview; keep them from naming anything but a slot
+    let safe: String = id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect();
+    root.join(TRASH_DIR).join(safe)
+}
+
+fn read_meta(slot: &Path) -> AppResult<TrashEntry> {
+    let content = fs::read_to_string(slot.join(META_FILE))
+        .map_err(|_| AppError::not_found("Trash item not found"))?;
+    serde_json::from_str(&content)
+        .map_err(|e| AppError::internal(format!("Failed to parse trash entry: {}", e)))
+}
+
+fn write_meta(slot: &Path, entry: &TrashEntry) -> AppResult<()> {
+    let content = serde_json::to_string_pretty(entry)
+        .map_err(|e| AppError::internal(format!("Failed to serialize trash entry: {}", e)))?;
+    file_ops::atomic_write(
+        &slot.join(META_FILE),
+        content.as_bytes(),
+        &WriteExpectation::default(),
+    )
+    .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trash_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "pub fn x() {}").unwrap();
+
+        let entry = move_to_trash(&root, &root.join("src/lib.rs"), "src/lib.rs").unwrap();
+        assert!(!root.join("src/lib.rs").exists());
+        assert_eq!(list(&root).unwrap(), vec![entry.clone()]);
+
+        let (restored, path) = restore(&root, &entry.id, None).unwrap();
+        assert_eq!(restored.original_path, "src/lib.rs");
+        assert_eq!(path, root.join("src/lib.rs"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "pub fn x() {}");
+        assert!(list(&root).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_restore_refuses_to_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::write(root.join("a.txt"), "old").unwrap();
+
+        let repo = git2::Repository::init(&root).unwrap();
+        let entry = move_to_trash(&root, &root.join("a.txt"), "a.txt").unwrap();
+        let mut options = git2::StatusOptions::new();
+        options.include_untracked(true);
+        assert!(repo.statuses(Some(&mut options)).unwrap().is_empty());
+        fs::write(root.join("a.txt"), "new").unwrap();
+        assert!(restore(&root, &entry.id, None).is_err());
+        assert_eq!(list(&root).unwrap().len(), 1);
+
+        assert_eq!(empty(&root).unwrap(), 1);
+        assert!(list(&root).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,388 @@
+// Workspace Sandbox — R20-02
+// Registered project root, path canonicalization and escape rejection
+
+use ignore::WalkBuilder;
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Project-specific ignore file, read alongside `.gitignore`
+pub const NOVA_IGNORE_FILE: &str = ".novaignore";
+
+/// Directories that belong to git or the app itself and never count as project content
+pub const INTERNAL_DIRS: [&str; 2] = [".git", ".nova"];
+
+/// Where per-agent git worktrees are checked out, relative to the root
+pub const WORKTREES_DIR: &str = ".nova/worktrees";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkspaceError {
+    NoWorkspace,
+    InvalidRoot(String),
+    OutsideRoot(String),
+    Protected(String),
+    UnknownWorktree(String),
+    Io(String),
+}
+
+impl fmt::Display for WorkspaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkspaceError::NoWorkspace => write!(f, "No workspace root is registered"),
+            WorkspaceError::InvalidRoot(msg) => write!(f, "Invalid workspace root: {}", msg),
+            WorkspaceError::OutsideRoot(path) => {
+                write!(f, "Path is outside the workspace root: {}", path)
+            }
+            WorkspaceError::Protected(path) => {
+                write!(f, "Path cannot be moved or deleted: {}", path)
+            }
+            WorkspaceError::UnknownWorktree(name) => write!(f, "No worktree named '{}'", name),
+            WorkspaceError::Io(msg) => write!(f, "Failed to resolve path: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for WorkspaceError {}
+
+pub struct Workspace {
+    root: Option<PathBuf>,
+}
+
+impl Workspace {
+    pub fn new() -> Self {
+        Self { root: None }
+    }
+
+    /// Register the project root every file command is resolved against
+    pub fn set_root(&mut self, path: &str) -> Result<PathBuf, WorkspaceError> {
+        let root = fs::canonicalize(path)
+            .map_err(|e| WorkspaceError::InvalidRoot(format!("{}: {}", path, e)))?;
+
+        if !root.is_dir() {
+            return Err(WorkspaceError::InvalidRoot(format!(
+                "{} is not a directory",
+                path
+            )));
+        }
+
+        self.root = Some(root.clone());
+        Ok(root)
+    }
+
+    /// Get the registered root
+    pub fn root(&self) -> Result<&Path, WorkspaceError> {
+        self.root.as_deref().ok_or(WorkspaceError::NoWorkspace)
+    }
+
+    /// Resolve a relative or absolute path to a canonical path inside the root
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, WorkspaceError> {
+        self.resolve_in(None, path)
+    }
+
+    /// Resolve a path that is about to be moved, copied or deleted. The root
+    /// itself and the `.git`/`.nova` directories are refused.
+    pub fn resolve_entry(&self, path: &str) -> Result<PathBuf, WorkspaceError> {
+        self.resolve_entry_in(None, path)
+    }
+
+    /// Checkout directory of the named agent worktree
+    pub fn worktree_root(&self, name: &str) -> Result<PathBuf, WorkspaceError> {
+        let unknown = || WorkspaceError::UnknownWorktree(name.to_string());
+        let mut components = Path::new(name).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => {}
+            _ => return Err(unknown()),
+        }
+        let dir = self.root()?.join(WORKTREES_DIR).join(name);
+        if !dir.is_dir() {
+            return Err(unknown());
+        }
+        fs::canonicalize(&dir).map_err(|e| WorkspaceError::Io(e.to_string()))
+    }
+
+    /// The directory file commands are scoped to: a worktree, or the root for `None`
+    pub fn scope_root(&self, worktree: Option<&str>) -> Result<PathBuf, WorkspaceError> {
+        match worktree {
+            Some(name) => self.worktree_root(name),
+            None => Ok(self.root()?.to_path_buf()),
+        }
+    }
+
+    /// Like `resolve`, with relative paths taken from a worktree's checkout
+    pub fn resolve_in(
+        &self,
+        worktree: Option<&str>,
+        path: &str,
+    ) -> Result<PathBuf, WorkspaceError> {
+        resolve_within(&self.scope_root(worktree)?, Path::new(path))
+    }
+
+    /// Like `resolve_entry`, protecting the worktree's own root and `.git` instead
+    pub fn resolve_entry_in(
+        &self,
+        worktree: Option<&str>,
+        path: &str,
+    ) -> Result<PathBuf, WorkspaceError> {
+        let scope = self.scope_root(worktree)?;
+        let resolved = resolve_entry_within(&scope, Path::new(path))?;
+        if resolved == scope || is_internal_path(&scope, &resolved) {
+            return Err(WorkspaceError::Protected(path.to_string()));
+        }
+        Ok(resolved)
+    }
+
+    /// Express a resolved path relative to the root, using `/` separators
+    pub fn relative_path(&self, path: &Path) -> Result<String, WorkspaceError> {
+        let root = self.root()?;
+        path.strip_prefix(root)
+            .map(to_slash_path)
+            .map_err(|_| WorkspaceError::OutsideRoot(path.display().to_string()))
+    }
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether a path lies in `.git` or `.nova` under `root`
+pub fn is_internal_path(root: &Path, path: &Path) -> bool {
+    path.strip_prefix(root)
+        .ok()
+        .and_then(|rel| rel.components().next())
+        .map(|first| INTERNAL_DIRS.iter().any(|d| first.as_os_str() == *d))
+        .unwrap_or(false)
+}
+
+/// Walker over project content starting at `start`, honoring `.gitignore`
+/// (even outside a git repo) and `.novaignore`, and skipping `.git`/`.nova`
+pub fn project_walker(start: &Path) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(start);
+    builder
+        .hidden(false)
+        .require_git(false)
+        .add_custom_ignore_filename(NOVA_IGNORE_FILE)
+        .filter_entry(|entry| {
+            !(entry.depth() > 0
+                && entry.file_type().map(|t| t.is_dir()).unwrap_or(false)
+                && INTERNAL_DIRS.iter().any(|d| entry.file_name() == *d))
+        });
+    builder
+}
+
+/// Resolve `path` against a canonical `root`, rejecting anything that escapes it
+/// lexically (`..`) or through symlinks. The path does not need to exist yet.
+pub fn resolve_within(root: &Path, path: &Path) -> Result<PathBuf, WorkspaceError> {
+    let outside = || WorkspaceError::OutsideRoot(path.display().to_string());
+
+    let joined = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        root.join(path)
+    };
+
+    let normalized = normalize_lexically(&joined).ok_or_else(outside)?;
+    if !normalized.starts_with(root) {
+        return Err(outside());
+    }
+
+    // Canonicalize the deepest existing ancestor so symlinks along the way are
+    // followed, then re-append the components that do not exist yet.
+    let mut existing = normalized.as_path();
+    let mut missing = Vec::new();
+    loop {
+        match fs::symlink_metadata(existing) {
+            Ok(_) => break,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let name = existing.file_name().ok_or_else(outside)?;
+                missing.push(name.to_os_string());
+                existing = existing.parent().ok_or_else(outside)?;
+            }
+            Err(e) => return Err(WorkspaceError::Io(e.to_string())),
+        }
+    }
+
+    // A dangling symlink exists but cannot be canonicalized; its target is
+    // unknown, so it is treated as an escape rather than written through.
+    let mut resolved = fs::canonicalize(existing).map_err(|_| outside())?;
+    for name in missing.into_iter().rev() {
+        resolved.push(name);
+    }
+
+    if !resolved.starts_with(root) {
+        return Err(outside());
+    }
+
+    Ok(resolved)
+}
+
+/// Like `resolve_within`, but a symlink in the final component is not
+/// followed, so moving, copying or deleting it acts on the link itself
+pub fn resolve_entry_within(root: &Path, path: &Path) -> Result<PathBuf, WorkspaceError> {
+    let outside = || WorkspaceError::OutsideRoot(path.display().to_string());
+
+    let joined = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        root.join(path)
+    };
+    let normalized = normalize_lexically(&joined).ok_or_else(outside)?;
+    if !normalized.starts_with(root) {
+        return Err(outside());
+    }
+    if normalized == root {
+        return Ok(normalized);
+    }
+
+    let name = normalized.file_name().ok_or_else(outside)?;
+    let parent = normalized.parent().ok_or_else(outside)?;
+    let parent = resolve_within(root, parent).map_err(|_| outside())?;
+    Ok(parent.join(name))
+}
+
+/// Collapse `.` and `..` without touching the filesystem.
+/// Returns `None` if `..` would climb above the filesystem root.
+fn normalize_lexically(path: &Path) -> Option<PathBuf> {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => out.push(component.as_os_str()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() || out.as_os_str().is_empty() {
+                    return None;
+                }
+            }
+            Component::Normal(name) => out.push(name),
+        }
+    }
+    Some(out)
+}
+
+pub(crate) fn to_slash_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace() -> (tempfile::TempDir, Workspace) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ws = Workspace::new();
+        ws.set_root(dir.path().to_str().unwrap()).unwrap();
+        (dir, ws)
+    }
+
+    #[test]
+    fn test_resolve_requires_root() {
+        let ws = Workspace::new();
+        assert_eq!(ws.resolve("a.txt"), Err(WorkspaceError::NoWorkspace));
+    }
+
+    #[test]
+    fn test_resolve_relative_and_missing_paths() {
+        let (_dir, ws) = workspace();
+        let resolved = ws.resolve("src/new/file.rs").unwrap();
+        assert!(resolved.starts_with(ws.root().unwrap()));
+        assert_eq!(ws.relative_path(&resolved).unwrap(), "src/new/file.rs");
+    }
+
+    #[test]
+    fn test_resolve_rejects_parent_escape() {
+        let (_dir, ws) = workspace();
+        assert!(ws.resolve("src/../notes.md").is_ok());
+        assert!(matches!(
+            ws.resolve("../outside.txt"),
+            Err(WorkspaceError::OutsideRoot(_))
+        ));
+        assert!(matches!(
+            ws.resolve("/etc/shadow"),
+            Err(WorkspaceError::OutsideRoot(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_entry_protects_root_and_internal_dirs() {
+        let (_dir, ws) = workspace();
+        assert!(ws.resolve_entry("src/main.rs").is_ok());
+        for path in [".", ".git/config", ".nova/history"] {
+            assert!(matches!(
+                ws.resolve_entry(path),
+                Err(WorkspaceError::Protected(_))
+            ));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_rejects_symlink_escape() {
+        let (dir, ws) = workspace();
+        let outside = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+        std::os::unix::fs::symlink("/nonexistent/target", dir.path().join("dangling")).unwrap();
+
+        assert!(matches!(
+            ws.resolve("link/secret.txt"),
+            Err(WorkspaceError::OutsideRoot(_))
+        ));
+        assert!(matches!(
+            ws.resolve("dangling"),
+            Err(WorkspaceError::OutsideRoot(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_entry_keeps_final_symlink() {
+        let (dir, ws) = workspace();
+        let root = ws.root().unwrap().to_path_buf();
+        let outside = tempfile::tempdir().unwrap();
+        fs::create_dir(root.join("src")).unwrap();
+        std::os::unix::fs::symlink(root.join("src"), dir.path().join("lib")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("ext")).unwrap();
+
+        assert_eq!(ws.resolve_entry("lib").unwrap(), root.join("lib"));
+        assert_eq!(ws.resolve_entry("./ext").unwrap(), root.join("ext"));
+        // Links along the way are still followed and checked
+        assert_eq!(ws.resolve_entry("lib/a.rs").unwrap(), root.join("src/a.rs"));
+        assert!(matches!(
+            ws.resolve_entry("ext/secret.txt"),
+            Err(WorkspaceError::OutsideRoot(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_in_worktree() {
+        let (_dir, ws) = workspace();
+        let root = ws.root().unwrap().to_path_buf();
+        fs::create_dir_all(root.join(WORKTREES_DIR).join("agent-1/src")).unwrap();
+
+        let resolved = ws.resolve_in(Some("agent-1"), "src/lib.rs").unwrap();
+        assert_eq!(resolved, root.join(".nova/worktrees/agent-1/src/lib.rs"));
+        // History and trash stay keyed on the workspace root
+        assert_eq!(
+            ws.relative_path(&resolved).unwrap(),
+            ".nova/worktrees/agent-1/src/lib.rs"
+        );
+        assert!(ws.resolve_entry_in(Some("agent-1"), "src/lib.rs").is_ok());
+        assert!(matches!(
+            ws.resolve_entry_in(Some("agent-1"), ".git"),
+            Err(WorkspaceError::Protected(_))
+        ));
+        assert!(matches!(
+            ws.resolve_in(Some("agent-1"), "../agent-2/x"),
+            Err(WorkspaceError::OutsideRoot(_))
+        ));
+        for name in ["missing", "../agent-1", "agent-1/src"] {
+            assert!(matches!(
+                ws.resolve_in(Some(name), "a.txt"),
+                Err(WorkspaceError::UnknownWorktree(_))
+            ));
+        }
+    }
+}